@@ -0,0 +1,351 @@
+//! Expansion of `query_as!`, which checks a query against the schema QuestDB reports for it
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Ident, LitStr, Path, Token};
+
+/// Arguments of `query_as!`: the row type, the connection and the query
+pub(crate) struct QueryAsInput {
+    row: Path,
+    connection: Expr,
+    query: LitStr,
+}
+
+impl Parse for QueryAsInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let row = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let connection = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let query = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(QueryAsInput {
+            row,
+            connection,
+            query,
+        })
+    }
+}
+
+/// Column of the result as reported in the metadata of /exec
+struct Column {
+    name: String,
+    column_type: String,
+}
+
+pub(crate) fn expand(input: QueryAsInput) -> syn::Result<TokenStream> {
+    let query = input.query.value();
+    let span = input.query.span();
+    let columns = describe(&query).map_err(|e| syn::Error::new(span, e))?;
+
+    let mut fields = Vec::new();
+    for (index, column) in columns.iter().enumerate() {
+        let field = syn::parse_str::<Ident>(&column.name).map_err(|_| {
+            syn::Error::new(
+                span,
+                format!("column `{}` is not a field name, alias it", column.name),
+            )
+        })?;
+        let field = Ident::new(&field.to_string(), span);
+        let source = rust_type(&column.column_type);
+        let name = &column.name;
+        fields.push(quote! {
+            #field: ::questdb::__private::column::<#source, _>(row, #index, #name)?
+        });
+    }
+
+    let row = &input.row;
+    let connection = &input.connection;
+    Ok(quote! {
+        ::questdb::__private::query_as(&#connection, #query, |row| {
+            ::core::result::Result::Ok(#row { #(#fields),* })
+        })
+    })
+}
+
+/// Type a column is read as, nullable types are read as an `Option`
+fn rust_type(column_type: &str) -> TokenStream {
+    match column_type.to_ascii_uppercase().as_str() {
+        "BOOLEAN" => quote! { bool },
+        "BYTE" => quote! { i8 },
+        "SHORT" => quote! { i16 },
+        "CHAR" => quote! { ::core::option::Option<char> },
+        "INT" => quote! { ::core::option::Option<i32> },
+        "LONG" => quote! { ::core::option::Option<i64> },
+        "FLOAT" => quote! { ::core::option::Option<f32> },
+        "DOUBLE" => quote! { ::core::option::Option<f64> },
+        "DATE" | "TIMESTAMP" => quote! { ::core::option::Option<::questdb::Timestamp> },
+        "STRING" | "SYMBOL" | "VARCHAR" | "UUID" | "IPV4" | "LONG256" => {
+            quote! { ::core::option::Option<::std::string::String> }
+        }
+        _ => quote! { ::questdb::__private::Value },
+    }
+}
+
+/// Columns of the query, from the server at `QUESTDB_URL` when it is set or from the cache
+/// written by an earlier build otherwise
+fn describe(query: &str) -> Result<Vec<Column>, String> {
+    // The query runs on the server while building, it must not change anything
+    if !is_read_only(query) {
+        return Err(String::from(
+            "only SELECT, SHOW and EXPLAIN queries can be checked",
+        ));
+    }
+
+    let cache = cache_path(query);
+    let response = match std::env::var("QUESTDB_URL") {
+        Ok(url) => {
+            let (status, response) = fetch(&url, query)?;
+            if status != 200 {
+                let error = serde_json::from_str::<serde_json::Value>(&response)
+                    .ok()
+                    .and_then(|r| r.get("error").and_then(|e| e.as_str()).map(String::from));
+                return Err(match error {
+                    Some(error) => format!("QuestDB rejected the query: {}", error),
+                    None => format!("{} answered with status {}", url, status),
+                });
+            }
+            // Best effort, builds without a server report the missing cache
+            if let Some(dir) = cache.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            let _ = std::fs::write(&cache, &response);
+            response
+        }
+        Err(_) => std::fs::read_to_string(&cache).map_err(|_| {
+            format!(
+                "no cached schema at {}, build once with QUESTDB_URL pointing to a server",
+                cache.display()
+            )
+        })?,
+    };
+
+    let response: serde_json::Value =
+        serde_json::from_str(&response).map_err(|e| format!("invalid response: {}", e))?;
+    if let Some(error) = response.get("error").and_then(|e| e.as_str()) {
+        return Err(format!("QuestDB rejected the query: {}", error));
+    }
+    let columns = response
+        .get("columns")
+        .and_then(|c| c.as_array())
+        .ok_or("the query returns no rows")?;
+
+    Ok(columns
+        .iter()
+        .map(|c| Column {
+            name: String::from(c["name"].as_str().unwrap_or_default()),
+            column_type: String::from(c["type"].as_str().unwrap_or_default()),
+        })
+        .collect())
+}
+
+/// File caching the response for a query, in `QUESTDB_QUERY_CACHE` or `.questdb` next to the
+/// manifest of the crate being built
+fn cache_path(query: &str) -> PathBuf {
+    let dir = match std::env::var_os("QUESTDB_QUERY_CACHE") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default())
+            .join(".questdb"),
+    };
+    dir.join(format!("query-{:016x}.json", fnv1a(query.as_bytes())))
+}
+
+/// Hash that doesn't change between compiler versions, unlike the one of the standard library
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Whether the query only reads, like `questdb::sql::classify(query).is_read_only()` which this
+/// crate can't depend on. Common table expressions are classified by the statement following
+/// them.
+fn is_read_only(query: &str) -> bool {
+    const STATEMENTS: [&str; 11] = [
+        "SELECT", "SHOW", "EXPLAIN", "INSERT", "UPDATE", "CREATE", "ALTER", "DROP", "TRUNCATE",
+        "RENAME", "COPY",
+    ];
+    let words = top_level_words(query);
+    let keyword = match words.split_first() {
+        Some((first, rest)) if first.eq_ignore_ascii_case("WITH") => rest
+            .iter()
+            .find(|w| STATEMENTS.iter().any(|s| w.eq_ignore_ascii_case(s))),
+        Some((first, _)) => Some(first),
+        None => None,
+    };
+    keyword.is_some_and(|k| {
+        ["SELECT", "SHOW", "EXPLAIN"]
+            .iter()
+            .any(|s| k.eq_ignore_ascii_case(s))
+    })
+}
+
+/// Words of the query outside of parentheses, comments and quotes. Parentheses wrapping the
+/// whole query are ignored.
+fn top_level_words(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let mut words = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            quote @ (b'\'' | b'"') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'(' => {
+                if !words.is_empty() || depth > 0 {
+                    depth += 1;
+                }
+                i += 1;
+            }
+            b')' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                if depth == 0 {
+                    words.push(&sql[start..i]);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    words
+}
+
+/// Asks the server for the columns of the query without reading any row, returns the status and
+/// the body of the response. Only plain http is supported.
+fn fetch(url: &str, query: &str) -> Result<(u16, String), String> {
+    let host = url
+        .strip_prefix("http://")
+        .ok_or("QUESTDB_URL must start with http://")?
+        .trim_end_matches('/');
+    let mut stream = TcpStream::connect(host).map_err(|e| format!("{}: {}", host, e))?;
+    let request = format!(
+        "GET /exec?query={}&limit=0 HTTP/1.0\r\nHost: {}\r\n\r\n",
+        encode(query),
+        host
+    );
+    let mut response = Vec::new();
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|_| stream.read_to_end(&mut response))
+        .map_err(|e| format!("{}: {}", host, e))?;
+
+    parse_response(&response).ok_or_else(|| format!("{}: invalid response", host))
+}
+
+/// Status and body of an HTTP/1 response, decoding chunked bodies
+fn parse_response(response: &[u8]) -> Option<(u16, String)> {
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&response[..split]).ok()?;
+    let body = &response[split + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines.next()?.split(' ').nth(1)?.parse().ok()?;
+    let chunked = lines.any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("transfer-encoding")
+                && value.to_ascii_lowercase().contains("chunked")
+        })
+    });
+    let body = match chunked {
+        true => decode_chunked(body)?,
+        false => body.to_vec(),
+    };
+    Some((status, String::from_utf8(body).ok()?))
+}
+
+/// Joins the chunks of a chunked body, ignoring chunk extensions and trailers
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    loop {
+        let end = body.windows(2).position(|w| w == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        body = &body[end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+/// Percent-encodes everything but unreserved characters
+fn encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                String::from(b as char)
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_read_only, parse_response};
+
+    #[test]
+    fn test_is_read_only() {
+        assert!(is_read_only("select * from readings"));
+        assert!(is_read_only("(SELECT 1)"));
+        assert!(is_read_only(
+            "-- latest\nwith t as (select 1) select * from t"
+        ));
+        assert!(is_read_only("show tables"));
+        assert!(!is_read_only("drop table readings"));
+        assert!(!is_read_only("insert into readings select * from t"));
+        assert!(!is_read_only(
+            "with t as (select 1) insert into readings select * from t"
+        ));
+        assert!(!is_read_only("update readings set temp = 0"));
+        assert!(!is_read_only(""));
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}";
+        assert_eq!(parse_response(response), Some((200, String::from("{}"))));
+
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            4\r\n{\"a\"\r\n3;ext=1\r\n:1}\r\n0\r\n\r\n";
+        assert_eq!(
+            parse_response(response),
+            Some((200, String::from("{\"a\":1}")))
+        );
+
+        let response = b"HTTP/1.1 502 Bad Gateway\r\n\r\n<html>";
+        assert_eq!(
+            parse_response(response),
+            Some((502, String::from("<html>")))
+        );
+        assert_eq!(parse_response(b"garbage"), None);
+    }
+}
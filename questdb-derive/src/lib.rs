@@ -0,0 +1,285 @@
+//! Derive macros for the questdb crate, use them through the `derive` feature of `questdb`.
+
+mod checked;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, Ident, LitStr, Token};
+
+/// What a field becomes in the row
+enum Role {
+    Symbol,
+    Column,
+    Timestamp,
+    Skip,
+}
+
+/// Implements `questdb::ingress::IlpRow` for a struct with named fields
+///
+/// The table is the struct name in snake case unless set with `#[ilp(table = "...")]`. Fields
+/// are columns by default, `#[ilp(symbol)]` makes a field a symbol, `#[ilp(timestamp)]` the
+/// designated timestamp and `#[ilp(skip)]` leaves it out. `#[ilp(rename = "...")]` sets the
+/// column name.
+#[proc_macro_derive(IlpRow, attributes(ilp))]
+pub fn derive_ilp_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut table = snake_case(&input.ident.to_string());
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("ilp")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("expected `table = \"...\"`"))
+            }
+        })?;
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "IlpRow needs named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "IlpRow needs a struct",
+            ))
+        }
+    };
+
+    let mut symbols = Vec::new();
+    let mut columns = Vec::new();
+    let mut timestamp = None;
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let mut role = Role::Column;
+        let mut name = ident.to_string();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("ilp")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("symbol") {
+                    role = Role::Symbol;
+                } else if meta.path.is_ident("timestamp") {
+                    role = Role::Timestamp;
+                } else if meta.path.is_ident("skip") {
+                    role = Role::Skip;
+                } else if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                } else {
+                    return Err(meta.error("expected `symbol`, `timestamp`, `skip` or `rename`"));
+                }
+                Ok(())
+            })?;
+        }
+        let name = LitStr::new(&name, Span::call_site());
+        match role {
+            Role::Symbol => symbols.push(quote! {
+                buffer.symbol(#name, ::core::convert::AsRef::<str>::as_ref(&self.#ident))?;
+            }),
+            Role::Column => columns.push(quote! {
+                ::questdb::ingress::IlpValue::write_column(&self.#ident, buffer, #name)?;
+            }),
+            Role::Timestamp if timestamp.is_some() => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "only one field can be the timestamp",
+                ))
+            }
+            Role::Timestamp => timestamp = Some(ident),
+            Role::Skip => {}
+        }
+    }
+
+    let at = match timestamp {
+        Some(ident) => quote! {
+            buffer.at(::core::convert::Into::<::questdb::Timestamp>::into(
+                ::core::clone::Clone::clone(&self.#ident),
+            ))
+        },
+        None => quote! { buffer.at_now() },
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::questdb::ingress::IlpRow for #ident #ty_generics #where_clause {
+            fn write_row(
+                &self,
+                buffer: &mut ::questdb::ingress::Buffer,
+            ) -> ::core::result::Result<(), ::questdb::Error> {
+                buffer.table(#table)?;
+                #(#symbols)*
+                #(#columns)*
+                #at
+            }
+        }
+    })
+}
+
+/// `SensorReading` becomes `sensor_reading`
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.char_indices() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Runs a query checked at build time into rows of a struct, the struct needs a field named
+/// after each column with a matching type
+///
+/// The columns and their types are asked from the QuestDB at `QUESTDB_URL` (plain http) and
+/// cached in `.questdb` next to the manifest, or in `QUESTDB_QUERY_CACHE`. Builds without
+/// `QUESTDB_URL` use the cache, so it can be committed for CI. Nullable columns are read as
+/// `Option`, a plain field fails at runtime on null.
+///
+/// Only `SELECT`, `SHOW` and `EXPLAIN` queries are accepted, since the query runs on the server
+/// during the build. No row is read.
+///
+/// ```ignore
+/// let rows: Vec<TestData> =
+///     query_as!(TestData, connection, "select id, ts, temp from readings").await?;
+/// ```
+#[proc_macro]
+pub fn query_as(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as checked::QueryAsInput);
+    match checked::expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Arguments of `sql!`: the connection and the query template
+struct SqlInput {
+    connection: Expr,
+    template: LitStr,
+}
+
+impl Parse for SqlInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let connection = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let template = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(SqlInput {
+            connection,
+            template,
+        })
+    }
+}
+
+/// Builds a `questdb::ExecRequest` from a query template naming variables in scope
+///
+/// `{name}` inserts the value of `name` as a literal, escaped like a parameter bound with
+/// `questdb::params!`. `{name:ident}` inserts it as a quoted table or column name. `{{` and
+/// `}}` stand for braces.
+///
+/// ```ignore
+/// let table = "readings";
+/// let id = 42;
+/// let rows: Vec<TestData> = sql!(connection, "select * from {table:ident} where id = {id}")
+///     .fetch()
+///     .await?;
+/// ```
+#[proc_macro]
+pub fn sql(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as SqlInput);
+    match expand_sql(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand_sql(input: SqlInput) -> syn::Result<proc_macro2::TokenStream> {
+    let template = input.template.value();
+    let span = input.template.span();
+    let error = |message: &str| syn::Error::new(span, message);
+
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' => return Err(error("unmatched `}`, use `}}` for a brace")),
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(error("unmatched `{`, use `{{` for a brace")),
+                    }
+                }
+                let (name, spec) = match placeholder.split_once(':') {
+                    Some((name, spec)) => (name.trim(), spec.trim()),
+                    None => (placeholder.trim(), ""),
+                };
+                let name = syn::parse_str::<Ident>(name)
+                    .map(|ident| Ident::new(&ident.to_string(), span))
+                    .map_err(|_| error(&format!("`{}` is not a variable name", name)))?;
+
+                let literal = LitStr::new(&std::mem::take(&mut text), span);
+                parts.push(quote! { sql.push_str(#literal); });
+                parts.push(match spec {
+                    "" => quote! {
+                        sql.push_str(&::std::string::ToString::to_string(
+                            &::questdb::sql::SqlValue::from(::core::clone::Clone::clone(&#name)),
+                        ));
+                    },
+                    "ident" => quote! {
+                        sql.push_str(&::questdb::sql::quote_ident(
+                            ::core::convert::AsRef::<str>::as_ref(&#name),
+                        ));
+                    },
+                    _ => {
+                        return Err(error(&format!(
+                            "unknown format `{}`, expected `ident`",
+                            spec
+                        )))
+                    }
+                });
+            }
+            c => text.push(c),
+        }
+    }
+    let literal = LitStr::new(&text, span);
+
+    let connection = &input.connection;
+    Ok(quote! {
+        ::questdb::QuestDB::query(&#connection, &{
+            let mut sql = ::std::string::String::new();
+            #(#parts)*
+            sql.push_str(#literal);
+            sql
+        })
+    })
+}
@@ -1,16 +1,132 @@
+use crate::auth::{BasicAuth, TokenState};
+use crate::balance::Balancer;
+use crate::breaker::CircuitBreaker;
+use crate::builder::QuestDBBuilder;
 use crate::error::SQLError;
+use crate::failover::{self, Failover, DEFAULT_PROBE_INTERVAL};
+use crate::hedge::{self, HedgeConfig};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::import::ImportResult;
+use crate::limit::Limits;
+use crate::metadata::Metadata;
+use crate::options::{ExecOptions, Limit, Priority};
+use crate::outcome::ExecOutcome;
+use crate::platform::{self, Instant};
+use crate::redact;
+use crate::retry::{self, RetryConfig};
+use crate::row::{self, QueryResult, Row};
+use crate::sql::{self, SqlValue};
+use crate::stale::StaleCache;
+use crate::stats::{QueryStats, SlowQueryHook, Tracker};
+#[cfg(not(target_arch = "wasm32"))]
 use crate::types::Atomicity;
 use crate::Error;
-use reqwest::Client;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
 use serde::de::DeserializeOwned;
-use std::fs::File;
-use std::io::{Read, Write};
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{BufWriter, Write};
+#[cfg(not(target_arch = "wasm32"))]
 use std::path::Path;
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::io::AsyncReadExt;
 use urlencoding::encode;
 
+/// Size of the buffer exports are written through
+#[cfg(not(target_arch = "wasm32"))]
+const EXPORT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Longest url sent by default, queries that don't fit are sent in the request body
+pub(crate) const DEFAULT_MAX_URL_LENGTH: usize = 8000;
+
+/// Value of the `User-Agent` header unless configured otherwise
+pub(crate) const USER_AGENT: &str = concat!("questdb-rs/", env!("CARGO_PKG_VERSION"));
+
+/// Connection to a QuestDB server
+///
+/// The connection is cheap to clone, clones share the same HTTP connection pool, configuration
+/// and limits. This makes it easy to keep in the state of a web application and hand a clone to
+/// every request handler.
+#[derive(Clone)]
 pub struct QuestDB {
-    client: Client,
-    url: String,
+    pub(crate) inner: Arc<Inner>,
+}
+
+impl From<Url> for QuestDB {
+    fn from(url: Url) -> Self {
+        // Paths are appended to the base url, which must not end with a slash
+        QuestDB::new(url.as_str().trim_end_matches('/'))
+    }
+}
+
+impl TryFrom<&str> for QuestDB {
+    type Error = Error;
+
+    /// Parses the url, failing with [`Error::InvalidUrl`] if it isn't a valid http(s) url
+    fn try_from(url: &str) -> Result<Self, Self::Error> {
+        let parsed = Url::parse(url).map_err(|e| Error::InvalidUrl(format!("{}: {}", url, e)))?;
+        match parsed.scheme() {
+            "http" | "https" => Ok(QuestDB::from(parsed)),
+            scheme => Err(Error::InvalidUrl(format!(
+                "{}: unsupported scheme '{}'",
+                url, scheme
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for QuestDB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = &self.inner;
+        f.debug_struct("QuestDB")
+            .field("url", &redact::url(&inner.url))
+            .field("auth", &inner.auth)
+            .field("basic_auth", &inner.basic_auth)
+            .field("read_only", &inner.read_only)
+            .field("retry", &inner.retry)
+            .field("hedge", &inner.hedge)
+            .field("breaker", &inner.breaker)
+            .field("failover", &inner.failover)
+            .field("balancer", &inner.balancer)
+            .field("limits", &inner.limits)
+            .field("slow_query", &inner.slow_query)
+            .field("max_url_length", &inner.max_url_length)
+            .finish()
+    }
+}
+
+impl fmt::Display for QuestDB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "QuestDB({})", redact::url(&self.inner.url))
+    }
+}
+
+/// State shared by all clones of a connection
+pub(crate) struct Inner {
+    pub(crate) client: Client,
+    pub(crate) url: String,
+    pub(crate) auth: TokenState,
+    pub(crate) basic_auth: Option<BasicAuth>,
+    pub(crate) read_only: bool,
+    pub(crate) retry: RetryConfig,
+    pub(crate) hedge: HedgeConfig,
+    pub(crate) breaker: Option<CircuitBreaker>,
+    pub(crate) failover: Failover,
+    pub(crate) balancer: Option<Balancer>,
+    pub(crate) limits: Limits,
+    pub(crate) slow_query: Option<SlowQueryHook>,
+    pub(crate) max_url_length: usize,
+    pub(crate) default_options: ExecOptions,
+    pub(crate) validate_writes: bool,
+    pub(crate) metadata: Metadata,
+    pub(crate) stale: Option<StaleCache>,
+    pub(crate) health_url: Option<String>,
+    pub(crate) metrics_url: Option<String>,
 }
 
 impl QuestDB {
@@ -22,26 +138,288 @@ impl QuestDB {
     /// let connection = QuestDB::new("http://192.168.1.37:9000");
     /// ```
     pub fn new(url: &str) -> Self {
-        QuestDB {
-            client: Client::new(),
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .build()
+            .expect("the default client can be built");
+        QuestDB::from_inner(Inner {
+            client,
             url: String::from(url),
+            auth: TokenState::default(),
+            basic_auth: None,
+            read_only: false,
+            retry: RetryConfig::default(),
+            hedge: HedgeConfig::default(),
+            breaker: None,
+            failover: Failover::new(url, Vec::new(), DEFAULT_PROBE_INTERVAL),
+            balancer: None,
+            limits: Limits::default(),
+            slow_query: None,
+            max_url_length: DEFAULT_MAX_URL_LENGTH,
+            default_options: ExecOptions::default(),
+            validate_writes: false,
+            metadata: Metadata::default(),
+            stale: None,
+            health_url: None,
+            metrics_url: None,
+        })
+    }
+
+    pub(crate) fn from_inner(inner: Inner) -> Self {
+        QuestDB {
+            inner: Arc::new(inner),
         }
     }
 
+    /// Creates a builder to configure the connection before creating it
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .refresh_endpoint("http://auth.internal/token")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder(url: &str) -> QuestDBBuilder {
+        QuestDBBuilder::new(url)
+    }
+
+    /// Rejects statements that could modify data when the connection is read-only
+    pub(crate) fn check_read_only(&self, query: &str) -> Result<(), Error> {
+        let kind = sql::classify(query);
+        if self.inner.read_only && !kind.is_read_only() {
+            return Err(Error::WriteForbidden(kind.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Reports the operation to the slow query hook
+    pub(crate) fn observe(&self, endpoint: &'static str, query: &str, stats: &QueryStats) {
+        if let Some(hook) = &self.inner.slow_query {
+            hook.observe(endpoint, query, stats);
+        }
+    }
+
+    /// Sends the request created by `build` for `path` on the server, failing over to the next
+    /// server when configured and the one in use is unreachable or unhealthy. Idempotent requests
+    /// go to a replica instead when reads are load balanced, and only fall back to the servers
+    /// when it fails.
+    pub(crate) async fn send<F>(
+        &self,
+        tracker: &Tracker,
+        idempotent: bool,
+        path: &str,
+        build: F,
+    ) -> Result<Response, Error>
+    where
+        F: Fn(&str) -> RequestBuilder,
+    {
+        if let Some(balancer) = self.inner.balancer.as_ref().filter(|_| idempotent) {
+            let (index, replica) = balancer.pick();
+            let started = Instant::now();
+            let res = self
+                .send_hedged(tracker, replica, idempotent, path, &build)
+                .await;
+            balancer.observe(index, started.elapsed(), hedge::succeeded(&res));
+            if !failover::should_fail_over(idempotent, &res) {
+                return res;
+            }
+        }
+
+        let failover = &self.inner.failover;
+        self.probe_primary();
+
+        let first = failover.current();
+        let mut index = first;
+        loop {
+            let base = failover.endpoint(index);
+            let res = self
+                .send_hedged(tracker, base, idempotent, path, &build)
+                .await;
+            match failover.next(first, index, idempotent, &res) {
+                Some(next) => index = next,
+                None => {
+                    if hedge::succeeded(&res) {
+                        failover.answered(index);
+                    }
+                    return res;
+                }
+            }
+        }
+    }
+
+    /// Sends the request to the server at `base`. Idempotent requests are hedged against a
+    /// replica other than `base` when configured.
+    async fn send_hedged<F>(
+        &self,
+        tracker: &Tracker,
+        base: &str,
+        idempotent: bool,
+        path: &str,
+        build: &F,
+    ) -> Result<Response, Error>
+    where
+        F: Fn(&str) -> RequestBuilder,
+    {
+        let (replica, delay) = match idempotent.then(|| self.inner.hedge.replica(base)).flatten() {
+            Some(hedge) => hedge,
+            None => return self.send_to(tracker, base, idempotent, path, build).await,
+        };
+
+        let primary = self.send_to(tracker, base, idempotent, path, build);
+        let hedged = async {
+            platform::sleep(delay).await;
+            self.send_to(tracker, replica, idempotent, path, build)
+                .await
+        };
+        tokio::pin!(primary, hedged);
+
+        // The first successful response wins, dropping the other request cancels it
+        tokio::select! {
+            res = &mut primary => match hedge::succeeded(&res) {
+                true => res,
+                false => hedged.await,
+            },
+            res = &mut hedged => match hedge::succeeded(&res) {
+                true => res,
+                false => primary.await,
+            },
+        }
+    }
+
+    /// Sends the request to the server at `base`. Idempotent requests failing with a connection
+    /// error or a 5xx status are built and sent again until the attempts or the retry budget are
+    /// exhausted. Every attempt goes through the circuit breaker when configured.
+    pub(crate) async fn send_to<F>(
+        &self,
+        tracker: &Tracker,
+        base: &str,
+        idempotent: bool,
+        path: &str,
+        build: &F,
+    ) -> Result<Response, Error>
+    where
+        F: Fn(&str) -> RequestBuilder,
+    {
+        let url = format!("{}{}", base, path);
+        let retry = &self.inner.retry;
+        let started = Instant::now();
+        let deadline = retry.deadline(started, idempotent);
+        let mut attempt = 0;
+
+        loop {
+            let res = match &self.inner.breaker {
+                Some(breaker) => {
+                    breaker.acquire(base)?;
+                    let res = self.send_once(tracker, &url, build, deadline).await;
+                    breaker.record(base, !retry::is_retriable(&res));
+                    res
+                }
+                None => self.send_once(tracker, &url, build, deadline).await,
+            };
+
+            match retry.next_retry(idempotent, attempt, started, deadline, &res) {
+                Some(wait) => platform::sleep(wait).await,
+                None => return res,
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Single attempt at sending the request, attaching the credentials unless the request has its
+    /// own. If the server rejects the token and a refresher is configured the request is built and
+    /// sent again with a new token.
+    /// Sends a GET to `url` once with the credentials of the connection, for the endpoints of
+    /// the server beside the query API
+    pub(crate) async fn get_authorized(&self, url: &str) -> Result<Response, Error> {
+        let client = &self.inner.client;
+        self.send_once(&Tracker::start(), url, &|url: &str| client.get(url), None)
+            .await
+    }
+
+    async fn send_once<F>(
+        &self,
+        tracker: &Tracker,
+        url: &str,
+        build: &F,
+        deadline: Option<Instant>,
+    ) -> Result<Response, Error>
+    where
+        F: Fn(&str) -> RequestBuilder,
+    {
+        let retry = &self.inner.retry;
+        let timeout = retry.attempt_timeout(deadline);
+        // The browser has no timeout per request, attempts are bounded by the operation timeout
+        #[cfg(not(target_arch = "wasm32"))]
+        let build = || match timeout {
+            Some(timeout) => build(url).timeout(timeout),
+            None => build(url),
+        };
+        #[cfg(target_arch = "wasm32")]
+        let build = || build(url);
+        let failed = |err| retry.attempt_error(err, timeout);
+
+        // Credentials set on the request itself, e.g. a token for one query, take precedence
+        #[cfg(not(target_arch = "wasm32"))]
+        let req = {
+            let (client, req) = build().build_split();
+            let req = req?;
+            if req.headers().contains_key(AUTHORIZATION) {
+                tracker.attempt();
+                return retry.read(client.execute(req)).await?.map_err(failed);
+            }
+            RequestBuilder::from_parts(client, req)
+        };
+        // The builder can't be taken apart in the browser, it is built twice instead
+        #[cfg(target_arch = "wasm32")]
+        let req = {
+            let probe = build().build()?;
+            if probe.headers().contains_key(AUTHORIZATION) {
+                tracker.attempt();
+                return retry
+                    .read(self.inner.client.execute(probe))
+                    .await?
+                    .map_err(failed);
+            }
+            build()
+        };
+
+        let token = self.inner.auth.token().await?;
+        tracker.attempt();
+        let basic = self.inner.basic_auth.as_ref();
+        let res = retry
+            .read(authorize(req, token.as_deref(), basic).send())
+            .await?
+            .map_err(failed)?;
+
+        if res.status() == StatusCode::UNAUTHORIZED && self.inner.auth.can_refresh() {
+            let token = self.inner.auth.refresh(token.as_deref()).await?;
+            tracker.attempt();
+            let req = authorize(build(), Some(&token), basic);
+            return retry.read(req.send()).await?.map_err(failed);
+        }
+
+        Ok(res)
+    }
+
     /// Compiles and executes the SQL query supplied
     ///
+    /// Structs get the values of a row by column name, tuples and vectors by position. Without
+    /// metadata (`nm` set to true) every type gets them by position.
+    ///
     /// # Arguments
     /// * `query` - query text. It can be multi-line, but query separator, such as ; must not be
-    /// included.
-    /// * `limit` - This argument is used for paging. Limit can be either in format of X, Y where X
-    /// is the lower limit and Y is the upper, or just Y. For example, limit=10,20 will return row
-    /// numbers 10 thru to 20 inclusive. and limit=20 will return first 20 rows, which is
-    /// equivalent to limit=0,20
+    ///   included.
+    /// * `limit` - Returns only the first `limit` rows. Pages are selected with
+    ///   [`Limit::Range`] in the [`ExecOptions`]: `Limit::Range(10, 20)` returns the rows 10
+    ///   (inclusive) to 20 (exclusive), counting from 0, and `Some(20)` here is equivalent to
+    ///   `Limit::Range(0, 20)`
     /// * `count` - Instructs /exec to count rows and return this value in message header. Default
-    /// value is false. There is slight performance hit for requesting row count.
+    ///   value is false. There is slight performance hit for requesting row count.
     /// * `nm` - Skips metadata section of the response when true. When metadata is known and client
-    /// is paging this flag should typically be set to true to reduce response size. Default value
-    /// is false and metadata is included in the response.
+    ///   is paging this flag should typically be set to true to reduce response size. Default value
+    ///   is false and metadata is included in the response.
     ///
     /// # Example
     /// ```no-test
@@ -61,6 +439,7 @@ impl QuestDB {
     ///     .await
     ///     .unwrap();
     /// ```
+    #[deprecated(note = "use `QuestDB::query` instead")]
     pub async fn exec<T: DeserializeOwned>(
         &self,
         query: &str,
@@ -68,41 +447,344 @@ impl QuestDB {
         count: Option<bool>,
         nm: Option<bool>,
     ) -> Result<Vec<T>, crate::error::Error> {
-        let query = encode(query);
-        let mut url = format!("{}/exec?query={}", self.url, query);
+        let options = ExecOptions {
+            limit: limit.map(Limit::from),
+            count,
+            nm,
+            ..ExecOptions::default()
+        };
 
-        // Check all the optional arguments and add them to the URL
-        if let Some(l) = limit {
-            url += format!("&limit={}", l).as_str();
+        self.exec_with(query, &options).await
+    }
+
+    /// Compiles and executes the SQL query supplied using the given options
+    ///
+    /// # Arguments
+    /// * `query` - query text. It can be multi-line, but query separator, such as ; must not be
+    ///   included.
+    /// * `options` - Per-call options, see [`ExecOptions`]
+    ///
+    /// # Example
+    /// ```no-test
+    /// use questdb::{ExecOptions, QuestDB, RequestContext};
+    ///
+    /// let connection = QuestDB::new("http://192.168.1.37:9000");
+    /// let options = ExecOptions::new()
+    ///     .limit(5)
+    ///     .context(RequestContext::new().header("X-Tenant-Id", "acme"));
+    /// let res = connection.exec_with::<TestData>("select * from readings", &options)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub async fn exec_with<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        options: &ExecOptions,
+    ) -> Result<Vec<T>, crate::error::Error> {
+        let (rows, _stats) = self.exec_with_stats(query, options).await?;
+        Ok(rows)
+    }
+
+    /// Executes a query with `$1`, `$2`, ... placeholders, the values are escaped and inlined
+    /// on the client, see [`sql::bind`]
+    ///
+    /// # Example
+    /// ```no-test
+    /// use questdb::params;
+    ///
+    /// let rows = connection
+    ///     .exec_params::<TestData>(
+    ///         "select * from readings where sensor_id = $1 and ts > $2",
+    ///         &params![sensor_id, Timestamp::parse("2023-01-01")?],
+    ///     )
+    ///     .await?;
+    /// ```
+    pub async fn exec_params<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        params: &[SqlValue],
+    ) -> Result<Vec<T>, Error> {
+        let query = sql::bind(query, params)?;
+        self.exec_with(&query, &ExecOptions::new()).await
+    }
+
+    /// Same as [`exec_with`](Self::exec_with), also returning the execution statistics of the
+    /// query
+    ///
+    /// # Example
+    /// ```no-test
+    /// use questdb::{ExecOptions, QuestDB};
+    ///
+    /// let connection = QuestDB::new("http://192.168.1.37:9000");
+    /// let options = ExecOptions::new().timings(true);
+    /// let (rows, stats) = connection
+    ///     .exec_with_stats::<TestData>("select * from readings", &options)
+    ///     .await
+    ///     .unwrap();
+    /// println!("{} rows in {:?}", rows.len(), stats.total_duration);
+    /// ```
+    pub async fn exec_with_stats<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        options: &ExecOptions,
+    ) -> Result<(Vec<T>, QueryStats), crate::error::Error> {
+        let (res, stats) = self.exec_json(query, options).await?;
+        let res: row::Dataset = serde_json::from_value(res)?;
+
+        Ok((res.into_rows()?, stats))
+    }
+
+    /// Same as [`exec_with`](Self::exec_with), also returning the names and types of the
+    /// columns, the number of rows of the whole result when `count` is set, and the time the
+    /// server spent when `timings` is set. The metadata is always requested, whatever `nm` is set
+    /// to.
+    ///
+    /// # Example
+    /// ```no-test
+    /// let connection = QuestDB::new("http://192.168.1.37:9000");
+    /// let options = ExecOptions::new().limit(Limit::Range(0, 20)).count(true);
+    /// let res = connection
+    ///     .exec_with_metadata::<TestData>("select * from readings", &options)
+    ///     .await
+    ///     .unwrap();
+    /// for column in &res.columns {
+    ///     println!("{}: {}", column.name, column.column_type);
+    /// }
+    /// println!("page 1 of {}", res.count.unwrap_or(0).div_ceil(20));
+    /// ```
+    pub async fn exec_with_metadata<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        options: &ExecOptions,
+    ) -> Result<QueryResult<T>, crate::error::Error> {
+        let options = options.clone().nm(false);
+        let (res, stats) = self.exec_json(query, &options).await?;
+        let res: row::Dataset = serde_json::from_value(res)?;
+        let columns = res.columns.clone();
+        // Without count=true the server only reports the rows it returned
+        let counted = options.or(&self.inner.default_options).count == Some(true);
+        let count = res.count.filter(|_| counted);
+        let rows = res.into_rows()?;
+
+        Ok(QueryResult {
+            columns,
+            rows,
+            count,
+            timings: stats.server_timings,
+        })
+    }
+
+    /// Executes the query and returns the rows as maps from column name to value, for scripts
+    /// and templates where declaring a struct for every query is overkill
+    ///
+    /// # Example
+    /// ```no-test
+    /// let connection = QuestDB::new("http://192.168.1.37:9000");
+    /// for row in connection.exec_maps("select * from readings limit 5").await.unwrap() {
+    ///     println!("{} at {}", row["temp"], row["ts"]);
+    /// }
+    /// ```
+    pub async fn exec_maps(
+        &self,
+        query: &str,
+    ) -> Result<Vec<HashMap<String, serde_json::Value>>, crate::error::Error> {
+        // The column names are part of the metadata
+        let options = ExecOptions::new().nm(false);
+        let (res, _stats) = self.exec_json(query, &options).await?;
+        let res: row::Dataset = serde_json::from_value(res)?;
+
+        Ok(res.into_maps())
+    }
+
+    /// Executes the query and returns rows with typed getters, for ad-hoc queries without a
+    /// struct to deserialize into, see [`Row`]
+    ///
+    /// # Example
+    /// ```no-test
+    /// let connection = QuestDB::new("http://192.168.1.37:9000");
+    /// for row in connection.exec_rows("select * from readings limit 5").await.unwrap() {
+    ///     println!("{:?}", row.get::<Option<f64>>("temp"));
+    /// }
+    /// ```
+    pub async fn exec_rows(&self, query: &str) -> Result<Vec<Row>, crate::error::Error> {
+        self.exec_rows_with(query, &ExecOptions::new()).await
+    }
+
+    /// Same as [`exec_rows`](Self::exec_rows) with options, the metadata is always requested
+    pub async fn exec_rows_with(
+        &self,
+        query: &str,
+        options: &ExecOptions,
+    ) -> Result<Vec<Row>, crate::error::Error> {
+        // The column names are part of the metadata
+        let options = options.clone().nm(false);
+        let (res, _stats) = self.exec_json(query, &options).await?;
+        let res: row::Dataset = serde_json::from_value(res)?;
+
+        Ok(res.into_dynamic_rows())
+    }
+
+    /// Executes a query that returns exactly one row, failing with [`Error::RowCount`] otherwise
+    ///
+    /// # Example
+    /// ```no-test
+    /// let reading: TestData = connection
+    ///     .fetch_one("select * from readings where id = 42")
+    ///     .await?;
+    /// ```
+    pub async fn fetch_one<T: DeserializeOwned>(&self, query: &str) -> Result<T, Error> {
+        self.query(query).fetch_one().await
+    }
+
+    /// Executes a query that returns at most one row, failing with [`Error::RowCount`] when it
+    /// returns more
+    pub async fn fetch_optional<T: DeserializeOwned>(
+        &self,
+        query: &str,
+    ) -> Result<Option<T>, Error> {
+        self.query(query).fetch_optional().await
+    }
+
+    /// Executes a query that returns a single value, such as `select count() from readings`
+    ///
+    /// # Example
+    /// ```no-test
+    /// let count: i64 = connection.fetch_scalar("select count() from readings").await?;
+    /// ```
+    pub async fn fetch_scalar<V: DeserializeOwned>(&self, query: &str) -> Result<V, Error> {
+        self.query(query).fetch_scalar().await
+    }
+
+    /// Executes a statement that doesn't return rows, such as `CREATE TABLE`, `INSERT` or
+    /// `ALTER TABLE`, and tells what it did. Queries are accepted too, their rows are dropped.
+    ///
+    /// # Example
+    /// ```no-test
+    /// let outcome = connection
+    ///     .execute("UPDATE readings SET temp = 0 WHERE temp < -273.15")
+    ///     .await?;
+    /// println!("{:?} rows fixed", outcome.rows_affected());
+    /// ```
+    pub async fn execute(&self, sql: &str) -> Result<ExecOutcome, Error> {
+        self.execute_with(sql, &ExecOptions::new()).await
+    }
+
+    /// Same as [`execute`](Self::execute) with options, e.g. a statement timeout
+    pub async fn execute_with(
+        &self,
+        sql: &str,
+        options: &ExecOptions,
+    ) -> Result<ExecOutcome, Error> {
+        let (res, _stats) = self.exec_json(sql, options).await?;
+        Ok(ExecOutcome::from_response(&res))
+    }
+
+    /// Runs a query on /exec and returns the whole response, which contains the rows under
+    /// `dataset` and the metadata of the columns under `columns`
+    pub(crate) async fn exec_json(
+        &self,
+        query: &str,
+        options: &ExecOptions,
+    ) -> Result<(serde_json::Value, QueryStats), Error> {
+        let options = &options.or(&self.inner.default_options);
+        match self.inner.retry.operation_timeout(options.timeout) {
+            Some(timeout) => platform::timeout(timeout, self.exec_json_with(query, options))
+                .await
+                .unwrap_or(Err(Error::Timeout(timeout))),
+            None => self.exec_json_with(query, options).await,
         }
-        if let Some(c) = count {
-            url += format!("&count={}", c).as_str();
+    }
+
+    /// [`exec_json`](Self::exec_json) with the defaults already applied to the options
+    async fn exec_json_with(
+        &self,
+        query: &str,
+        options: &ExecOptions,
+    ) -> Result<(serde_json::Value, QueryStats), Error> {
+        let tracker = Tracker::start();
+        self.check_read_only(query)?;
+        // Statements that write must not be sent twice
+        let idempotent = sql::classify(query).is_read_only();
+        let params = encode_params(query, options);
+
+        let _permit = self.inner.limits.acquire(options.priority).await;
+        let (res, request_bytes) = self
+            .exec_request(&tracker, idempotent, &params, options)
+            .await;
+
+        let stale = self.inner.stale.as_ref().filter(|_| idempotent);
+        if let Some(stale) = stale.filter(|_| retry::is_retriable(&res)) {
+            if let Some((res, age)) = stale.get(&params) {
+                let mut stats = tracker.finish(request_bytes, 0);
+                stats.stale = Some(age);
+                return Ok((res, stats));
+            }
         }
-        if let Some(n) = nm {
-            url += format!("&nm={}", n).as_str();
+        let res = self.inner.retry.body(res?).await?;
+
+        let mut stats = tracker.finish(request_bytes, res.len());
+        let mut res: serde_json::Value = serde_json::from_slice(&res)?;
+        if let Some(timings) = res.get_mut("timings") {
+            stats.server_timings = Some(serde_json::from_value(timings.take())?);
         }
+        self.observe("/exec", query, &stats);
 
-        let res = self
-            .client
-            .get(url.as_str())
-            .send()
-            .await?
-            .json::<serde_json::Value>()
-            .await?;
+        if res.get("error").is_some() {
+            // The SQL failed, return an error with the error data
+            let e = Error::SQLError(serde_json::from_value::<SQLError>(res)?);
+            self.inner.metadata.failed(&e);
+            return Err(e);
+        }
 
-        let deserialized = match res.get("dataset") {
-            Some(d) => d,
-            None => {
-                // The SQL failed, return an error with the error data
-                let e: SQLError = serde_json::from_value(res)?;
-                return Err(Error::SQLError(e));
-            }
+        self.inner.metadata.executed(query);
+        if let Some(stale) = stale {
+            stale.put(&params, &res);
         }
-        .to_owned();
+        Ok((res, stats))
+    }
+
+    /// Sends the encoded parameters of a query to /exec, returns the response and the size of
+    /// the request
+    pub(crate) async fn exec_request(
+        &self,
+        tracker: &Tracker,
+        idempotent: bool,
+        params: &str,
+        options: &ExecOptions,
+    ) -> (Result<Response, Error>, usize) {
+        // Long queries don't fit in the url, send them in the body instead
+        let post = options.post.unwrap_or_else(|| {
+            self.inner.url.len() + "/exec?".len() + params.len() > self.inner.max_url_length
+        });
+        let url = match post {
+            true => String::from("/exec"),
+            false => format!("/exec?{}", params),
+        };
 
-        let deserialized: Vec<T> = serde_json::from_value(deserialized)?;
+        let res = self
+            .send(tracker, idempotent, &url, |url| {
+                let req = match post {
+                    true => self
+                        .inner
+                        .client
+                        .post(url)
+                        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                        .body(params.to_owned()),
+                    false => self.inner.client.get(url),
+                };
+                let req = match options.statement_timeout.or(options.timeout) {
+                    Some(t) => req.header("Statement-Timeout", t.as_millis().to_string()),
+                    None => req,
+                };
+                match &options.context {
+                    Some(context) => context.apply(req),
+                    None => req,
+                }
+            })
+            .await;
 
-        Ok(deserialized)
+        let request_bytes = url.len() + if post { params.len() } else { 0 };
+        (res, request_bytes)
     }
 
     /// The function `imp` streams tabular text data directly into a table. It supports CSV, TAB and
@@ -111,17 +793,20 @@ impl QuestDB {
     /// However in some cases additional configuration can be provided to augment automatic
     /// detection results.
     ///
+    /// Returns the number of imported and rejected rows along with the type and number of
+    /// rejected values of every column.
+    ///
     /// # Arguments
     /// * `file_path` - Path to the file that is going to be imported
     /// * `table_name` - Name of the table where the data will be saved
     /// * `overwrite` - Default value is false. Set it to true to have existing table deleted before
-    ///     appending data.
+    ///   appending data.
     /// * `durable` - When request is durable QuestDB will flush relevant disk cache before
-    ///     responding. Default value is false
+    ///   responding. Default value is false
     /// * `atomicity` - Available values are strict and relaxed. Default value is relaxed. When
-    ///     atomicity is relaxed data rows that cannot be appended to table are discarded, thus
-    ///     allowing partial uploads. In strict mode upload fails as soon as any data error is
-    ///     encountered and all previously appended rows are rolled back.
+    ///   atomicity is relaxed data rows that cannot be appended to table are discarded, thus
+    ///   allowing partial uploads. In strict mode upload fails as soon as any data error is
+    ///   encountered and all previously appended rows are rolled back.
     ///
     /// # Example
     /// ```no-test
@@ -140,6 +825,7 @@ impl QuestDB {
     ///     }
     /// };
     /// ```
+    #[cfg(not(target_arch = "wasm32"))]
     pub async fn imp(
         &self,
         file_path: &'static str,
@@ -147,74 +833,97 @@ impl QuestDB {
         overwrite: Option<bool>,
         durable: Option<bool>,
         atomicity: Option<Atomicity>,
-    ) -> Result<(), crate::error::Error> {
-        let mut form = reqwest::multipart::Form::new();
-        let mut url = format!("{}/imp?fmt=json&name={}", self.url, table_name);
+    ) -> Result<ImportResult, crate::error::Error> {
+        self.inner
+            .retry
+            .total(async {
+                let tracker = Tracker::start();
+                if self.inner.read_only {
+                    return Err(Error::WriteForbidden(String::from("import")));
+                }
 
-        // Check all the optional arguments and add them to the URL
+                let mut url = format!("/imp?fmt=json&name={}", table_name);
 
-        /*if let Some(s) = schema {
-            let mut data = String::new();
+                // Check all the optional arguments and add them to the URL
 
-            for (i, &(name, schema)) in s.iter().enumerate() {
-                if i == s.len() - 1 {
-                    data += format!("{}={}", name, schema).as_str();
-                } else {
-                    data += format!("{}={}&", name, schema).as_str();
-                }
-            }
+                /*if let Some(s) = schema {
+                    let mut data = String::new();
 
-            form = form.part("schema", reqwest::multipart::Part::text(data));
-        }*/
+                    for (i, &(name, schema)) in s.iter().enumerate() {
+                        if i == s.len() - 1 {
+                            data += format!("{}={}", name, schema).as_str();
+                        } else {
+                            data += format!("{}={}&", name, schema).as_str();
+                        }
+                    }
 
-        if let Some(o) = overwrite {
-            url += format!("&overwrite={}", o).as_str();
-        }
-        if let Some(d) = durable {
-            url += format!("&durable={}", d).as_str();
-        }
-        if let Some(a) = atomicity {
-            url += format!("&atomicity={}", a).as_str();
-        }
+                    form = form.part("schema", reqwest::multipart::Part::text(data));
+                }*/
 
-        // Read the file as bytes
-        let filep = Path::new(file_path);
-        let mut file = File::open(&filep)?;
-        let mut file_bytes: Vec<u8> = Vec::new();
-        file.read_to_end(&mut file_bytes)?;
+                if let Some(o) = overwrite {
+                    url += format!("&overwrite={}", o).as_str();
+                }
+                if let Some(d) = durable {
+                    url += format!("&durable={}", d).as_str();
+                }
+                if let Some(a) = atomicity {
+                    url += format!("&atomicity={}", a).as_str();
+                }
 
-        // Create a part with the file_name
-        let file_name = match filep.file_name() {
-            Some(name) => name.to_str().unwrap(),
-            None => filep.to_str().unwrap(),
-        };
-        let part = reqwest::multipart::Part::bytes(file_bytes).file_name(file_name);
+                // Read the file as bytes, off the executor threads
+                let filep = Path::new(file_path);
+                let import_io = |operation| {
+                    move |source| Error::FileIo {
+                        path: filep.to_path_buf(),
+                        operation,
+                        source,
+                    }
+                };
+                let mut file = File::open(filep).await.map_err(import_io("open"))?;
+                let mut file_bytes: Vec<u8> = Vec::new();
+                file.read_to_end(&mut file_bytes)
+                    .await
+                    .map_err(import_io("read"))?;
 
-        // Create the form with the file part
-        form = form.part("data", part);
+                // Create a part with the file_name
+                let file_name = match filep.file_name() {
+                    Some(name) => name.to_str().unwrap(),
+                    None => filep.to_str().unwrap(),
+                };
 
-        // Make the POST request
-        let _res = self
-            .client
-            .post(url.as_str())
-            .multipart(form)
-            .send()
-            .await?
-            .text()
-            .await?;
+                // Make the POST request, the form is rebuilt in case the request has to be resent
+                let _permit = self.inner.limits.acquire(Priority::Batch).await;
+                let res = self
+                    .send(&tracker, false, &url, |url| {
+                        let part = reqwest::multipart::Part::bytes(file_bytes.clone())
+                            .file_name(String::from(file_name));
 
-        Ok(())
+                        // Create the form with the file part
+                        let form = reqwest::multipart::Form::new().part("data", part);
+                        self.inner.client.post(url).multipart(form)
+                    })
+                    .await?;
+                let res = self.inner.retry.body(error_for_status(res).await?).await?;
+
+                let stats = tracker.finish(url.len() + file_bytes.len(), res.len());
+                self.observe("/imp", table_name, &stats);
+
+                Ok(serde_json::from_slice::<ImportResult>(&res)?)
+            })
+            .await
     }
 
-    /// Exports the result of the query to a CSV file
+    /// Exports the result of the query as CSV to `output`. The response is copied to the output
+    /// as it arrives, so exports of any size only need a small, fixed amount of memory.
+    ///
+    /// Returns the number of bytes written.
     ///
     /// # Arguments
     /// * `query` - query text. It can be multi-line, but query separator, such as ; must not be
-    /// included.
-    /// * `limit` - This argument is used for paging. Limit can be either in format of X, Y where X
-    /// is the lower limit and Y is the upper, or just Y. For example, limit=10,20 will return row
-    /// numbers 10 thru to 20 inclusive. and limit=20 will return first 20 rows, which is
-    /// equivalent to limit=0,20
+    ///   included.
+    /// * `limit` - Exports only the first `limit` rows, the rows 0 (inclusive) to `limit`
+    ///   (exclusive) like [`Limit::Rows`]
+    /// * `output` - Destination of the CSV data, e.g. a [`File`](std::fs::File)
     ///
     /// # Example
     /// ```no-test
@@ -232,13 +941,96 @@ impl QuestDB {
     ///     }
     /// };
     /// ```
-    pub async fn exp(
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn exp<W: Write>(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        output: &mut W,
+    ) -> Result<u64, Error> {
+        let retry = &self.inner.retry;
+        retry
+            .total(async {
+                let tracker = Tracker::start();
+                let _permit = self.inner.limits.acquire(Priority::Batch).await;
+                let limit = limit.map(Limit::from);
+                let (url, mut res) = self.export(&tracker, query, limit).await?;
+
+                // Copy the body to the output chunk by chunk
+                let mut writer = BufWriter::with_capacity(EXPORT_BUFFER_SIZE, output);
+                let mut written = 0;
+                while let Some(chunk) = retry.chunk(&mut res).await? {
+                    writer.write_all(&chunk)?;
+                    written += chunk.len();
+                }
+                writer.flush()?;
+
+                let stats = tracker.finish(url.len(), written);
+                self.observe("/exp", query, &stats);
+
+                Ok(written as u64)
+            })
+            .await
+    }
+
+    /// Exports the result of the query and reads it with [`csv_async`] as it arrives, so exports
+    /// can be processed by existing csv based code without going through a temporary file
+    ///
+    /// The column names are read as the headers of the reader.
+    ///
+    /// # Example
+    /// ```no-test
+    /// use futures_util::StreamExt;
+    ///
+    /// let connection = QuestDB::new("http://192.168.1.37:9000");
+    /// let mut reader = connection.exp_csv("select * from readings", None).await.unwrap();
+    /// let mut records = reader.records();
+    /// while let Some(record) = records.next().await {
+    ///     println!("{:?}", record.unwrap());
+    /// }
+    /// ```
+    #[cfg(all(feature = "csv", not(target_arch = "wasm32")))]
+    pub async fn exp_csv(
         &self,
         query: &str,
         limit: Option<usize>,
-        output_file: &mut File,
-    ) -> Result<(), Error> {
-        let mut url = format!("{}/exp?query={}", self.url, query);
+    ) -> Result<csv_async::AsyncReader<impl futures_util::AsyncRead + Unpin + Send>, Error> {
+        use futures_util::TryStreamExt;
+
+        let tracker = Tracker::start();
+        // The permit and the total timeout only cover sending the request, the body is read by
+        // the caller
+        let _permit = self.inner.limits.acquire(Priority::Batch).await;
+        let limit = limit.map(Limit::from);
+        let export = self.export(&tracker, query, limit);
+        let (_url, res) = self.inner.retry.total(export).await?;
+
+        let retry = self.inner.retry.clone();
+        let chunks = futures_util::stream::try_unfold(res, move |mut res| {
+            let retry = retry.clone();
+            async move {
+                let chunk = retry.chunk(&mut res).await?;
+                Ok::<_, Error>(chunk.map(|chunk| (chunk, res)))
+            }
+        });
+        let body = Box::pin(chunks)
+            .map_err(std::io::Error::other)
+            .into_async_read();
+        Ok(csv_async::AsyncReader::from_reader(body))
+    }
+
+    /// Sends the request for an export and returns the url it was sent to along with the
+    /// response, whose body is the CSV. `limit` is either a number of rows or a range `lo,hi`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn export(
+        &self,
+        tracker: &Tracker,
+        query: &str,
+        limit: Option<Limit>,
+    ) -> Result<(String, Response), Error> {
+        self.check_read_only(query)?;
+
+        let mut url = format!("/exp?query={}", encode(query));
 
         // Check all the optional arguments and add them to the URL
         if let Some(l) = limit {
@@ -246,11 +1038,80 @@ impl QuestDB {
         }
 
         // Make the GET request
-        let res: String = self.client.get(url.as_str()).send().await?.text().await?;
+        let res = self
+            .send(tracker, true, &url, |url| self.inner.client.get(url))
+            .await?;
+        Ok((url, error_for_status(res).await?))
+    }
+}
 
-        // Try to write data to the file
-        output_file.write_all(res.as_bytes())?;
+/// Encodes the query and its options as the parameters of /exec
+pub(crate) fn encode_params(query: &str, options: &ExecOptions) -> String {
+    let mut params = vec![format!("query={}", encode(query))];
 
-        Ok(())
+    // Check all the optional arguments and add them to the parameters
+    if let Some(l) = options.limit {
+        params.push(format!("limit={}", l));
+    }
+    if let Some(c) = options.count {
+        params.push(format!("count={}", c));
+    }
+    if let Some(n) = options.nm {
+        params.push(format!("nm={}", n));
+    }
+    if let Some(t) = options.timings {
+        params.push(format!("timings={}", t));
+    }
+    if let Some(q) = options.quote_large_num {
+        params.push(format!("quoteLargeNum={}", q));
+    }
+    params.join("&")
+}
+
+/// Attaches the bearer token to the request, or the basic credentials without a token
+fn authorize(
+    req: RequestBuilder,
+    token: Option<&str>,
+    basic: Option<&BasicAuth>,
+) -> RequestBuilder {
+    match (token, basic) {
+        (Some(token), _) => req.bearer_auth(token),
+        (None, Some(basic)) => req.basic_auth(&basic.username, Some(&basic.password)),
+        (None, None) => req,
+    }
+}
+
+/// Turns responses with a non-2xx status into an error carrying the message sent by the server,
+/// which may be a JSON document or plain text
+pub(crate) async fn error_for_status(res: Response) -> Result<Response, Error> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+
+    let body = res.text().await?;
+    Err(status_error(status, body))
+}
+
+/// Error for a response with a non-2xx status and the given body
+pub(crate) fn status_error(status: StatusCode, body: String) -> Error {
+    let message = match serde_json::from_str::<serde_json::Value>(&body) {
+        Ok(json) => {
+            // Queries that fail to compile are reported the same way as by /exec
+            if let Ok(e) = serde_json::from_value::<SQLError>(json.clone()) {
+                return Error::SQLError(e);
+            }
+            ["error", "message", "status"]
+                .iter()
+                .find_map(|key| json.get(key).and_then(|v| v.as_str()))
+                .map(String::from)
+                .unwrap_or(body)
+        }
+        Err(_) => body,
+    };
+
+    Error::HttpError {
+        status: status.as_u16(),
+        message: String::from(message.trim()),
     }
 }
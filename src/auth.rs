@@ -0,0 +1,314 @@
+use crate::platform::Instant;
+use crate::redact::{self, Redacted};
+use crate::Error;
+use reqwest::Client;
+use serde::Deserialize;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+
+/// Token handed out by a [`TokenRefresher`]
+#[derive(Clone)]
+pub struct AccessToken {
+    pub(crate) token: String,
+    pub(crate) expires_in: Option<Duration>,
+}
+
+impl AccessToken {
+    /// Creates a token without a known expiration
+    pub fn new(token: &str) -> Self {
+        AccessToken {
+            token: String::from(token),
+            expires_in: None,
+        }
+    }
+
+    /// Sets how long the token stays valid after it was obtained
+    pub fn expires_in(mut self, expires_in: Duration) -> Self {
+        self.expires_in = Some(expires_in);
+        self
+    }
+}
+
+impl fmt::Debug for AccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessToken")
+            .field("token", &Redacted)
+            .field("expires_in", &self.expires_in)
+            .finish()
+    }
+}
+
+impl From<String> for AccessToken {
+    fn from(token: String) -> Self {
+        AccessToken {
+            token,
+            expires_in: None,
+        }
+    }
+}
+
+impl From<&str> for AccessToken {
+    fn from(token: &str) -> Self {
+        AccessToken::new(token)
+    }
+}
+
+/// Future returned by [`TokenRefresher::refresh`]
+#[cfg(not(target_arch = "wasm32"))]
+pub type TokenFuture = Pin<Box<dyn Future<Output = Result<AccessToken, Error>> + Send>>;
+
+/// Future returned by [`TokenRefresher::refresh`], the HTTP client of the browser isn't `Send`
+#[cfg(target_arch = "wasm32")]
+pub type TokenFuture = Pin<Box<dyn Future<Output = Result<AccessToken, Error>>>>;
+
+/// Source of fresh REST tokens
+///
+/// When the server answers with `401 Unauthorized` the client asks the refresher for a new token
+/// and retries the request once. Any `Fn() -> impl Future<Output = Result<AccessToken, Error>>`
+/// closure can be used as a refresher.
+///
+/// # Example
+/// ```
+/// use questdb::{AccessToken, QuestDB};
+///
+/// let connection = QuestDB::builder("http://192.168.1.37:9000")
+///     .token_refresher(|| async { Ok(AccessToken::new("fresh-token")) })
+///     .build()
+///     .unwrap();
+/// ```
+pub trait TokenRefresher: Send + Sync {
+    /// Obtains a new token
+    fn refresh(&self) -> TokenFuture;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<F, Fut> TokenRefresher for F
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<AccessToken, Error>> + Send + 'static,
+{
+    fn refresh(&self) -> TokenFuture {
+        Box::pin(self())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<F, Fut> TokenRefresher for F
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<AccessToken, Error>> + 'static,
+{
+    fn refresh(&self) -> TokenFuture {
+        Box::pin(self())
+    }
+}
+
+/// Refresher that obtains tokens by POSTing to an HTTP endpoint
+///
+/// The endpoint must answer with a JSON object containing the token in a `token` or
+/// `access_token` field, and optionally its lifetime in seconds in `expires_in`.
+///
+/// It is called with an HTTP client of its own, proxies, certificates and headers configured on
+/// the [`QuestDBBuilder`](crate::QuestDBBuilder) don't apply, see
+/// [`with_client`](Self::with_client).
+pub struct RefreshEndpoint {
+    client: Client,
+    url: String,
+}
+
+impl RefreshEndpoint {
+    /// Creates a refresher calling the given url
+    pub fn new(url: &str) -> Self {
+        RefreshEndpoint {
+            client: Client::new(),
+            url: String::from(url),
+        }
+    }
+
+    /// Calls the endpoint with `client`, e.g. one configured with a proxy or custom certificates
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+impl fmt::Debug for RefreshEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefreshEndpoint")
+            .field("url", &redact::url(&self.url))
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct RefreshResponse {
+    #[serde(alias = "access_token")]
+    token: String,
+    expires_in: Option<u64>,
+}
+
+impl TokenRefresher for RefreshEndpoint {
+    fn refresh(&self) -> TokenFuture {
+        let req = self.client.post(self.url.as_str());
+
+        Box::pin(async move {
+            let res = req
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<RefreshResponse>()
+                .await?;
+
+            Ok(AccessToken {
+                token: res.token,
+                expires_in: res.expires_in.map(Duration::from_secs),
+            })
+        })
+    }
+}
+
+struct CachedToken {
+    value: String,
+    expires_at: Option<Instant>,
+}
+
+/// How long before its expiration a token is proactively refreshed
+const REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+impl CachedToken {
+    fn from_access_token(token: AccessToken) -> Self {
+        CachedToken {
+            // Refresh early so requests in flight never carry an expired token, but never spend
+            // more than half of a short lifetime waiting
+            expires_at: token
+                .expires_in
+                .map(|d| Instant::now() + d - REFRESH_MARGIN.min(d / 2)),
+            value: token.token,
+        }
+    }
+}
+
+/// Username and password sent with HTTP Basic authentication
+#[derive(Clone)]
+pub(crate) struct BasicAuth {
+    pub(crate) username: String,
+    pub(crate) password: String,
+}
+
+impl fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("username", &self.username)
+            .field("password", &Redacted)
+            .finish()
+    }
+}
+
+/// Token currently used by a client and the way to refresh it
+#[derive(Default)]
+pub(crate) struct TokenState {
+    current: RwLock<Option<CachedToken>>,
+    refresher: Option<Arc<dyn TokenRefresher>>,
+    // Serializes refreshes so concurrent 401s only fetch a single new token
+    refreshing: Mutex<()>,
+}
+
+impl fmt::Debug for TokenState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The current token can't be inspected without locking, it is never printed anyway
+        f.debug_struct("TokenState")
+            .field(
+                "refresher",
+                &self.refresher.as_ref().map(|_| "TokenRefresher"),
+            )
+            .finish()
+    }
+}
+
+impl TokenState {
+    pub(crate) fn new(token: Option<String>, refresher: Option<Arc<dyn TokenRefresher>>) -> Self {
+        TokenState {
+            current: RwLock::new(token.map(|value| CachedToken {
+                value,
+                expires_at: None,
+            })),
+            refresher,
+            refreshing: Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn can_refresh(&self) -> bool {
+        self.refresher.is_some()
+    }
+
+    /// Returns the token to use for the next request
+    pub(crate) async fn token(&self) -> Result<Option<String>, Error> {
+        let current = self.current.read().await;
+        let rejected = match &*current {
+            Some(t) if !self.can_refresh() => return Ok(Some(t.value.clone())),
+            Some(t) => match t.expires_at {
+                Some(expires_at) if expires_at <= Instant::now() => Some(t.value.clone()),
+                _ => return Ok(Some(t.value.clone())),
+            },
+            // Nothing was configured up front, fetch the first token lazily
+            None if self.can_refresh() => None,
+            None => return Ok(None),
+        };
+        drop(current);
+
+        self.refresh(rejected.as_deref()).await.map(Some)
+    }
+
+    /// Replaces the token `rejected` with a fresh one. If another request already refreshed it
+    /// in the meantime that token is returned instead.
+    pub(crate) async fn refresh(&self, rejected: Option<&str>) -> Result<String, Error> {
+        let refresher = match &self.refresher {
+            Some(r) => r,
+            None => {
+                return Err(Error::AuthError(String::from(
+                    "no token refresher configured",
+                )))
+            }
+        };
+
+        let _guard = self.refreshing.lock().await;
+        if let Some(t) = &*self.current.read().await {
+            if Some(t.value.as_str()) != rejected {
+                return Ok(t.value.clone());
+            }
+        }
+
+        let token = CachedToken::from_access_token(refresher.refresh().await?);
+        let value = token.value.clone();
+        *self.current.write().await = Some(token);
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_refresh_once_per_rejected_token() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let refresher = move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(AccessToken::from(format!("token-{}", n))) }
+        };
+        let state = TokenState::new(Some(String::from("expired")), Some(Arc::new(refresher)));
+
+        assert_eq!(state.refresh(Some("expired")).await.unwrap(), "token-0");
+        // A second request that failed with the old token gets the already refreshed one
+        assert_eq!(state.refresh(Some("expired")).await.unwrap(), "token-0");
+        assert_eq!(state.token().await.unwrap().as_deref(), Some("token-0"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Latency recorded for a failed request, so that failing replicas are avoided for a while
+const FAILURE_LATENCY: Duration = Duration::from_secs(1);
+
+/// Every this many reads, [`LoadBalancing::LeastLatency`] sends one to the next replica in turn,
+/// so that slow or failed replicas are measured again once they recovered
+const PROBE_EVERY: usize = 16;
+
+/// How read queries and exports are spread over the replicas, see
+/// [`QuestDBBuilder::load_balance_reads`](crate::QuestDBBuilder::load_balance_reads)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadBalancing {
+    /// Each replica in turn
+    RoundRobin,
+    /// The replica that answered fastest recently. Replicas that haven't answered yet are tried
+    /// first, and failures count as slow answers. One read in 16 goes to each replica in turn,
+    /// so a replica that was slow or failing is picked again once it answers fast.
+    LeastLatency,
+}
+
+/// Replicas serving the reads and what is known about them
+#[derive(Debug)]
+pub(crate) struct Balancer {
+    strategy: LoadBalancing,
+    replicas: Vec<String>,
+    next: AtomicUsize,
+    /// Moving average of the latency of every replica in microseconds, zero until it answered
+    latencies: Vec<AtomicU64>,
+}
+
+impl Balancer {
+    /// `None` without replicas to balance over
+    pub(crate) fn new(strategy: LoadBalancing, replicas: Vec<String>) -> Option<Self> {
+        if replicas.is_empty() {
+            return None;
+        }
+        let latencies = replicas.iter().map(|_| AtomicU64::new(0)).collect();
+        Some(Balancer {
+            strategy,
+            replicas,
+            next: AtomicUsize::new(0),
+            latencies,
+        })
+    }
+
+    /// Replica the next read goes to, and its index for [`observe`](Self::observe)
+    pub(crate) fn pick(&self) -> (usize, &str) {
+        let index = match self.strategy {
+            LoadBalancing::RoundRobin => {
+                self.next.fetch_add(1, Ordering::Relaxed) % self.replicas.len()
+            }
+            LoadBalancing::LeastLatency => {
+                let n = self.next.fetch_add(1, Ordering::Relaxed);
+                match n % PROBE_EVERY {
+                    0 if n > 0 => (n / PROBE_EVERY) % self.replicas.len(),
+                    _ => (0..self.replicas.len())
+                        .min_by_key(|i| self.latencies[*i].load(Ordering::Relaxed))
+                        .unwrap_or_default(),
+                }
+            }
+        };
+        (index, &self.replicas[index])
+    }
+
+    /// Records how long a read took on the replica at `index`
+    pub(crate) fn observe(&self, index: usize, latency: Duration, succeeded: bool) {
+        let latency = match succeeded {
+            true => latency,
+            false => latency.max(FAILURE_LATENCY),
+        };
+        // At least a microsecond, zero is reserved for replicas that haven't answered yet
+        let sample = (latency.as_micros() as u64).max(1);
+        let average = &self.latencies[index];
+        let previous = average.load(Ordering::Relaxed);
+        let updated = match previous {
+            0 => sample,
+            previous => (previous * 7 + sample) / 8,
+        };
+        average.store(updated, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Balancer, LoadBalancing};
+    use std::time::Duration;
+
+    #[test]
+    fn test_balancer() {
+        let replicas = vec![String::from("http://a:9000"), String::from("http://b:9000")];
+        assert!(Balancer::new(LoadBalancing::RoundRobin, Vec::new()).is_none());
+
+        let balancer = Balancer::new(LoadBalancing::RoundRobin, replicas.clone()).unwrap();
+        let picked: Vec<usize> = (0..4).map(|_| balancer.pick().0).collect();
+        assert_eq!(picked, [0, 1, 0, 1]);
+
+        let balancer = Balancer::new(LoadBalancing::LeastLatency, replicas).unwrap();
+        assert_eq!(balancer.pick(), (0, "http://a:9000"));
+        balancer.observe(0, Duration::from_millis(20), true);
+        // b hasn't answered yet
+        assert_eq!(balancer.pick().0, 1);
+        balancer.observe(1, Duration::from_millis(5), true);
+        assert_eq!(balancer.pick().0, 1);
+        balancer.observe(1, Duration::from_millis(5), false);
+        assert_eq!(balancer.pick().0, 0);
+
+        // b recovers, the probes measure it again until it is the fastest
+        let mut picked = Vec::new();
+        for _ in 0..1000 {
+            let (index, _) = balancer.pick();
+            let latency = [20, 5][index];
+            balancer.observe(index, Duration::from_millis(latency), true);
+            picked.push(index);
+        }
+        assert!(picked[..100].contains(&1));
+        assert!(picked[900..].iter().filter(|i| **i == 1).count() > 90);
+    }
+}
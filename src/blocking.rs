@@ -0,0 +1,333 @@
+//! Synchronous client for programs without an async runtime, such as CLIs and batch jobs
+//!
+//! [`QuestDB`] mirrors `exec_with`, `imp` and `exp` of the async [`QuestDB`](crate::QuestDB)
+//! and blocks the calling thread until the server answered. It must not be used from within an
+//! async runtime, use the async client there.
+//!
+//! It is a thin client: only the url, the credentials and the [`ExecOptions`] of each call are
+//! used. What is configured with the [`QuestDBBuilder`](crate::QuestDBBuilder) doesn't apply,
+//! such as the read-only guard, retries, replicas, limits and default options, so statements
+//! that the async client would refuse as read-only are sent.
+//!
+//! # Example
+//! ```no_run
+//! use questdb::blocking::QuestDB;
+//! use questdb::ExecOptions;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug)]
+//! struct TestData {
+//!     id: i32,
+//!     temp: f64,
+//! }
+//!
+//! let connection = QuestDB::new("http://192.168.1.37:9000");
+//! let rows = connection
+//!     .exec_with::<TestData>("select id, temp from readings", &ExecOptions::new().limit(5))
+//!     .unwrap();
+//! println!("{:#?}", rows);
+//! ```
+
+use crate::api::{encode_params, status_error, DEFAULT_MAX_URL_LENGTH, USER_AGENT};
+use crate::auth::BasicAuth;
+use crate::error::SQLError;
+use crate::import::ImportResult;
+use crate::options::ExecOptions;
+use crate::redact::{self, Redacted};
+use crate::row;
+use crate::types::Atomicity;
+use crate::Error;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::CONTENT_TYPE;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use urlencoding::encode;
+
+/// Blocking connection to a QuestDB server
+///
+/// The connection is cheap to clone, clones share the same HTTP connection pool.
+#[derive(Clone)]
+pub struct QuestDB {
+    client: Client,
+    url: String,
+    token: Option<String>,
+    basic_auth: Option<BasicAuth>,
+}
+
+impl QuestDB {
+    /// Creates a connection to the server at `url`, e.g. `http://localhost:9000`
+    pub fn new(url: &str) -> Self {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .build()
+            .expect("the default client can be built");
+        Self::with_client(url, client)
+    }
+
+    /// Creates a connection sending its requests with `client`, e.g. to configure TLS or proxies
+    pub fn with_client(url: &str, client: Client) -> Self {
+        QuestDB {
+            client,
+            url: String::from(url.trim_end_matches('/')),
+            token: None,
+            basic_auth: None,
+        }
+    }
+
+    /// Sends `Authorization: Bearer <token>` with every request
+    pub fn token(mut self, token: &str) -> Self {
+        self.token = Some(String::from(token));
+        self
+    }
+
+    /// Sends the credentials with HTTP Basic authentication with every request. A
+    /// [token](Self::token) takes precedence when both are set.
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.basic_auth = Some(BasicAuth {
+            username: String::from(username),
+            password: String::from(password),
+        });
+        self
+    }
+
+    /// Compiles and executes the SQL query supplied using the given options, see
+    /// [`QuestDB::exec_with`](crate::QuestDB::exec_with)
+    pub fn exec_with<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        options: &ExecOptions,
+    ) -> Result<Vec<T>, Error> {
+        let params = encode_params(query, options);
+
+        // Long queries don't fit in the url, send them in the body instead
+        let post = options.post.unwrap_or_else(|| {
+            self.url.len() + "/exec?".len() + params.len() > DEFAULT_MAX_URL_LENGTH
+        });
+        let req = match post {
+            true => self
+                .client
+                .post(format!("{}/exec", self.url))
+                .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(params),
+            false => self.client.get(format!("{}/exec?{}", self.url, params)),
+        };
+        let req = match options.statement_timeout.or(options.timeout) {
+            Some(t) => req.header("Statement-Timeout", t.as_millis().to_string()),
+            None => req,
+        };
+        let req = match options.timeout {
+            Some(t) => req.timeout(t),
+            None => req,
+        };
+        let req = match &options.context {
+            Some(context) => context
+                .headers
+                .iter()
+                .fold(req, |req, (name, value)| req.header(name, value)),
+            None => req,
+        };
+
+        let res = error_for_status(self.send(req)?)?;
+        let res: serde_json::Value = serde_json::from_slice(&res.bytes()?)?;
+        if res.get("error").is_some() {
+            // The SQL failed, return an error with the error data
+            return Err(Error::SQLError(serde_json::from_value::<SQLError>(res)?));
+        }
+
+        let res: row::Dataset = serde_json::from_value(res)?;
+        Ok(res.into_rows()?)
+    }
+
+    /// Imports the delimited file into the table, see [`QuestDB::imp`](crate::QuestDB::imp)
+    pub fn imp(
+        &self,
+        file_path: &str,
+        table_name: &str,
+        overwrite: Option<bool>,
+        durable: Option<bool>,
+        atomicity: Option<Atomicity>,
+    ) -> Result<ImportResult, Error> {
+        let mut url = format!("{}/imp?fmt=json&name={}", self.url, table_name);
+
+        // Check all the optional arguments and add them to the URL
+        if let Some(o) = overwrite {
+            url += format!("&overwrite={}", o).as_str();
+        }
+        if let Some(d) = durable {
+            url += format!("&durable={}", d).as_str();
+        }
+        if let Some(a) = atomicity {
+            url += format!("&atomicity={}", a).as_str();
+        }
+
+        let filep = Path::new(file_path);
+        let file_bytes = std::fs::read(filep).map_err(|source| Error::FileIo {
+            path: filep.to_path_buf(),
+            operation: "read",
+            source,
+        })?;
+        let file_name = match filep.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => String::from(file_path),
+        };
+
+        let part = reqwest::blocking::multipart::Part::bytes(file_bytes).file_name(file_name);
+        let form = reqwest::blocking::multipart::Form::new().part("data", part);
+        let res = error_for_status(self.send(self.client.post(url).multipart(form))?)?;
+
+        Ok(serde_json::from_slice::<ImportResult>(&res.bytes()?)?)
+    }
+
+    /// Exports the result of the query as CSV to `output`, see
+    /// [`QuestDB::exp`](crate::QuestDB::exp). The response is copied to the output as it
+    /// arrives.
+    ///
+    /// Returns the number of bytes written.
+    pub fn exp<W: Write>(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        output: &mut W,
+    ) -> Result<u64, Error> {
+        let mut url = format!("{}/exp?query={}", self.url, encode(query));
+
+        // Check all the optional arguments and add them to the URL
+        if let Some(l) = limit {
+            url += format!("&limit={}", l).as_str();
+        }
+
+        let mut res = error_for_status(self.send(self.client.get(url))?)?;
+        Ok(res.copy_to(output)?)
+    }
+
+    /// Attaches the credentials and sends the request
+    fn send(&self, req: RequestBuilder) -> Result<Response, Error> {
+        let req = match (&self.token, &self.basic_auth) {
+            (Some(token), _) => req.bearer_auth(token),
+            (None, Some(basic)) => req.basic_auth(&basic.username, Some(&basic.password)),
+            (None, None) => req,
+        };
+        Ok(req.send()?)
+    }
+}
+
+impl fmt::Debug for QuestDB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuestDB")
+            .field("url", &redact::url(&self.url))
+            .field("token", &self.token.as_ref().map(|_| Redacted))
+            .field("basic_auth", &self.basic_auth)
+            .finish()
+    }
+}
+
+/// Turns responses with a non-2xx status into an error carrying the message sent by the server
+fn error_for_status(res: Response) -> Result<Response, Error> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+    Err(status_error(status, res.text()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QuestDB;
+    use crate::{Error, ExecOptions};
+    use serde::Deserialize;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Answers a single request with `status` and `body`, returns the request head and body
+    fn serve_once(
+        status: &'static str,
+        body: &'static str,
+    ) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut head = String::new();
+            while !head.ends_with("\r\n\r\n") {
+                stream.read_line(&mut head).unwrap();
+            }
+            let length = head
+                .lines()
+                .find_map(|l| {
+                    l.to_ascii_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|v| v.trim().parse().unwrap())
+                })
+                .unwrap_or(0);
+            let mut request_body = vec![0; length];
+            stream.read_exact(&mut request_body).unwrap();
+
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.get_mut().write_all(response.as_bytes()).unwrap();
+            head + &String::from_utf8_lossy(&request_body)
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn test_exec() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Reading {
+            id: i32,
+            temp: f64,
+        }
+
+        let (url, server) = serve_once(
+            "200 OK",
+            r#"{"query":"","columns":[{"name":"id","type":"INT"},{"name":"temp","type":"DOUBLE"}],"dataset":[[1,16.5]],"count":1}"#,
+        );
+        let connection = QuestDB::new(&url).token("secret");
+        let rows = connection
+            .exec_with::<Reading>(
+                "select id, temp from readings",
+                &ExecOptions::new().limit(1),
+            )
+            .unwrap();
+        assert_eq!(rows, [Reading { id: 1, temp: 16.5 }]);
+        let request = server.join().unwrap();
+        assert!(
+            request.starts_with("GET /exec?query=select%20id%2C%20temp%20from%20readings&limit=1 ")
+        );
+        assert!(request.contains("authorization: Bearer secret"));
+
+        let (url, server) = serve_once(
+            "400 Bad Request",
+            r#"{"query":"selec 1","error":"unexpected token","position":0}"#,
+        );
+        let options = ExecOptions::new().post(true);
+        let res = QuestDB::new(&url).exec_with::<Reading>("selec 1", &options);
+        assert!(matches!(res, Err(Error::SQLError(_))));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /exec "));
+        assert!(request.ends_with("query=selec%201"));
+    }
+
+    #[test]
+    fn test_exp() {
+        let (url, server) = serve_once("200 OK", "\"id\"\r\n1\r\n2\r\n");
+        let mut output = Vec::new();
+        let written = QuestDB::new(&url)
+            .exp("select id from readings", Some(2), &mut output)
+            .unwrap();
+        assert_eq!(written, 12);
+        assert_eq!(output, b"\"id\"\r\n1\r\n2\r\n");
+        assert!(server
+            .join()
+            .unwrap()
+            .starts_with("GET /exp?query=select%20id%20from%20readings&limit=2 "));
+    }
+}
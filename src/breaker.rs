@@ -0,0 +1,125 @@
+use crate::platform::Instant;
+use crate::redact;
+use crate::Error;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Fails requests to a server fast after it failed repeatedly, so that callers don't pile up
+/// requests against a node that is down
+///
+/// Every server has its own circuit. It opens after `threshold` consecutive failures and rejects
+/// requests until the cool-down passed. A single request is then let through to probe the
+/// server: it closes the circuit when it succeeds and opens it again when it fails.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    threshold: u32,
+    cool_down: Duration,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Circuit {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// A probe was let through at the instant, others wait for its outcome
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(threshold: u32, cool_down: Duration) -> Self {
+        CircuitBreaker {
+            threshold: threshold.max(1),
+            cool_down,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Allows a request to `server`, failing with [`Error::CircuitOpen`] while its circuit is
+    /// open or a probe is under way
+    pub(crate) fn acquire(&self, server: &str) -> Result<(), Error> {
+        let now = Instant::now();
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits
+            .entry(String::from(server))
+            .or_insert(Circuit::Closed { failures: 0 });
+
+        let reopens = match *circuit {
+            Circuit::Closed { .. } => return Ok(()),
+            Circuit::Open { until } => until,
+            // A probe that never reported back, e.g. because it was cancelled, is given up on
+            Circuit::HalfOpen { since } => since + self.cool_down,
+        };
+        if now < reopens {
+            return Err(Error::CircuitOpen {
+                server: redact::url(server),
+                retry_after: reopens - now,
+            });
+        }
+        *circuit = Circuit::HalfOpen { since: now };
+        Ok(())
+    }
+
+    /// Records the outcome of a request to `server`
+    pub(crate) fn record(&self, server: &str, succeeded: bool) {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits
+            .entry(String::from(server))
+            .or_insert(Circuit::Closed { failures: 0 });
+
+        *circuit = match (*circuit, succeeded) {
+            (_, true) => Circuit::Closed { failures: 0 },
+            (Circuit::Closed { failures }, false) if failures + 1 < self.threshold => {
+                Circuit::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (_, false) => Circuit::Open {
+                until: Instant::now() + self.cool_down,
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CircuitBreaker;
+    use crate::Error;
+    use std::time::Duration;
+
+    #[test]
+    fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+        let server = "http://127.0.0.1:9000";
+
+        breaker.acquire(server).unwrap();
+        breaker.record(server, false);
+        breaker.acquire(server).unwrap();
+        breaker.record(server, false);
+        assert!(matches!(
+            breaker.acquire(server),
+            Err(Error::CircuitOpen { .. })
+        ));
+        // Other servers have their own circuit
+        breaker.acquire("http://127.0.0.1:9001").unwrap();
+
+        // A single probe after the cool-down, failing opens the circuit again
+        std::thread::sleep(Duration::from_millis(60));
+        breaker.acquire(server).unwrap();
+        assert!(breaker.acquire(server).is_err());
+        breaker.record(server, false);
+        assert!(breaker.acquire(server).is_err());
+
+        std::thread::sleep(Duration::from_millis(60));
+        breaker.acquire(server).unwrap();
+        breaker.record(server, true);
+        breaker.acquire(server).unwrap();
+        breaker.acquire(server).unwrap();
+    }
+}
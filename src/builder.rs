@@ -0,0 +1,925 @@
+use crate::api::{Inner, QuestDB, DEFAULT_MAX_URL_LENGTH, USER_AGENT};
+use crate::auth::{BasicAuth, RefreshEndpoint, TokenRefresher, TokenState};
+use crate::balance::{Balancer, LoadBalancing};
+use crate::breaker::CircuitBreaker;
+use crate::failover::{Failover, DEFAULT_PROBE_INTERVAL};
+use crate::hedge::HedgeConfig;
+use crate::limit::Limits;
+use crate::metadata::Metadata;
+use crate::options::{ExecOptions, Priority, RequestContext};
+use crate::redact;
+use crate::retry::{CustomPolicy, RetryConfig, RetryPolicy};
+use crate::stale::StaleCache;
+use crate::stats::{SlowQuery, SlowQueryHook};
+use crate::Error;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Client;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::{NoProxy, Proxy};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builder for a configured [`QuestDB`] connection
+///
+/// # Example
+/// ```
+/// use questdb::QuestDB;
+///
+/// let connection = QuestDB::builder("http://192.168.1.37:9000")
+///     .token("my-rest-token")
+///     .build()
+///     .unwrap();
+/// ```
+pub struct QuestDBBuilder {
+    url: String,
+    token: Option<String>,
+    token_refresher: Option<Arc<dyn TokenRefresher>>,
+    basic_auth: Option<BasicAuth>,
+    read_only: bool,
+    retry: RetryConfig,
+    replicas: Vec<String>,
+    hedge_after: Option<Duration>,
+    load_balancing: Option<LoadBalancing>,
+    circuit_breaker: Option<(u32, Duration)>,
+    failover: Vec<String>,
+    probe_interval: Duration,
+    limits: Limits,
+    slow_query: Option<SlowQueryHook>,
+    max_url_length: usize,
+    default_options: ExecOptions,
+    validate_writes: bool,
+    health_url: Option<String>,
+    metrics_url: Option<String>,
+    max_staleness: Option<Duration>,
+    headers: RequestContext,
+    user_agent: String,
+    root_certificates: Vec<Vec<u8>>,
+    root_certificate_files: Vec<PathBuf>,
+    accept_invalid_certs: bool,
+    proxy: Option<String>,
+    proxy_auth: Option<BasicAuth>,
+    no_proxy: Option<String>,
+    system_proxy: bool,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout: Option<Option<Duration>>,
+    #[cfg(feature = "keyring")]
+    token_keyring: Option<crate::keystore::Keyring>,
+    /// Username and the entry holding the password
+    #[cfg(feature = "keyring")]
+    basic_auth_keyring: Option<(String, crate::keystore::Keyring)>,
+}
+
+impl fmt::Debug for QuestDBBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let replicas: Vec<String> = self.replicas.iter().map(|r| redact::url(r)).collect();
+        let failover: Vec<String> = self.failover.iter().map(|r| redact::url(r)).collect();
+        f.debug_struct("QuestDBBuilder")
+            .field("url", &redact::url(&self.url))
+            .field("token", &redact::secret(&self.token))
+            .field(
+                "token_refresher",
+                &self.token_refresher.as_ref().map(|_| "TokenRefresher"),
+            )
+            .field("basic_auth", &self.basic_auth)
+            .field("read_only", &self.read_only)
+            .field("retry", &self.retry)
+            .field("replicas", &replicas)
+            .field("hedge_after", &self.hedge_after)
+            .field("load_balancing", &self.load_balancing)
+            .field("circuit_breaker", &self.circuit_breaker)
+            .field("failover", &failover)
+            .field("probe_interval", &self.probe_interval)
+            .field("limits", &self.limits)
+            .field("slow_query", &self.slow_query)
+            .field("max_url_length", &self.max_url_length)
+            .field("default_options", &self.default_options)
+            .field("validate_writes", &self.validate_writes)
+            .field("health_url", &self.health_url.as_deref().map(redact::url))
+            .field("metrics_url", &self.metrics_url.as_deref().map(redact::url))
+            .field("max_staleness", &self.max_staleness)
+            .field("headers", &self.headers)
+            .field("user_agent", &self.user_agent)
+            .field("root_certificates", &self.root_certificates.len())
+            .field("root_certificate_files", &self.root_certificate_files)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("proxy", &self.proxy.as_deref().map(redact::url))
+            .field("proxy_auth", &self.proxy_auth)
+            .field("no_proxy", &self.no_proxy)
+            .field("system_proxy", &self.system_proxy)
+            .field("pool_max_idle_per_host", &self.pool_max_idle_per_host)
+            .field("pool_idle_timeout", &self.pool_idle_timeout)
+            .finish()
+    }
+}
+
+impl QuestDBBuilder {
+    pub(crate) fn new(url: &str) -> Self {
+        QuestDBBuilder {
+            url: String::from(url),
+            token: None,
+            token_refresher: None,
+            basic_auth: None,
+            read_only: false,
+            retry: RetryConfig::default(),
+            replicas: Vec::new(),
+            hedge_after: None,
+            load_balancing: None,
+            circuit_breaker: None,
+            failover: Vec::new(),
+            probe_interval: DEFAULT_PROBE_INTERVAL,
+            limits: Limits::default(),
+            slow_query: None,
+            max_url_length: DEFAULT_MAX_URL_LENGTH,
+            default_options: ExecOptions::default(),
+            validate_writes: false,
+            health_url: None,
+            metrics_url: None,
+            max_staleness: None,
+            headers: RequestContext::new(),
+            user_agent: String::from(USER_AGENT),
+            root_certificates: Vec::new(),
+            root_certificate_files: Vec::new(),
+            accept_invalid_certs: false,
+            proxy: None,
+            proxy_auth: None,
+            no_proxy: None,
+            system_proxy: true,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+            #[cfg(feature = "keyring")]
+            token_keyring: None,
+            #[cfg(feature = "keyring")]
+            basic_auth_keyring: None,
+        }
+    }
+
+    /// Sends `Authorization: Bearer <token>` with every request
+    pub fn token(mut self, token: &str) -> Self {
+        self.token = Some(String::from(token));
+        self
+    }
+
+    /// Sends the credentials with HTTP Basic authentication with every request, for servers
+    /// behind a proxy asking for them. A [token](Self::token) takes precedence when both are set.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    ///
+    /// let connection = QuestDB::builder("https://questdb.example.com")
+    ///     .basic_auth("admin", "quest")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.basic_auth = Some(BasicAuth {
+            username: String::from(username),
+            password: String::from(password),
+        });
+        self
+    }
+
+    /// Uses `refresher` to obtain a new token whenever the server rejects the current one with
+    /// `401 Unauthorized`. The failed request is retried once with the new token. When no initial
+    /// token is set the first one is obtained from the refresher as well.
+    pub fn token_refresher<R: TokenRefresher + 'static>(mut self, refresher: R) -> Self {
+        self.token_refresher = Some(Arc::new(refresher));
+        self
+    }
+
+    /// Shorthand for [`token_refresher`](Self::token_refresher) with a [`RefreshEndpoint`]
+    pub fn refresh_endpoint(self, url: &str) -> Self {
+        self.token_refresher(RefreshEndpoint::new(url))
+    }
+
+    /// Authenticates with tokens obtained through the OAuth2 client-credentials grant
+    #[cfg(feature = "oauth2")]
+    pub fn oauth2(self, credentials: crate::oauth2::ClientCredentials) -> Self {
+        self.token_refresher(credentials)
+    }
+
+    /// Reads the token from the system credential store when the connection is built
+    #[cfg(feature = "keyring")]
+    pub fn token_from_keyring(mut self, entry: crate::keystore::Keyring) -> Self {
+        self.token_keyring = Some(entry);
+        self
+    }
+
+    /// Sends the credentials with HTTP Basic authentication like [`basic_auth`](Self::basic_auth),
+    /// the password is read from the system credential store when the connection is built
+    ///
+    /// # Example
+    /// ```no-test
+    /// let connection = QuestDB::builder("https://questdb.example.com")
+    ///     .basic_auth_from_keyring("admin", Keyring::new("questdb", "admin"))
+    ///     .build()?;
+    /// ```
+    #[cfg(feature = "keyring")]
+    pub fn basic_auth_from_keyring(
+        mut self,
+        username: &str,
+        password: crate::keystore::Keyring,
+    ) -> Self {
+        self.basic_auth_keyring = Some((String::from(username), password));
+        self
+    }
+
+    /// Only allows statements that cannot modify data (SELECT, SHOW and EXPLAIN). Anything else,
+    /// including imports, fails with [`Error::WriteForbidden`] before reaching the server.
+    /// Queries naming a table without a leading `SELECT` are rejected as well.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Maximum time a single attempt at a request may take. No timeout is applied by default.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.retry.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum time establishing a connection to the server may take, failing with
+    /// [`Error::ConnectTimeout`] once it passes
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.retry.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum time to wait for the server to send something, the response once the request is
+    /// sent or the next chunk of its body, failing with [`Error::ReadTimeout`] once it passes.
+    /// Uploads, such as [`imp`](QuestDB::imp), must be sent within it too.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.retry.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum time queries, imports and exports may take as a whole, including retries and
+    /// reading the response, failing with [`Error::Timeout`] once it passes. A shorter
+    /// [`ExecOptions::timeout`] takes precedence for a query.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    /// use std::time::Duration;
+    ///
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .connect_timeout(Duration::from_secs(1))
+    ///     .read_timeout(Duration::from_secs(10))
+    ///     .total_timeout(Duration::from_secs(60))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn total_timeout(mut self, timeout: Duration) -> Self {
+        self.retry.total_timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum number of idle connections kept open to each server for reuse. Unlimited by
+    /// default, lower it when bursts leave more connections open than the steady load needs.
+    ///
+    /// The pool has no upper bound on connections in use, one is opened for every concurrent
+    /// request. [`max_in_flight`](Self::max_in_flight) bounds the concurrent requests, and with
+    /// them the size of the pool.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::{Priority, QuestDB};
+    /// use std::time::Duration;
+    ///
+    /// // At most 64 connections, of which 16 are kept for reuse for up to 5 minutes
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .max_in_flight(Priority::Interactive, 64)
+    ///     .pool_max_idle_per_host(16)
+    ///     .pool_idle_timeout(Some(Duration::from_secs(300)))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Time after which idle connections are closed, 90 seconds by default. `None` keeps them
+    /// open until the server closes them.
+    pub fn pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sends a header with every request, e.g. for a gateway in front of QuestDB. Invalid names
+    /// or values fail the [`build`](Self::build).
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    ///
+    /// let connection = QuestDB::builder("https://questdb.example.com")
+    ///     .default_header("X-Tenant-Id", "acme")
+    ///     .app_name("ingest-service/1.2")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn default_header(mut self, name: &str, value: &str) -> Self {
+        self.headers = self.headers.header(name, value);
+        self
+    }
+
+    /// Appends an identifier of the application to the `User-Agent` header, e.g.
+    /// `questdb-rs/0.1.4 ingest-service/1.2`, so the server's logs tell services apart
+    pub fn app_name(mut self, app_name: &str) -> Self {
+        self.user_agent = format!("{} {}", self.user_agent, app_name);
+        self
+    }
+
+    /// Replaces the value of the `User-Agent` header, `questdb-rs/<version>` by default
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = String::from(user_agent);
+        self
+    }
+
+    /// Trusts the PEM encoded certificate authorities in addition to the system ones, for servers
+    /// with a certificate issued by an internal CA. `pem` may hold several certificates. Needs the
+    /// `tls-native` or `tls-rustls` feature, [`build`](Self::build) fails without.
+    pub fn root_certificate(mut self, pem: &[u8]) -> Self {
+        self.root_certificates.push(pem.to_vec());
+        self
+    }
+
+    /// Same as [`root_certificate`](Self::root_certificate) with the certificates read from a
+    /// file when the connection is built
+    ///
+    /// # Example
+    /// ```no-test
+    /// let connection = QuestDB::builder("https://questdb.internal:9000")
+    ///     .root_certificate_file("/etc/ssl/internal-ca.pem")
+    ///     .build()?;
+    /// ```
+    pub fn root_certificate_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.root_certificate_files.push(path.into());
+        self
+    }
+
+    /// Accepts any certificate the server presents, including expired and self-signed ones.
+    /// Only meant for development, connections are open to interception. Needs a TLS feature
+    /// like [`root_certificate`](Self::root_certificate).
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Sends all requests through the HTTP(S) proxy at `url`, e.g. `http://proxy.corp:3128`.
+    /// Invalid urls fail the [`build`](Self::build).
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    ///
+    /// let connection = QuestDB::builder("http://questdb.internal:9000")
+    ///     .proxy("http://proxy.corp:3128")
+    ///     .proxy_auth("svc-ingest", "secret")
+    ///     .no_proxy("localhost,.internal.corp,10.0.0.0/8")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn proxy(mut self, url: &str) -> Self {
+        self.proxy = Some(String::from(url));
+        self
+    }
+
+    /// Credentials sent to the [proxy](Self::proxy) with HTTP Basic authentication
+    pub fn proxy_auth(mut self, username: &str, password: &str) -> Self {
+        self.proxy_auth = Some(BasicAuth {
+            username: String::from(username),
+            password: String::from(password),
+        });
+        self
+    }
+
+    /// Hosts reached without the [proxy](Self::proxy), a comma separated list of host names,
+    /// domains (`.corp` matches all hosts below it), IP addresses and networks in CIDR notation
+    pub fn no_proxy(mut self, hosts: &str) -> Self {
+        self.no_proxy = Some(String::from(hosts));
+        self
+    }
+
+    /// Whether to use the proxies of the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment
+    /// variables when no [proxy](Self::proxy) is set. Defaults to true.
+    pub fn system_proxy(mut self, system_proxy: bool) -> Self {
+        self.system_proxy = system_proxy;
+        self
+    }
+
+    /// Total time budget for an operation, including all of its retries. Read queries and exports
+    /// failing with a connection error or a 5xx status are retried with exponential backoff while
+    /// the budget lasts, and no attempt is allowed to run past it. Statements that may write and
+    /// imports are never retried. Defaults to zero, which disables retries.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    /// use std::time::Duration;
+    ///
+    /// // Each attempt may take up to 2 seconds, but the whole operation never more than 10
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .request_timeout(Duration::from_secs(2))
+    ///     .retry_timeout(Duration::from_secs(10))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn retry_timeout(mut self, timeout: Duration) -> Self {
+        self.retry.retry_timeout = timeout;
+        self
+    }
+
+    /// Maximum number of attempts at a read query or an export, including the first one. Retries
+    /// are enabled by this alone, and when a [retry timeout](Self::retry_timeout) is set too
+    /// whichever runs out first ends them. One disables retries.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    /// use std::time::Duration;
+    ///
+    /// // Up to 5 attempts, waiting 100ms, 200ms, 400ms and 800ms in between plus some jitter
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .max_attempts(5)
+    ///     .retry_backoff(Duration::from_millis(100), Duration::from_secs(2))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.retry.max_attempts = Some(max_attempts.max(1));
+        self
+    }
+
+    /// Wait before the first retry, doubled for every following one up to `max`. Defaults to 10ms
+    /// up to one second.
+    pub fn retry_backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.retry.backoff = base;
+        self.retry.max_backoff = max;
+        self
+    }
+
+    /// Upper bound for the random time added to every wait between attempts, so that clients
+    /// failing together don't retry together. Defaults to 10ms, zero disables it.
+    pub fn retry_jitter(mut self, jitter: Duration) -> Self {
+        self.retry.jitter = jitter;
+        self
+    }
+
+    /// Decides on retries with `policy` instead of the attempts and the backoff configured above,
+    /// see [`RetryPolicy`]
+    pub fn retry_policy<P: RetryPolicy + 'static>(mut self, policy: P) -> Self {
+        self.retry.policy = Some(CustomPolicy(Arc::new(policy)));
+        self
+    }
+
+    /// Adds a read replica of the server. Replicas only serve hedged reads, see
+    /// [`hedge_after`](Self::hedge_after), unless [reads are load
+    /// balanced](Self::load_balance_reads).
+    pub fn replica(mut self, url: &str) -> Self {
+        self.replicas.push(String::from(url));
+        self
+    }
+
+    /// Hedges read queries and exports: when the server hasn't answered after `delay` the same
+    /// request is also sent to one of the replicas, and whichever answers successfully first is
+    /// used while the other request is cancelled. Has no effect without replicas.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    /// use std::time::Duration;
+    ///
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .replica("http://192.168.1.38:9000")
+    ///     .hedge_after(Duration::from_millis(200))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn hedge_after(mut self, delay: Duration) -> Self {
+        self.hedge_after = Some(delay);
+        self
+    }
+
+    /// Sends read queries and exports to the replicas, spread according to `strategy`, while the
+    /// server only receives writes. A read falls back to the server when its replica is
+    /// unreachable or unhealthy. Replicas may lag behind the server, so reads right after a write
+    /// might not see it. Has no effect without replicas.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::{LoadBalancing, QuestDB};
+    ///
+    /// let connection = QuestDB::builder("http://primary:9000")
+    ///     .replica("http://replica-1:9000")
+    ///     .replica("http://replica-2:9000")
+    ///     .load_balance_reads(LoadBalancing::LeastLatency)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn load_balance_reads(mut self, strategy: LoadBalancing) -> Self {
+        self.load_balancing = Some(strategy);
+        self
+    }
+
+    /// Adds a server to fail over to, after the ones added before, when the server in use is
+    /// unreachable or unhealthy. Read queries and exports fail over on connection errors,
+    /// timeouts and 5xx statuses, other requests only when they couldn't connect. Requests then
+    /// stick to the server that answered, while the primary is probed in the background every
+    /// [probe interval](Self::failover_probe_interval) until it answers again.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    ///
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .failover("http://192.168.1.38:9000")
+    ///     .failover("http://192.168.1.39:9000")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn failover(mut self, url: &str) -> Self {
+        self.failover.push(String::from(url));
+        self
+    }
+
+    /// How often the primary is probed once requests failed over, 30 seconds by default
+    pub fn failover_probe_interval(mut self, interval: Duration) -> Self {
+        self.probe_interval = interval;
+        self
+    }
+
+    /// Stops sending requests to a server for `cool_down` after `failures` consecutive attempts
+    /// failed with a connection error, a timeout or a 5xx status. Requests fail fast with
+    /// [`Error::CircuitOpen`] in the meantime, then a single request probes whether the server
+    /// is back. Disabled by default.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    /// use std::time::Duration;
+    ///
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .circuit_breaker(5, Duration::from_secs(30))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn circuit_breaker(mut self, failures: u32, cool_down: Duration) -> Self {
+        self.circuit_breaker = Some((failures, cool_down));
+        self
+    }
+
+    /// Allows at most `max` requests of the given priority in flight at once, further requests
+    /// wait for one to finish. Each priority has its own limit, so a burst of batch exports
+    /// doesn't delay interactive queries. Unlimited by default, a `max` of 0 is treated as 1.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::{Priority, QuestDB};
+    ///
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .max_in_flight(Priority::Interactive, 32)
+    ///     .max_in_flight(Priority::Batch, 2)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn max_in_flight(mut self, priority: Priority, max: usize) -> Self {
+        self.limits.set(priority, max);
+        self
+    }
+
+    /// Allows at most `max` requests in flight at once whatever their priority, further requests
+    /// wait for one to finish. Bounds the connections a burst of queries opens and the workers
+    /// it occupies on the server. Unlimited by default, a `max` of 0 is treated as 1.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::{Priority, QuestDB};
+    ///
+    /// // Batch work never takes more than 4 of the 16 places
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .max_concurrent_requests(16)
+    ///     .max_in_flight(Priority::Batch, 4)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.limits.set_total(max);
+        self
+    }
+
+    /// Calls `callback` for every query, import or export taking `threshold` or longer, from the
+    /// call until its response was read completely.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    /// use std::time::Duration;
+    ///
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .slow_query(Duration::from_secs(1), |q| {
+    ///         eprintln!("slow query ({:?}): {}", q.stats.total_duration, q.query)
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn slow_query<F>(mut self, threshold: Duration, callback: F) -> Self
+    where
+        F: Fn(&SlowQuery) + Send + Sync + 'static,
+    {
+        self.slow_query = Some(SlowQueryHook {
+            threshold,
+            callback: Arc::new(callback),
+        });
+        self
+    }
+
+    /// Longest url a query may be sent in, in bytes. Queries that would produce a longer url are
+    /// POSTed to /exec in a form body instead, which avoids the url length limits of servers and
+    /// proxies. Defaults to 8000.
+    pub fn max_url_length(mut self, max_url_length: usize) -> Self {
+        self.max_url_length = max_url_length;
+        self
+    }
+
+    /// Options applied to every query, options passed to a call take precedence. Lets a team
+    /// enforce a default row limit or statement timeout in one place.
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::{ExecOptions, QuestDB};
+    /// use std::time::Duration;
+    ///
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .default_options(
+    ///         ExecOptions::new()
+    ///             .limit(10_000)
+    ///             .statement_timeout(Duration::from_secs(30)),
+    ///     )
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn default_options(mut self, options: ExecOptions) -> Self {
+        self.default_options = options;
+        self
+    }
+
+    /// Checks the lines of [`write_batch`](QuestDB::write_batch) against the columns of their
+    /// tables before sending them, so a typo or a wrong type fails locally with
+    /// [`Error::SchemaViolation`] instead of adding a column to the table. The columns are
+    /// fetched once per table and cached until the connection alters a table or the server reports
+    /// an error hinting at a change, see [`QuestDB::clear_schema_cache`].
+    ///
+    /// This covers lines written over HTTP, with [`write_batch`](QuestDB::write_batch) or
+    /// [`ilp_write`](QuestDB::ilp_write). The TCP [`Sender`](crate::ingress::Sender) is checked
+    /// against this connection with
+    /// [`SenderConfig::validate_writes`](crate::ingress::SenderConfig::validate_writes). SQL
+    /// `INSERT` statements are left to the server, which rejects unknown columns instead of
+    /// adding them.
+    pub fn validate_writes(mut self, validate_writes: bool) -> Self {
+        self.validate_writes = validate_writes;
+        self
+    }
+
+    /// Url of the health endpoint asked by [`QuestDB::status`], `/status` of the server by
+    /// default
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::QuestDB;
+    ///
+    /// let connection = QuestDB::builder("http://192.168.1.37:9000")
+    ///     .health_url("http://192.168.1.37:9003/status")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn health_url(mut self, url: &str) -> Self {
+        self.health_url = Some(String::from(url));
+        self
+    }
+
+    /// Url of the Prometheus endpoint read by [`QuestDB::metrics`], `/metrics` of the server by
+    /// default
+    pub fn metrics_url(mut self, url: &str) -> Self {
+        self.metrics_url = Some(String::from(url));
+        self
+    }
+
+    /// Keeps the last result of every read-only query and returns it in place of an error when
+    /// the server can't be reached or fails with a 5xx status, as long as the result is at most
+    /// `max_staleness` old. Lets dashboards keep rendering through brief outages.
+    ///
+    /// Stale results are flagged by [`QueryStats::stale`](crate::QueryStats::stale) with their
+    /// age, use [`QuestDB::exec_with_stats`] to tell them apart. Results are kept in memory for
+    /// every distinct query until they expire.
+    pub fn serve_stale(mut self, max_staleness: Duration) -> Self {
+        self.max_staleness = Some(max_staleness);
+        self
+    }
+
+    /// Creates the connection
+    pub fn build(self) -> Result<QuestDB, Error> {
+        #[allow(unused_mut)]
+        let mut token = self.token;
+        #[cfg(feature = "keyring")]
+        if let Some(entry) = &self.token_keyring {
+            token = Some(entry.get()?);
+        }
+        #[allow(unused_mut)]
+        let mut basic_auth = self.basic_auth;
+        #[cfg(feature = "keyring")]
+        if let Some((username, entry)) = &self.basic_auth_keyring {
+            basic_auth = Some(BasicAuth {
+                username: username.clone(),
+                password: entry.get()?,
+            });
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| Error::InvalidHeader(name.clone()))?;
+            let value =
+                HeaderValue::from_str(value).map_err(|_| Error::InvalidHeader(name.to_string()))?;
+            headers.append(name, value);
+        }
+        #[allow(unused_mut)]
+        let mut client = Client::builder()
+            .default_headers(headers)
+            .user_agent(&self.user_agent);
+        // The browser manages connections, proxies and certificates itself
+        #[cfg(target_arch = "wasm32")]
+        if self.proxy.is_some() {
+            return Err(Error::Unsupported("proxies"));
+        }
+        #[cfg(target_arch = "wasm32")]
+        if !self.root_certificates.is_empty()
+            || !self.root_certificate_files.is_empty()
+            || self.accept_invalid_certs
+        {
+            return Err(Error::Unsupported("custom certificates"));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.retry.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(max) = self.pool_max_idle_per_host {
+            client = client.pool_max_idle_per_host(max);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.pool_idle_timeout {
+            client = client.pool_idle_timeout(timeout);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if !self.system_proxy {
+            client = client.no_proxy();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(url) = &self.proxy {
+            let mut proxy = Proxy::all(url.as_str())
+                .map_err(|e| Error::InvalidUrl(format!("{}: {}", redact::url(url), e)))?;
+            if let Some(auth) = &self.proxy_auth {
+                proxy = proxy.basic_auth(&auth.username, &auth.password);
+            }
+            let no_proxy = self.no_proxy.as_deref().and_then(NoProxy::from_string);
+            client = client.proxy(proxy.no_proxy(no_proxy));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut pems = self.root_certificates;
+        #[cfg(not(target_arch = "wasm32"))]
+        for path in &self.root_certificate_files {
+            pems.push(std::fs::read(path).map_err(|source| Error::FileIo {
+                path: path.clone(),
+                operation: "read",
+                source,
+            })?);
+        }
+        #[cfg(all(
+            any(feature = "tls-native", feature = "tls-rustls"),
+            not(target_arch = "wasm32")
+        ))]
+        {
+            client = client.danger_accept_invalid_certs(self.accept_invalid_certs);
+            for pem in &pems {
+                for certificate in reqwest::Certificate::from_pem_bundle(pem)? {
+                    client = client.add_root_certificate(certificate);
+                }
+            }
+        }
+        #[cfg(not(any(feature = "tls-native", feature = "tls-rustls", target_arch = "wasm32")))]
+        if !pems.is_empty() || self.accept_invalid_certs {
+            return Err(Error::FeatureDisabled("tls-native or tls-rustls"));
+        }
+
+        let failover = Failover::new(&self.url, self.failover, self.probe_interval);
+        let balancer = self
+            .load_balancing
+            .and_then(|strategy| Balancer::new(strategy, self.replicas.clone()));
+        Ok(QuestDB::from_inner(Inner {
+            client: client.build()?,
+            url: self.url,
+            auth: TokenState::new(token, self.token_refresher),
+            basic_auth,
+            read_only: self.read_only,
+            retry: self.retry,
+            hedge: HedgeConfig::new(self.replicas, self.hedge_after),
+            breaker: self
+                .circuit_breaker
+                .map(|(failures, cool_down)| CircuitBreaker::new(failures, cool_down)),
+            failover,
+            balancer,
+            limits: self.limits,
+            slow_query: self.slow_query,
+            max_url_length: self.max_url_length,
+            default_options: self.default_options,
+            validate_writes: self.validate_writes,
+            health_url: self.health_url,
+            metrics_url: self.metrics_url,
+            stale: self.max_staleness.map(StaleCache::new),
+            metadata: Metadata::default(),
+        }))
+    }
+}
+
+#[cfg(all(test, any(feature = "tls-native", feature = "tls-rustls")))]
+mod tests {
+    use crate::QuestDB;
+
+    /// Two self-signed certificate authorities
+    const BUNDLE: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBfzCCASWgAwIBAgIUFZnmKOFpzDO46Uzdi0mQ1I6ysbYwCgYIKoZIzj0EAwIw
+FDESMBAGA1UEAwwJdGVzdC1jYS0xMCAXDTI2MTAxNjAyNDMzNloYDzIxMjYwOTIy
+MDI0MzM2WjAUMRIwEAYDVQQDDAl0ZXN0LWNhLTEwWTATBgcqhkjOPQIBBggqhkjO
+PQMBBwNCAASNpjKsTCC9UOiPpGJ46SRpdMLAvp9lD8e9Y6MVqGy19rL3VNVj1g89
+6D/lurMmA4CpZ3JBc8p24Zw8/0N2Lha+o1MwUTAdBgNVHQ4EFgQU8PI5xGaqAYbI
+TmFkPSDD6TiIlV8wHwYDVR0jBBgwFoAU8PI5xGaqAYbITmFkPSDD6TiIlV8wDwYD
+VR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiEA99Ceg7Pjvqq5esBhooTy
+19I5TcscrN9Jygekn/3c2M8CIE1eSvf2yYlGijOwLFgH5ZCtCeH+MCa9j2xyZFnu
+1EOE
+-----END CERTIFICATE-----
+-----BEGIN CERTIFICATE-----
+MIIBfzCCASWgAwIBAgIUY1/pcSBwI1QHZ8z/cDjgJqKufbYwCgYIKoZIzj0EAwIw
+FDESMBAGA1UEAwwJdGVzdC1jYS0yMCAXDTI2MTAxNjAyNDMzNloYDzIxMjYwOTIy
+MDI0MzM2WjAUMRIwEAYDVQQDDAl0ZXN0LWNhLTIwWTATBgcqhkjOPQIBBggqhkjO
+PQMBBwNCAATQrM9OpxTgoEqxIqC4IZWr7sDBrR80XVP2heVSR4y2OaswDbdiStkQ
+bKkj/iANlgLYrE3E1PbJcem1v+uG7RSeo1MwUTAdBgNVHQ4EFgQUsIauyxAXs3Hg
+hpjfuPp5D3VGdB8wHwYDVR0jBBgwFoAUsIauyxAXs3HghpjfuPp5D3VGdB8wDwYD
+VR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiAw5MFY1R1p67Jr9uYkp5ec
+w+0TKHwJYsAGVM6iZ9q5KAIhALYuvzaauB+L4MHqZE1CvbGQoSFvhgecl0Uk/71s
+FNRk
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn test_root_certificates() {
+        let path = std::env::temp_dir().join("questdb-test-ca-bundle.pem");
+        std::fs::write(&path, BUNDLE).unwrap();
+
+        QuestDB::builder("https://questdb.internal:9000")
+            .root_certificate(BUNDLE.as_bytes())
+            .root_certificate_file(&path)
+            .build()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let res = QuestDB::builder("https://questdb.internal:9000")
+            .root_certificate_file(&path)
+            .build();
+        assert!(matches!(
+            res,
+            Err(crate::Error::FileIo { path: p, operation: "read", .. }) if p == path
+        ));
+    }
+
+    #[cfg(feature = "keyring")]
+    #[test]
+    fn test_keyring() {
+        use crate::{Error, Keyring};
+
+        // The mock store keeps no secrets between entries, every read finds none
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        let missing = |res: Result<QuestDB, Error>| {
+            matches!(res, Err(Error::KeyringError(keyring::Error::NoEntry)))
+        };
+
+        assert!(missing(
+            QuestDB::builder("http://localhost:9000")
+                .token_from_keyring(Keyring::new("questdb", "token"))
+                .build()
+        ));
+        assert!(missing(
+            QuestDB::builder("http://localhost:9000")
+                .basic_auth_from_keyring("admin", Keyring::new("questdb", "admin"))
+                .build()
+        ));
+        assert!(matches!(
+            Keyring::new("questdb", "admin").get(),
+            Err(Error::KeyringError(keyring::Error::NoEntry))
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{Error, ExecOptions, QuestDB};
+use serde::de::DeserializeOwned;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::task::JoinHandle;
+
+/// Numbers the queries started with [`QuestDB::exec_cancellable`] within this process
+static NEXT_TAG: AtomicU64 = AtomicU64::new(0);
+
+/// Query running in a background task which can be cancelled on the server, created with
+/// [`QuestDB::exec_cancellable`]
+///
+/// The query text is prefixed with a comment identifying it, so it can be found among the
+/// running queries reported by `query_activity()` without knowing its id up front.
+///
+/// # Example
+/// ```no-test
+/// let handle = connection
+///     .exec_cancellable::<TestData>("select * from readings", &ExecOptions::new());
+///
+/// tokio::time::sleep(Duration::from_secs(30)).await;
+/// if !handle.is_finished() {
+///     handle.cancel().await?;
+/// }
+/// // Fails with the error the server reports for a cancelled query
+/// let rows = handle.result().await;
+/// ```
+#[derive(Debug)]
+pub struct QueryHandle<T> {
+    connection: QuestDB,
+    tag: String,
+    task: JoinHandle<Result<Vec<T>, Error>>,
+}
+
+impl<T> QueryHandle<T> {
+    /// Id the server gave the query, `None` when it isn't running (yet or anymore)
+    pub async fn query_id(&self) -> Result<Option<i64>, Error> {
+        let running: Vec<(i64, String)> = self
+            .connection
+            .query("select query_id, query from query_activity()")
+            .fetch()
+            .await?;
+
+        // The lookup itself shows up too, but it doesn't start with the tag
+        Ok(running
+            .into_iter()
+            .find(|(_, query)| query.starts_with(&self.tag))
+            .map(|(id, _)| id))
+    }
+
+    /// Asks the server to cancel the query, returns `false` when it wasn't running
+    pub async fn cancel(&self) -> Result<bool, Error> {
+        match self.query_id().await? {
+            Some(id) => self.connection.cancel_query(id).await.map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Whether the query returned, successfully or not
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Waits for the rows of the query
+    pub async fn result(self) -> Result<Vec<T>, Error> {
+        match self.task.await {
+            Ok(res) => res,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+impl QuestDB {
+    /// Starts a query in a background task and returns a handle to cancel it or wait for its
+    /// rows, see [`QueryHandle`]
+    pub fn exec_cancellable<T: DeserializeOwned + Send + 'static>(
+        &self,
+        query: &str,
+        options: &ExecOptions,
+    ) -> QueryHandle<T> {
+        let tag = format!(
+            "/* questdb-rs {}-{} */",
+            std::process::id(),
+            NEXT_TAG.fetch_add(1, Ordering::Relaxed)
+        );
+        let connection = self.clone();
+        let query = format!("{} {}", tag, query);
+        let options = options.clone();
+        #[cfg(feature = "runtime-agnostic")]
+        let _reactor = crate::rt::enter();
+        let task = tokio::spawn(async move { connection.exec_with(&query, &options).await });
+
+        QueryHandle {
+            connection: self.clone(),
+            tag,
+            task,
+        }
+    }
+
+    /// Cancels a running query by the id `query_activity()` reports for it
+    ///
+    /// # Example
+    /// ```no-test
+    /// let running: Vec<(i64, String)> = connection
+    ///     .query("select query_id, query from query_activity()")
+    ///     .fetch()
+    ///     .await?;
+    /// for (id, _) in running.iter().filter(|(_, q)| q.contains("from trades")) {
+    ///     connection.cancel_query(*id).await?;
+    /// }
+    /// ```
+    pub async fn cancel_query(&self, query_id: i64) -> Result<(), Error> {
+        self.execute(&format!("CANCEL QUERY {}", query_id))
+            .await
+            .map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ExecOptions, QuestDB};
+
+    #[tokio::test]
+    async fn test_tagged_query() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::new(&format!("http://{}", listener.local_addr().unwrap()));
+        let first = connection.exec_cancellable::<(i64,)>("select 1", &ExecOptions::new());
+        let second = connection.exec_cancellable::<(i64,)>("select 1", &ExecOptions::new());
+
+        assert_ne!(first.tag, second.tag);
+        assert!(crate::sql::classify(&format!("{} select 1", first.tag)).is_read_only());
+        drop(listener);
+        assert!(first.result().await.is_err());
+    }
+}
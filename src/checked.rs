@@ -0,0 +1,94 @@
+use crate::{Error, QuestDB};
+use serde::de::{DeserializeOwned, Error as _};
+use serde_json::Value;
+
+/// Conversion of a column read as `S` into a field, a field that isn't an `Option` rejects
+/// nulls. Fields of any other type don't compile, which is how `query_as!` checks types.
+pub trait FromColumn<S>: Sized {
+    fn from_column(value: S) -> Option<Self>;
+}
+
+impl<T> FromColumn<T> for T {
+    fn from_column(value: T) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl<T> FromColumn<Option<T>> for T {
+    fn from_column(value: Option<T>) -> Option<Self> {
+        value
+    }
+}
+
+/// Reads the column at `index` of a row as `S` and converts it into the field
+pub fn column<S: DeserializeOwned, T: FromColumn<S>>(
+    row: &mut [Value],
+    index: usize,
+    name: &str,
+) -> Result<T, Error> {
+    let value = row
+        .get_mut(index)
+        .map(Value::take)
+        .ok_or_else(|| Error::ColumnNotFound(String::from(name)))?;
+    T::from_column(serde_json::from_value(value)?).ok_or_else(|| {
+        Error::DeserializeError(serde_json::Error::custom(format!(
+            "column '{}' is null, make the field an Option",
+            name
+        )))
+    })
+}
+
+/// Runs the query and maps every row, given as its values in column order
+pub async fn query_as<T>(
+    connection: &QuestDB,
+    query: &str,
+    map: impl Fn(&mut [Value]) -> Result<T, Error>,
+) -> Result<Vec<T>, Error> {
+    let rows: Vec<Vec<Value>> = connection.query(query).nm(true).fetch().await?;
+    rows.into_iter().map(|mut row| map(&mut row)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::column;
+    use crate::Timestamp;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq)]
+    struct Reading {
+        id: i32,
+        sensor: Option<String>,
+        ts: Timestamp,
+    }
+
+    #[test]
+    fn test_column() {
+        let mut row = vec![json!(1), json!(null), json!("1970-01-01T00:00:00.000000Z")];
+        let read = |row: &mut [serde_json::Value]| -> Result<Reading, crate::Error> {
+            Ok(Reading {
+                id: column::<Option<i32>, _>(row, 0, "id")?,
+                sensor: column::<Option<String>, _>(row, 1, "sensor")?,
+                ts: column::<Option<Timestamp>, _>(row, 2, "ts")?,
+            })
+        };
+        assert_eq!(
+            read(&mut row).unwrap(),
+            Reading {
+                id: 1,
+                sensor: None,
+                ts: Timestamp::from_micros(0)
+            }
+        );
+
+        let mut row = vec![json!(null), json!(null), json!(null)];
+        assert!(read(&mut row).is_err());
+    }
+
+    #[test]
+    fn test_query_as() {
+        // Checked against the schema cached in .questdb
+        let connection = crate::QuestDB::new("http://127.0.0.1:9000");
+        let rows = crate::query_as!(Reading, connection, "select id, sensor, ts from readings");
+        drop(rows);
+    }
+}
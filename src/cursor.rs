@@ -0,0 +1,118 @@
+use crate::options::{ExecOptions, Limit};
+use crate::row::{self, ColumnMeta, Dataset};
+use crate::{Error, QuestDB};
+use futures_util::stream::{self, Stream};
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+/// Walks the result of a query page by page, see [`QuestDB::cursor`]
+///
+/// Every page is a request for the next range of rows (`limit=lo,hi`). The column metadata is
+/// only requested with the first page. The query must return its rows in a stable order, e.g.
+/// ordered by the designated timestamp, or pages may overlap or miss rows.
+///
+/// # Example
+/// ```no-test
+/// let mut cursor = connection.cursor::<Reading>("select * from readings order by ts", 10_000);
+/// while let Some(page) = cursor.next_page().await? {
+///     println!("{} readings from row {}", page.len(), cursor.offset() - page.len() as u64);
+/// }
+/// ```
+pub struct Cursor<'a, T> {
+    connection: &'a QuestDB,
+    query: String,
+    options: ExecOptions,
+    page_size: u64,
+    /// Rows read so far
+    offset: u64,
+    /// Metadata of the first page
+    columns: Option<Vec<ColumnMeta>>,
+    done: bool,
+    rows: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: DeserializeOwned> Cursor<'a, T> {
+    /// Options for every page, a limit set there is ignored
+    pub fn options(mut self, options: ExecOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Starts at row `offset` instead of the first row
+    pub fn offset_by(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Number of rows read so far, including the skipped ones
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Reads the next page, `None` once all rows were read
+    pub async fn next_page(&mut self) -> Result<Option<Vec<T>>, Error> {
+        if self.done {
+            return Ok(None);
+        }
+        let mut options = self.options.clone();
+        options.limit = Some(Limit::Range(self.offset, self.offset + self.page_size));
+        options.nm = Some(self.columns.is_some());
+
+        let (res, _stats) = self.connection.exec_json(&self.query, &options).await?;
+        let mut res: Dataset = serde_json::from_value(res)?;
+        let columns = self
+            .columns
+            .get_or_insert_with(|| std::mem::take(&mut res.columns));
+        let rows = res
+            .dataset
+            .into_iter()
+            .enumerate()
+            .map(|(i, values)| row::from_row(self.offset as usize + i, columns, values))
+            .collect::<Result<Vec<T>, _>>()?;
+
+        // A short page is the last one
+        self.offset += rows.len() as u64;
+        self.done = (rows.len() as u64) < self.page_size;
+        match rows.is_empty() {
+            true => Ok(None),
+            false => Ok(Some(rows)),
+        }
+    }
+
+    /// Turns the cursor into a stream of rows, requesting the next page once the rows of the
+    /// previous one were taken
+    pub fn into_stream(self) -> impl Stream<Item = Result<T, Error>> + 'a
+    where
+        T: 'a,
+    {
+        stream::try_unfold(
+            (self, VecDeque::new()),
+            |(mut cursor, mut page)| async move {
+                if page.is_empty() {
+                    match cursor.next_page().await? {
+                        Some(rows) => page = rows.into(),
+                        None => return Ok(None),
+                    }
+                }
+                Ok(page.pop_front().map(|row| (row, (cursor, page))))
+            },
+        )
+    }
+}
+
+impl QuestDB {
+    /// Pages through the result of the query, `page_size` rows per request, see [`Cursor`]
+    pub fn cursor<T: DeserializeOwned>(&self, query: &str, page_size: u64) -> Cursor<'_, T> {
+        Cursor {
+            connection: self,
+            query: String::from(query),
+            options: ExecOptions::new(),
+            page_size: page_size.max(1),
+            offset: 0,
+            columns: None,
+            done: false,
+            rows: PhantomData,
+        }
+    }
+}
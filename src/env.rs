@@ -0,0 +1,178 @@
+use crate::dsn::{parse_bool, parse_duration};
+use crate::{Error, QuestDB, QuestDBBuilder};
+use reqwest::Url;
+
+impl QuestDBBuilder {
+    /// Creates a builder from environment variables, so services can be configured without
+    /// code changes. Settings can still be changed on the builder afterwards.
+    ///
+    /// * `QUESTDB_URL` - required, an http(s) url or a connection string, see
+    ///   [`from_dsn`](Self::from_dsn)
+    /// * `QUESTDB_USER` and `QUESTDB_PASSWORD` - HTTP Basic authentication
+    /// * `QUESTDB_TOKEN` - [`token`](Self::token)
+    /// * `QUESTDB_TIMEOUT` - [`request_timeout`](Self::request_timeout), e.g. `5s`
+    /// * `QUESTDB_CONNECT_TIMEOUT` - [`connect_timeout`](Self::connect_timeout)
+    /// * `QUESTDB_RETRY_TIMEOUT` - [`retry_timeout`](Self::retry_timeout)
+    /// * `QUESTDB_READ_TIMEOUT` - [`read_timeout`](Self::read_timeout)
+    /// * `QUESTDB_TOTAL_TIMEOUT` - [`total_timeout`](Self::total_timeout)
+    /// * `QUESTDB_READ_ONLY` - [`read_only`](Self::read_only), `true` or `false`
+    /// * `QUESTDB_TLS_ROOTS` - [`root_certificate_file`](Self::root_certificate_file)
+    /// * `QUESTDB_TLS_VERIFY` - `unsafe_off` to [accept any
+    ///   certificate](Self::danger_accept_invalid_certs)
+    ///
+    /// Variables that are set but invalid fail with [`Error::InvalidEnv`].
+    pub fn from_env() -> Result<Self, Error> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, Error> {
+        let invalid = |name: &'static str, reason: String| Error::InvalidEnv { name, reason };
+
+        let url =
+            var("QUESTDB_URL").ok_or_else(|| invalid("QUESTDB_URL", String::from("not set")))?;
+        let parsed = Url::parse(&url).map_err(|e| invalid("QUESTDB_URL", e.to_string()))?;
+
+        let mut builder = match parsed.scheme() {
+            "questdb" => QuestDBBuilder::from_dsn(&url)?,
+            "http" | "https" => QuestDB::builder(url.trim_end_matches('/')),
+            scheme => {
+                return Err(invalid(
+                    "QUESTDB_URL",
+                    format!("unsupported scheme '{}'", scheme),
+                ))
+            }
+        };
+
+        let duration = |name: &'static str| match var(name) {
+            Some(value) => match parse_duration(&value) {
+                Some(duration) => Ok(Some(duration)),
+                None => Err(invalid(
+                    name,
+                    format!("'{}' isn't a duration such as 5s", value),
+                )),
+            },
+            None => Ok(None),
+        };
+        if let Some(user) = var("QUESTDB_USER") {
+            let password = var("QUESTDB_PASSWORD").unwrap_or_default();
+            builder = builder.basic_auth(&user, &password);
+        }
+        if let Some(token) = var("QUESTDB_TOKEN") {
+            builder = builder.token(&token);
+        }
+        if let Some(timeout) = duration("QUESTDB_TIMEOUT")? {
+            builder = builder.request_timeout(timeout);
+        }
+        if let Some(timeout) = duration("QUESTDB_CONNECT_TIMEOUT")? {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = duration("QUESTDB_RETRY_TIMEOUT")? {
+            builder = builder.retry_timeout(timeout);
+        }
+        if let Some(timeout) = duration("QUESTDB_READ_TIMEOUT")? {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(timeout) = duration("QUESTDB_TOTAL_TIMEOUT")? {
+            builder = builder.total_timeout(timeout);
+        }
+        if let Some(path) = var("QUESTDB_TLS_ROOTS") {
+            builder = builder.root_certificate_file(path);
+        }
+        if let Some(value) = var("QUESTDB_TLS_VERIFY") {
+            builder = match value.as_str() {
+                "on" => builder,
+                "unsafe_off" => builder.danger_accept_invalid_certs(true),
+                _ => {
+                    return Err(invalid(
+                        "QUESTDB_TLS_VERIFY",
+                        format!("'{}' isn't on or unsafe_off", value),
+                    ))
+                }
+            };
+        }
+        if let Some(value) = var("QUESTDB_READ_ONLY") {
+            let read_only = parse_bool(&value)
+                .ok_or_else(|| invalid("QUESTDB_READ_ONLY", format!("'{}' isn't a bool", value)))?;
+            builder = builder.read_only(read_only);
+        }
+        Ok(builder)
+    }
+}
+
+impl QuestDB {
+    /// Creates a connection from environment variables, see [`QuestDBBuilder::from_env`]
+    ///
+    /// # Example
+    /// ```no-test
+    /// // QUESTDB_URL=https://questdb.example.com QUESTDB_TIMEOUT=5s ./service
+    /// let connection = QuestDB::from_env()?;
+    /// ```
+    pub fn from_env() -> Result<Self, Error> {
+        QuestDBBuilder::from_env()?.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, QuestDBBuilder};
+    use std::collections::HashMap;
+
+    fn from_vars(vars: &[(&str, &str)]) -> Result<QuestDBBuilder, Error> {
+        let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+        QuestDBBuilder::from_vars(|name| vars.get(name).map(|v| v.to_string()))
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let connection = from_vars(&[
+            ("QUESTDB_URL", "http://db.example.com:9000"),
+            ("QUESTDB_USER", "admin"),
+            ("QUESTDB_PASSWORD", "quest"),
+            ("QUESTDB_TIMEOUT", "5s"),
+        ])
+        .unwrap()
+        .build()
+        .unwrap();
+        assert_eq!(
+            connection.to_string(),
+            "QuestDB(http://db.example.com:9000)"
+        );
+        let debug = format!("{:?}", connection);
+        assert!(debug.contains("request_timeout: Some(5s)"));
+        assert!(debug.contains(r#"BasicAuth { username: "admin", password: *** }"#));
+
+        let connection = from_vars(&[("QUESTDB_URL", "questdb://localhost?tls=true")])
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(connection.to_string(), "QuestDB(https://localhost:9000)");
+
+        assert!(matches!(
+            from_vars(&[]),
+            Err(Error::InvalidEnv {
+                name: "QUESTDB_URL",
+                ..
+            })
+        ));
+        assert!(matches!(
+            from_vars(&[
+                ("QUESTDB_URL", "http://localhost"),
+                ("QUESTDB_TIMEOUT", "5")
+            ]),
+            Err(Error::InvalidEnv {
+                name: "QUESTDB_TIMEOUT",
+                ..
+            })
+        ));
+        assert!(matches!(
+            from_vars(&[
+                ("QUESTDB_URL", "http://localhost"),
+                ("QUESTDB_TLS_VERIFY", "off")
+            ]),
+            Err(Error::InvalidEnv {
+                name: "QUESTDB_TLS_VERIFY",
+                ..
+            })
+        ));
+    }
+}
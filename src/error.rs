@@ -1,19 +1,135 @@
 use std::fmt;
 use serde::Deserialize;
 
+/// Error reported by the server for a query
 #[derive(Debug, Deserialize)]
+#[serde(from = "ServerError")]
 pub struct SQLError {
     query: String,
     error: String,
     position: i32,
+    kind: ServerErrorKind,
 }
 
+/// Error as sent by the server, classified once when it becomes a [`SQLError`]
+#[derive(Deserialize)]
+struct ServerError {
+    query: String,
+    error: String,
+    position: i32,
+}
+
+impl From<ServerError> for SQLError {
+    fn from(e: ServerError) -> Self {
+        SQLError { kind: ServerErrorKind::from_message(&e.error), query: e.query, error: e.error, position: e.position }
+    }
+}
+
+impl SQLError {
+    /// Query that failed
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Message of the server
+    pub fn message(&self) -> &str {
+        &self.error
+    }
+
+    /// Position in the query the error was found at
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// Class of the error, as far as it could be told from the message
+    pub fn kind(&self) -> ServerErrorKind {
+        self.kind
+    }
+}
+
+/// Class of an error reported by the server, lets applications handle failures without matching
+/// on messages
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServerErrorKind {
+    TableNotFound,
+    TableExists,
+    ColumnNotFound,
+    TypeMismatch,
+    OutOfOrder,
+    PermissionDenied,
+    Other,
+}
+
+impl ServerErrorKind {
+    fn from_message(message: &str) -> Self {
+        let message = message.to_ascii_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+        // Short terms only count as whole words, "o3" must not match "o3maxlag"
+        let has_word = |word: &str| message.split(|c: char| !c.is_ascii_alphanumeric()).any(|w| w == word);
+
+        if has(&["table does not exist", "table not found"]) {
+            ServerErrorKind::TableNotFound
+        } else if has(&["table already exists"]) {
+            ServerErrorKind::TableExists
+        } else if has(&["invalid column", "column does not exist", "column not found"]) {
+            ServerErrorKind::ColumnNotFound
+        } else if has(&["inconvertible", "cannot be cast", "type mismatch"]) {
+            ServerErrorKind::TypeMismatch
+        } else if has(&["out of order", "out-of-order"]) || has_word("o3") {
+            ServerErrorKind::OutOfOrder
+        } else if has(&["permission denied", "access denied", "not authorized", "unauthorized"]) {
+            ServerErrorKind::PermissionDenied
+        } else {
+            ServerErrorKind::Other
+        }
+    }
+}
+
+/// Errors of the client
+///
+/// New variants may be added, e.g. by features of the crate, so matches need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     ExecError(reqwest::Error),
     DeserializeError(serde_json::error::Error),
     FileError(std::io::Error),
+    FileIo { path: std::path::PathBuf, operation: &'static str, source: std::io::Error },
     SQLError(SQLError),
+    AuthError(String),
+    WriteForbidden(String),
+    InvalidUrl(String),
+    InvalidHeader(String),
+    InvalidEnv { name: &'static str, reason: String },
+    InvalidTimestamp(String),
+    InvalidInterval(String),
+    InvalidVersion(String),
+    HttpError { status: u16, message: String },
+    SchemaMismatch { table: String, differences: Vec<String> },
+    TableNotFound(String),
+    TableExists(String),
+    ColumnNotFound(String),
+    RowCount { expected: &'static str, got: usize },
+    MissingParameter { index: usize, given: usize },
+    UnalignedRange(String),
+    InvalidLine(String),
+    BatchFull(String),
+    QueueFull(usize),
+    SenderClosed,
+    SchemaViolation { line: usize, message: String },
+    WriteRejected { code: String, message: String, lines: Vec<usize>, error_id: Option<String> },
+    ConnectionError(std::io::Error),
+    Timeout(std::time::Duration),
+    ConnectTimeout(std::time::Duration),
+    ReadTimeout(std::time::Duration),
+    CircuitOpen { server: String, retry_after: std::time::Duration },
+    FeatureDisabled(&'static str),
+    Unsupported(&'static str),
+    #[cfg(feature = "keyring")]
+    KeyringError(keyring::Error),
+    #[cfg(feature = "sqlx-adapter")]
+    PgWireError(sqlx::Error),
 }
 
 impl std::error::Error for Error {}
@@ -25,6 +141,40 @@ impl std::fmt::Display for Error {
             Error::DeserializeError(err) => format!("Error deserializing json: {}", err),
             Error::SQLError(err) => format!("Error '{}' with '{}' at position '{}'", err.error, err.query, err.position),
             Error::FileError(err) => format!("Failed to open file: {}", err),
+            Error::FileIo { path, operation, source } => format!("Failed to {} '{}': {}", operation, path.display(), source),
+            Error::AuthError(err) => format!("Authentication failed: {}", err),
+            Error::ConnectionError(err) => format!("Connection error: {}", err),
+            Error::Timeout(timeout) => format!("Query didn't finish within {:?}", timeout),
+            Error::ConnectTimeout(timeout) => format!("Couldn't connect within {:?}", timeout),
+            Error::ReadTimeout(timeout) => format!("Server sent nothing for {:?}", timeout),
+            Error::CircuitOpen { server, retry_after } => format!("{} failed repeatedly, not sending requests for {:?}", server, retry_after),
+            Error::FeatureDisabled(feature) => format!("This needs the {} feature", feature),
+            Error::Unsupported(feature) => format!("Not supported on this target: {}", feature),
+            Error::InvalidUrl(err) => format!("Invalid url {}", err),
+            Error::InvalidHeader(name) => format!("Invalid header '{}'", name),
+            Error::InvalidEnv { name, reason } => format!("Invalid environment variable {}: {}", name, reason),
+            Error::InvalidTimestamp(ts) => format!("Invalid timestamp '{}'", ts),
+            Error::InvalidInterval(interval) => format!("Invalid interval '{}'", interval),
+            Error::InvalidVersion(build) => format!("Invalid server version '{}'", build),
+            Error::HttpError { status, message } => format!("Server responded with status {}: {}", status, message),
+            Error::SchemaMismatch { table, differences } => format!("Table '{}' doesn't match its schema: {}", table, differences.join(", ")),
+            Error::TableNotFound(table) => format!("Table '{}' does not exist", table),
+            Error::TableExists(table) => format!("Table '{}' already exists", table),
+            Error::ColumnNotFound(column) => format!("Column '{}' does not exist", column),
+            Error::RowCount { expected, got } => format!("Expected {}, got {}", expected, if *got > 1 { String::from("several") } else { got.to_string() }),
+            Error::MissingParameter { index, given } => format!("Query refers to ${} but {} parameters were given", index, given),
+            Error::UnalignedRange(err) => format!("Range doesn't align with partitions: {}", err),
+            Error::InvalidLine(line) => format!("Invalid line '{}'", line),
+            Error::BatchFull(limit) => format!("Batch is full, it is limited to {}", limit),
+            Error::QueueFull(capacity) => format!("Queue is full, it holds at most {} batches", capacity),
+            Error::SenderClosed => String::from("The sender's background task stopped"),
+            Error::SchemaViolation { line, message } => format!("Line {} doesn't match its table: {}", line, message),
+            Error::WriteRejected { code, message, .. } => format!("Write rejected ({}): {}", code, message),
+            Error::WriteForbidden(op) => format!("'{}' is not allowed on a read-only connection", op),
+            #[cfg(feature = "keyring")]
+            Error::KeyringError(err) => format!("Credential store error: {}", err),
+            #[cfg(feature = "sqlx-adapter")]
+            Error::PgWireError(err) => format!("PostgreSQL wire protocol error: {}", err),
         })
     }
 }
@@ -45,4 +195,43 @@ impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Error {
         Error::FileError(err)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "keyring")]
+impl From<keyring::Error> for Error {
+    fn from(err: keyring::Error) -> Error {
+        Error::KeyringError(err)
+    }
+}
+
+#[cfg(feature = "sqlx-adapter")]
+impl From<sqlx::Error> for Error {
+    fn from(err: sqlx::Error) -> Error {
+        Error::PgWireError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SQLError, ServerError, ServerErrorKind};
+
+    #[test]
+    fn test_server_error_kind() {
+        let kind = |error: &str| SQLError::from(ServerError { query: String::new(), error: String::from(error), position: 0 }).kind();
+
+        assert_eq!(kind("table does not exist [table=readings]"), ServerErrorKind::TableNotFound);
+        assert_eq!(kind("Invalid column: humidity"), ServerErrorKind::ColumnNotFound);
+        assert_eq!(kind("inconvertible types: STRING -> INT [from=temp, to=temp]"), ServerErrorKind::TypeMismatch);
+        assert_eq!(kind("cannot insert rows out of order to non-partitioned table"), ServerErrorKind::OutOfOrder);
+        assert_eq!(kind("permission denied"), ServerErrorKind::PermissionDenied);
+        assert_eq!(kind("o3 commit failed"), ServerErrorKind::OutOfOrder);
+        assert_eq!(kind("unexpected token: FORM"), ServerErrorKind::Other);
+        assert_eq!(kind("invalid value for o3MaxLag"), ServerErrorKind::Other);
+        assert_eq!(kind("unknown function name: co3(LONG)"), ServerErrorKind::Other);
+        assert_eq!(kind("unexpected argument for function: abs"), ServerErrorKind::Other);
+
+        let error: SQLError = serde_json::from_str(r#"{"query":"select * from nope","error":"table does not exist [table=nope]","position":14}"#).unwrap();
+        assert_eq!(error.kind(), ServerErrorKind::TableNotFound);
+        assert_eq!(error.position(), 14);
+    }
+}
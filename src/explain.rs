@@ -0,0 +1,198 @@
+use crate::{Error, QuestDB};
+use std::fmt;
+
+/// Indentation of a child below its parent in the plan text, attributes are indented by less
+const CHILD_INDENT: usize = 4;
+
+/// Step of a query plan, such as a filter, a sort or a scan of a table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlanNode {
+    /// Name of the step, e.g. `Sort light` or `Frame forward scan on: readings`
+    pub name: String,
+    /// Details of the step, e.g. `keys` = `[ts desc]`
+    pub attributes: Vec<(String, String)>,
+    /// Steps feeding this one
+    pub children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    /// Value of the attribute called `key`
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// This step and all steps below it, depth first
+    pub fn iter(&self) -> impl Iterator<Item = &PlanNode> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = depth * CHILD_INDENT;
+        writeln!(f, "{:indent$}{}", "", self.name)?;
+        for (key, value) in &self.attributes {
+            writeln!(f, "{:indent$}  {}: {}", "", key, value)?;
+        }
+        self.children.iter().try_for_each(|c| c.write(f, depth + 1))
+    }
+}
+
+/// Plan of a query as reported by `EXPLAIN`, see [`QuestDB::explain`]
+///
+/// Displays as the indented text QuestDB reports, so plans can be logged or compared.
+///
+/// # Example
+/// ```
+/// use questdb::QueryPlan;
+///
+/// let plan = QueryPlan::parse(&[
+///     "Sort light",
+///     "  keys: [ts desc]",
+///     "    DataFrame",
+///     "        Row forward scan",
+///     "        Frame forward scan on: readings",
+/// ]);
+/// assert_eq!(plan.root.name, "Sort light");
+/// assert_eq!(plan.root.attribute("keys"), Some("[ts desc]"));
+/// assert!(plan.root.iter().any(|n| n.name == "Frame forward scan on: readings"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryPlan {
+    pub root: PlanNode,
+}
+
+impl QueryPlan {
+    /// Builds the tree from the lines of the plan. Steps are indented by 4 spaces below their
+    /// parent, attributes (`key: value`) by less.
+    pub fn parse<S: AsRef<str>>(lines: &[S]) -> Self {
+        // Path from the root to the last step, with the indentation of every step
+        let mut path: Vec<(usize, PlanNode)> = Vec::new();
+        let mut roots = Vec::new();
+
+        for line in lines.iter().map(AsRef::as_ref) {
+            let text = line.trim_start();
+            if text.is_empty() {
+                continue;
+            }
+            let indent = line.len() - text.len();
+            while path.last().is_some_and(|(i, _)| *i >= indent) {
+                close(&mut path, &mut roots);
+            }
+
+            if let Some((parent_indent, parent)) = path.last_mut() {
+                if indent < *parent_indent + CHILD_INDENT {
+                    if let Some((key, value)) = text.split_once(": ") {
+                        parent.attributes.push((key.to_owned(), value.to_owned()));
+                        continue;
+                    }
+                }
+            }
+            let node = PlanNode {
+                name: text.to_owned(),
+                attributes: Vec::new(),
+                children: Vec::new(),
+            };
+            path.push((indent, node));
+        }
+        while !path.is_empty() {
+            close(&mut path, &mut roots);
+        }
+
+        let root = match roots.len() {
+            1 => roots.pop().expect("one root"),
+            _ => PlanNode {
+                name: String::new(),
+                attributes: Vec::new(),
+                children: roots,
+            },
+        };
+        QueryPlan { root }
+    }
+
+    /// All steps, depth first
+    pub fn nodes(&self) -> impl Iterator<Item = &PlanNode> {
+        self.root.iter()
+    }
+}
+
+/// Ends the last step of the path, adding it to its parent
+fn close(path: &mut Vec<(usize, PlanNode)>, roots: &mut Vec<PlanNode>) {
+    let (_, node) = path.pop().expect("non-empty path");
+    match path.last_mut() {
+        Some((_, parent)) => parent.children.push(node),
+        None => roots.push(node),
+    }
+}
+
+impl fmt::Display for QueryPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.write(f, 0)
+    }
+}
+
+impl QuestDB {
+    /// Runs `EXPLAIN` for the query and returns its plan, without running the query itself
+    ///
+    /// # Example
+    /// ```no-test
+    /// let plan = connection
+    ///     .explain("select * from readings where temp > 20 order by ts desc")
+    ///     .await?;
+    /// if plan.nodes().any(|n| n.name.starts_with("Async JIT Filter")) {
+    ///     println!("filter is compiled");
+    /// }
+    /// ```
+    pub async fn explain(&self, query: &str) -> Result<QueryPlan, Error> {
+        let rows: Vec<(String,)> = self.query(&format!("EXPLAIN {}", query)).fetch().await?;
+        let lines: Vec<String> = rows.into_iter().map(|(line,)| line).collect();
+        Ok(QueryPlan::parse(&lines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryPlan;
+
+    const PLAN: &[&str] = &[
+        "SelectedRecord",
+        "    Sort light",
+        "      keys: [ts desc]",
+        "        Async JIT Filter workers: 1",
+        "          filter: 20<temp",
+        "            DataFrame",
+        "                Row forward scan",
+        "                Frame forward scan on: readings",
+    ];
+
+    #[test]
+    fn test_parse() {
+        let plan = QueryPlan::parse(PLAN);
+        let sort = &plan.root.children[0];
+        assert_eq!(sort.name, "Sort light");
+        assert_eq!(sort.attribute("keys"), Some("[ts desc]"));
+        let filter = &sort.children[0];
+        assert_eq!(filter.name, "Async JIT Filter workers: 1");
+        assert_eq!(filter.attribute("filter"), Some("20<temp"));
+        let frame = &filter.children[0];
+        assert_eq!(frame.children.len(), 2);
+        assert_eq!(frame.children[1].name, "Frame forward scan on: readings");
+        assert_eq!(plan.nodes().count(), 6);
+    }
+
+    #[test]
+    fn test_display() {
+        let plan = QueryPlan::parse(PLAN);
+        assert_eq!(plan.to_string(), PLAN.join("\n") + "\n");
+        assert_eq!(
+            QueryPlan::parse(&plan.to_string().lines().collect::<Vec<_>>()),
+            plan
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::options::{Limit, Priority};
+use crate::stats::Tracker;
+use crate::{Error, QuestDB};
+use std::io::{BufWriter, Write};
+
+/// Rows of an export written so far, lets [`QuestDB::exp_resume`] continue a failed export
+/// where it stopped
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportProgress {
+    rows: u64,
+    bytes: u64,
+    header: bool,
+}
+
+impl ExportProgress {
+    /// Progress of an export that hasn't started yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of rows written, not counting the header
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Number of bytes written, including the header
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+/// Writes the complete rows of a CSV response as it arrives, holding back a row until its end
+/// was received
+struct RowWriter<'a> {
+    progress: &'a mut ExportProgress,
+    /// Start of a row that hasn't been received completely
+    pending: Vec<u8>,
+    /// Bytes of `pending` already scanned for the end of a row
+    scanned: usize,
+    in_quotes: bool,
+    /// Whether the header of this response still has to be skipped
+    skip_header: bool,
+}
+
+impl<'a> RowWriter<'a> {
+    fn new(progress: &'a mut ExportProgress) -> Self {
+        // Every response starts with the header, it is only written once
+        let skip_header = progress.header;
+        RowWriter {
+            progress,
+            pending: Vec::new(),
+            scanned: 0,
+            in_quotes: false,
+            skip_header,
+        }
+    }
+
+    fn write<W: Write>(&mut self, chunk: &[u8], output: &mut W) -> Result<(), Error> {
+        self.pending.extend_from_slice(chunk);
+
+        let mut start = 0;
+        for i in self.scanned..self.pending.len() {
+            match self.pending[i] {
+                // Quoted values may contain newlines, escaped quotes toggle twice
+                b'"' => self.in_quotes = !self.in_quotes,
+                b'\n' if !self.in_quotes => {
+                    self.row(start, i + 1, output)?;
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+
+        self.pending.drain(..start);
+        self.scanned = self.pending.len();
+        Ok(())
+    }
+
+    /// Writes the last row if it doesn't end with a newline
+    fn finish<W: Write>(mut self, output: &mut W) -> Result<(), Error> {
+        if !self.pending.is_empty() {
+            self.row(0, self.pending.len(), output)?;
+        }
+        Ok(())
+    }
+
+    fn row<W: Write>(&mut self, start: usize, end: usize, output: &mut W) -> Result<(), Error> {
+        if self.skip_header {
+            self.skip_header = false;
+            return Ok(());
+        }
+
+        output.write_all(&self.pending[start..end])?;
+        self.progress.bytes += (end - start) as u64;
+        match self.progress.header {
+            true => self.progress.rows += 1,
+            false => self.progress.header = true,
+        }
+        Ok(())
+    }
+}
+
+impl QuestDB {
+    /// Exports the result of the query as CSV to `output`, continuing after the rows already
+    /// recorded in `progress`
+    ///
+    /// Only complete rows are written, so when the export fails calling this again with the same
+    /// output and progress continues from the last row written, and the output ends up the same
+    /// as an export that never failed. The query must return its rows in a stable order, e.g.
+    /// ordered by the designated timestamp, since the remaining rows are requested by position.
+    ///
+    /// Returns the number of bytes written by this call.
+    ///
+    /// # Example
+    /// ```no-test
+    /// let mut output = File::create("readings.csv")?;
+    /// let mut progress = ExportProgress::new();
+    /// let mut attempts = 0;
+    /// while let Err(e) = connection
+    ///     .exp_resume("select * from readings order by ts", &mut output, &mut progress)
+    ///     .await
+    /// {
+    ///     attempts += 1;
+    ///     if attempts == 5 {
+    ///         return Err(e);
+    ///     }
+    ///     eprintln!("export failed after {} rows, resuming: {}", progress.rows(), e);
+    /// }
+    /// ```
+    pub async fn exp_resume<W: Write>(
+        &self,
+        query: &str,
+        output: &mut W,
+        progress: &mut ExportProgress,
+    ) -> Result<u64, Error> {
+        let retry = &self.inner.retry;
+        let before = progress.bytes;
+        retry
+            .total(async {
+                let tracker = Tracker::start();
+                let _permit = self.inner.limits.acquire(Priority::Batch).await;
+                let limit = match progress.rows {
+                    0 => None,
+                    rows => Some(Limit::Range(rows, i64::MAX as u64)),
+                };
+                let (url, mut res) = self.export(&tracker, query, limit).await?;
+
+                // Rows completed before a failure stay written, dropping the writer when the
+                // total timeout cancels the export flushes them as well
+                let mut writer = BufWriter::new(output);
+                let mut rows = RowWriter::new(&mut *progress);
+                let mut received = 0;
+                let result = async {
+                    while let Some(chunk) = retry.chunk(&mut res).await? {
+                        rows.write(&chunk, &mut writer)?;
+                        received += chunk.len();
+                    }
+                    rows.finish(&mut writer)
+                }
+                .await;
+                writer.flush()?;
+                result?;
+
+                let stats = tracker.finish(url.len(), received);
+                self.observe("/exp", query, &stats);
+                Ok(())
+            })
+            .await?;
+
+        Ok(progress.bytes - before)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExportProgress, RowWriter};
+
+    #[test]
+    fn test_resume() {
+        let mut progress = ExportProgress::new();
+        let mut output = Vec::new();
+
+        // The connection fails in the middle of the second row
+        let mut rows = RowWriter::new(&mut progress);
+        rows.write(b"id,note\n1,a\n2,\"multi", &mut output).unwrap();
+        drop(rows);
+        assert_eq!(progress.rows(), 1);
+        assert_eq!(output, b"id,note\n1,a\n");
+
+        // The next response starts after the first row, with the header again
+        let mut rows = RowWriter::new(&mut progress);
+        rows.write(b"id,note\n2,\"multi\nline\"\n3,", &mut output)
+            .unwrap();
+        rows.write(b"c", &mut output).unwrap();
+        rows.finish(&mut output).unwrap();
+
+        assert_eq!(output, b"id,note\n1,a\n2,\"multi\nline\"\n3,c");
+        assert_eq!(progress.rows(), 3);
+        assert_eq!(progress.bytes(), output.len() as u64);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::platform::{self, Instant};
+use crate::redact;
+use crate::stats::Tracker;
+use crate::{Error, QuestDB};
+use reqwest::Response;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Probe interval unless configured otherwise
+pub(crate) const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Query the primary is probed with once requests failed over
+const PROBE_PATH: &str = "/exec?query=select%201";
+
+/// Servers requests fail over to, in order, when the one in use is unreachable or unhealthy
+///
+/// Requests stick to the server that last answered. While that isn't the primary, the primary
+/// is probed in the background every `probe_interval` and requests return to it once it
+/// answers again.
+pub(crate) struct Failover {
+    /// The primary first, then the servers to fail over to
+    endpoints: Vec<String>,
+    current: AtomicUsize,
+    probe_interval: Duration,
+    /// When the primary was last probed, or left
+    probed: Mutex<Instant>,
+}
+
+impl Failover {
+    pub(crate) fn new(primary: &str, fallbacks: Vec<String>, probe_interval: Duration) -> Self {
+        let mut endpoints = vec![String::from(primary)];
+        endpoints.extend(fallbacks);
+        Failover {
+            endpoints,
+            current: AtomicUsize::new(0),
+            probe_interval,
+            probed: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Index of the server requests are sent to
+    pub(crate) fn current(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn endpoint(&self, index: usize) -> &str {
+        &self.endpoints[index]
+    }
+
+    /// Server to try after the one at `index` failed with `res`, `None` when the failure is
+    /// returned to the caller: the request might have been processed, or all servers were tried
+    /// since `first`
+    pub(crate) fn next(
+        &self,
+        first: usize,
+        index: usize,
+        idempotent: bool,
+        res: &Result<Response, Error>,
+    ) -> Option<usize> {
+        let next = (index + 1) % self.endpoints.len();
+        (next != first && should_fail_over(idempotent, res)).then_some(next)
+    }
+
+    /// Sends the following requests to the server at `index`, which just answered
+    pub(crate) fn answered(&self, index: usize) {
+        if self.current.swap(index, Ordering::Relaxed) == 0 && index != 0 {
+            *self.probed.lock().unwrap() = Instant::now();
+        }
+    }
+
+    /// Whether the primary is due for a probe, which the caller then sends
+    fn probe_due(&self) -> bool {
+        if self.current() == 0 {
+            return false;
+        }
+        let mut probed = self.probed.lock().unwrap();
+        if probed.elapsed() < self.probe_interval {
+            return false;
+        }
+        *probed = Instant::now();
+        true
+    }
+}
+
+impl fmt::Debug for Failover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let endpoints: Vec<String> = self.endpoints.iter().map(|e| redact::url(e)).collect();
+        f.debug_struct("Failover")
+            .field("endpoints", &endpoints)
+            .field("current", &self.current())
+            .field("probe_interval", &self.probe_interval)
+            .finish()
+    }
+}
+
+/// Whether the request is sent to the next server after failing with `res`. Requests that
+/// never reached the server always are, others only when they may be sent twice.
+pub(crate) fn should_fail_over(idempotent: bool, res: &Result<Response, Error>) -> bool {
+    match res {
+        Ok(res) => idempotent && res.status().is_server_error(),
+        Err(Error::ExecError(err)) => platform::is_connect(err) || (idempotent && err.is_timeout()),
+        Err(Error::ConnectTimeout(_) | Error::CircuitOpen { .. }) => true,
+        Err(Error::ReadTimeout(_) | Error::Timeout(_)) => idempotent,
+        Err(_) => false,
+    }
+}
+
+impl QuestDB {
+    /// Probes the primary in the background when requests failed over and it is due, so that
+    /// they return to it once it answers
+    pub(crate) fn probe_primary(&self) {
+        if !self.inner.failover.probe_due() {
+            return;
+        }
+
+        let connection = self.clone();
+        platform::spawn(async move {
+            let failover = &connection.inner.failover;
+            let build = |url: &str| connection.inner.client.get(url);
+            let res = connection
+                .send_to(
+                    &Tracker::start(),
+                    failover.endpoint(0),
+                    true,
+                    PROBE_PATH,
+                    &build,
+                )
+                .await;
+            if matches!(res, Ok(res) if res.status().is_success()) {
+                failover.answered(0);
+            }
+        });
+    }
+}
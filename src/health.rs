@@ -0,0 +1,66 @@
+use crate::platform::Instant;
+use crate::{Error, QuestDB};
+use std::time::Duration;
+
+/// Outcome of a health check, see [`QuestDB::status`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HealthStatus {
+    /// Whether the server reported itself healthy
+    pub healthy: bool,
+    /// HTTP status of the response
+    pub status: u16,
+    /// Body of the response, e.g. `Status: Healthy`
+    pub message: String,
+    /// Time the server took to answer
+    pub latency: Duration,
+}
+
+impl QuestDB {
+    /// Asks the health endpoint how the server is doing. An unhealthy server is reported in the
+    /// result, errors are left to servers that couldn't be reached.
+    ///
+    /// The endpoint is `/status` of the connection's url unless
+    /// [`health_url`](crate::QuestDBBuilder::health_url) points elsewhere, e.g. to the minimal
+    /// HTTP server QuestDB runs on port 9003. The credentials of the connection are only sent to
+    /// `/status`, the minimal server doesn't authenticate.
+    ///
+    /// # Example
+    /// ```no-test
+    /// let status = connection.status().await?;
+    /// if !status.healthy {
+    ///     eprintln!("QuestDB is unhealthy: {}", status.message);
+    /// }
+    /// ```
+    pub async fn status(&self) -> Result<HealthStatus, Error> {
+        let retry = &self.inner.retry;
+
+        let started = Instant::now();
+        let res = match &self.inner.health_url {
+            Some(url) => retry
+                .read(self.inner.client.get(url).send())
+                .await?
+                .map_err(|e| retry.attempt_error(e, None))?,
+            None => {
+                self.get_authorized(&format!("{}/status", self.inner.url))
+                    .await?
+            }
+        };
+        let status = res.status();
+        let message = String::from_utf8_lossy(&retry.body(res).await?)
+            .trim()
+            .to_string();
+
+        Ok(HealthStatus {
+            healthy: status.is_success() && !message.to_ascii_lowercase().contains("unhealthy"),
+            status: status.as_u16(),
+            message,
+            latency: started.elapsed(),
+        })
+    }
+
+    /// Whether the server answers its health endpoint as healthy, for readiness probes. Servers
+    /// that can't be reached aren't healthy.
+    pub async fn is_healthy(&self) -> bool {
+        matches!(self.status().await, Ok(status) if status.healthy)
+    }
+}
@@ -0,0 +1,81 @@
+use crate::redact;
+use crate::Error;
+use reqwest::Response;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Replicas used to hedge read requests and how long to wait before doing so
+#[derive(Default)]
+pub(crate) struct HedgeConfig {
+    pub(crate) replicas: Vec<String>,
+    /// `None` disables hedging
+    pub(crate) delay: Option<Duration>,
+    next: AtomicUsize,
+}
+
+impl HedgeConfig {
+    pub(crate) fn new(replicas: Vec<String>, delay: Option<Duration>) -> Self {
+        HedgeConfig {
+            replicas,
+            delay,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Replica the next hedged request to `base` goes to, taking turns between all of them
+    /// except `base` itself, and the delay before sending it. `None` when hedging is disabled or
+    /// there is no other replica.
+    pub(crate) fn replica(&self, base: &str) -> Option<(&str, Duration)> {
+        let delay = self.delay?;
+        let replica = (0..self.replicas.len()).find_map(|_| {
+            let i = self.next.fetch_add(1, Ordering::Relaxed) % self.replicas.len();
+            Some(self.replicas[i].as_str()).filter(|replica| *replica != base)
+        })?;
+        Some((replica, delay))
+    }
+}
+
+impl fmt::Debug for HedgeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let replicas: Vec<String> = self.replicas.iter().map(|r| redact::url(r)).collect();
+        f.debug_struct("HedgeConfig")
+            .field("replicas", &replicas)
+            .field("delay", &self.delay)
+            .finish()
+    }
+}
+
+/// Whether a response can be handed to the caller, server errors and connection failures are
+/// left to the other request
+pub(crate) fn succeeded(res: &Result<Response, Error>) -> bool {
+    match res {
+        Ok(res) => !res.status().is_server_error(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HedgeConfig;
+    use std::time::Duration;
+
+    #[test]
+    fn test_replica() {
+        let delay = Some(Duration::from_millis(10));
+        let hedge = HedgeConfig::new(
+            vec![String::from("http://a"), String::from("http://b")],
+            delay,
+        );
+        assert_eq!(hedge.replica("http://a").unwrap().0, "http://b");
+        assert_eq!(hedge.replica("http://a").unwrap().0, "http://b");
+        assert_eq!(hedge.replica("http://c").unwrap().0, "http://a");
+        assert_eq!(hedge.replica("http://c").unwrap().0, "http://b");
+
+        let hedge = HedgeConfig::new(vec![String::from("http://a")], delay);
+        assert!(hedge.replica("http://a").is_none());
+        assert!(HedgeConfig::new(vec![String::from("http://a")], None)
+            .replica("http://b")
+            .is_none());
+    }
+}
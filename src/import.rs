@@ -0,0 +1,374 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::api::error_for_status;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::options::Priority;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::platform;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::retry;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::stats::Tracker;
+use crate::types::Atomicity;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{Error, QuestDB};
+#[cfg(not(target_arch = "wasm32"))]
+use futures_util::stream::{self, Stream};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::header::{ETAG, IF_RANGE, RANGE};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::{Client, Response, StatusCode};
+use serde::Deserialize;
+#[cfg(not(target_arch = "wasm32"))]
+use urlencoding::encode;
+
+/// Options of an import, see [`QuestDB::imp_url`]
+#[derive(Clone, Debug)]
+pub struct ImportOptions {
+    overwrite: Option<bool>,
+    durable: Option<bool>,
+    atomicity: Option<Atomicity>,
+    max_resumes: u32,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            overwrite: None,
+            durable: None,
+            atomicity: None,
+            max_resumes: 3,
+        }
+    }
+}
+
+impl ImportOptions {
+    /// Uses the server defaults and resumes a broken download up to 3 times
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deletes the existing table before appending the rows when true
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = Some(overwrite);
+        self
+    }
+
+    /// Makes the server flush the relevant disk cache before responding when true
+    pub fn durable(mut self, durable: bool) -> Self {
+        self.durable = Some(durable);
+        self
+    }
+
+    /// Whether rows that can't be stored are dropped or fail the whole import
+    pub fn atomicity(mut self, atomicity: Atomicity) -> Self {
+        self.atomicity = Some(atomicity);
+        self
+    }
+
+    /// How many times a broken download of the source is continued with a range request
+    pub fn max_resumes(mut self, max_resumes: u32) -> Self {
+        self.max_resumes = max_resumes;
+        self
+    }
+
+    /// Query parameters of /imp
+    #[cfg(not(target_arch = "wasm32"))]
+    fn params(&self) -> String {
+        let mut params = String::new();
+        if let Some(o) = self.overwrite {
+            params += &format!("&overwrite={}", o);
+        }
+        if let Some(d) = self.durable {
+            params += &format!("&durable={}", d);
+        }
+        if let Some(a) = self.atomicity {
+            params += &format!("&atomicity={}", a);
+        }
+        params
+    }
+}
+
+/// Outcome of an import, as reported by the server
+///
+/// In relaxed atomicity rows that can't be stored are dropped, the import still succeeds. Use
+/// [`rows_rejected`](ImportResult::rows_rejected) and the per column details to detect partial
+/// imports.
+///
+/// # Example
+/// ```no-test
+/// let result = connection.imp("./links.csv", "links", None, None, None).await?;
+/// if result.is_partial() {
+///     for column in result.worst_offenders(3) {
+///         println!("{} ({}): {} rows rejected", column.name, column.column_type, column.errors);
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImportResult {
+    /// Status reported by the server, `OK` when the file was imported
+    pub status: String,
+    /// Name of the table the rows were imported into
+    pub location: String,
+    /// Number of rows stored
+    pub rows_imported: u64,
+    /// Number of rows dropped because a value couldn't be parsed
+    pub rows_rejected: u64,
+    /// Whether the first line was taken as the header
+    pub header: bool,
+    /// Partitioning of the table
+    pub partition_by: Option<String>,
+    /// Designated timestamp of the table
+    pub timestamp: Option<String>,
+    /// Details on every column, in the order of the file
+    pub columns: Vec<ImportColumn>,
+}
+
+/// Column of an import, with the type it was imported as and how many values were rejected
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ImportColumn {
+    /// Name of the column, either from the header or generated by the server
+    pub name: String,
+    /// Type the column was detected as or already had in the table
+    #[serde(rename = "type")]
+    pub column_type: String,
+    /// Size of a value in bytes, 0 for variable length types
+    pub size: u32,
+    /// Number of values that couldn't be parsed as `column_type`
+    pub errors: u64,
+}
+
+impl ImportResult {
+    /// Whether some rows were dropped
+    pub fn is_partial(&self) -> bool {
+        self.rows_rejected > 0
+    }
+
+    /// Columns that had at least one value rejected
+    pub fn rejected_columns(&self) -> impl Iterator<Item = &ImportColumn> {
+        self.columns.iter().filter(|c| c.errors > 0)
+    }
+
+    /// Up to `n` columns with the most rejected values, the worst first
+    pub fn worst_offenders(&self, n: usize) -> Vec<&ImportColumn> {
+        let mut columns: Vec<&ImportColumn> = self.rejected_columns().collect();
+        columns.sort_by_key(|c| std::cmp::Reverse(c.errors));
+        columns.truncate(n);
+        columns
+    }
+}
+
+/// Download of the file to import
+#[cfg(not(target_arch = "wasm32"))]
+struct Source {
+    client: Client,
+    url: String,
+    response: Option<Response>,
+    /// Bytes received so far, where a resumed download continues
+    offset: u64,
+    /// Identifies the version of the file, a resumed download must return the same one
+    etag: Option<String>,
+    resumes: u32,
+    max_resumes: u32,
+    failed: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Source {
+    /// Requests the file from the current offset on
+    async fn request(&mut self) -> Result<Response, Error> {
+        let mut req = self.client.get(&self.url);
+        if self.offset > 0 {
+            req = req.header(RANGE, format!("bytes={}-", self.offset));
+            if let Some(etag) = &self.etag {
+                req = req.header(IF_RANGE, etag);
+            }
+        }
+
+        let res = req.send().await?.error_for_status()?;
+        if self.offset > 0 && res.status() != StatusCode::PARTIAL_CONTENT {
+            // The server ignored the range or the file changed, continuing would mix versions
+            return Err(Error::HttpError {
+                status: res.status().as_u16(),
+                message: format!("{} can't be resumed at byte {}", self.url, self.offset),
+            });
+        }
+        if self.offset == 0 {
+            self.etag = res
+                .headers()
+                .get(ETAG)
+                .and_then(|e| e.to_str().ok())
+                .map(String::from);
+        }
+        Ok(res)
+    }
+
+    /// Next chunk of the file, resuming the download when the connection breaks
+    async fn next(&mut self) -> Option<Result<bytes::Bytes, Error>> {
+        loop {
+            if self.failed {
+                return None;
+            }
+            let response = match &mut self.response {
+                Some(response) => response,
+                None => match self.request().await {
+                    Ok(response) => self.response.insert(response),
+                    Err(e) => {
+                        self.failed = true;
+                        return Some(Err(e));
+                    }
+                },
+            };
+
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    self.offset += chunk.len() as u64;
+                    return Some(Ok(chunk));
+                }
+                Ok(None) => return None,
+                Err(_) if self.resumes < self.max_resumes => {
+                    platform::sleep(retry::backoff(self.resumes)).await;
+                    self.resumes += 1;
+                    self.response = None;
+                }
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+    }
+
+    fn into_stream(self) -> impl Stream<Item = Result<bytes::Bytes, Error>> {
+        stream::unfold(self, |mut source| async move {
+            let chunk = source.next().await?;
+            Some((chunk, source))
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl QuestDB {
+    /// Imports a CSV file served over HTTP(S), e.g. a presigned S3 url, into `table`
+    ///
+    /// The file is streamed from the source to the server without being stored locally. When
+    /// the download breaks it is continued with a range request from the last byte received, so
+    /// the server still receives the file once and in order. Sources that don't support ranges
+    /// fail the import instead.
+    ///
+    /// # Example
+    /// ```no-test
+    /// let result = connection
+    ///     .imp_url(
+    ///         "https://bucket.s3.amazonaws.com/trades.csv?X-Amz-Signature=...",
+    ///         "trades",
+    ///         &ImportOptions::new().atomicity(Atomicity::Strict),
+    ///     )
+    ///     .await?;
+    /// println!("{} rows imported", result.rows_imported);
+    /// ```
+    pub async fn imp_url(
+        &self,
+        source_url: &str,
+        table: &str,
+        options: &ImportOptions,
+    ) -> Result<ImportResult, Error> {
+        self.inner
+            .retry
+            .total(async {
+                let tracker = Tracker::start();
+                if self.inner.read_only {
+                    return Err(Error::WriteForbidden(String::from("import")));
+                }
+
+                let url = format!("/imp?fmt=json&name={}{}", encode(table), options.params());
+                let file_name = source_url
+                    .split(['?', '#'])
+                    .next()
+                    .and_then(|path| path.rsplit('/').next())
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or(table);
+
+                let _permit = self.inner.limits.acquire(Priority::Batch).await;
+                let res = self
+                    .send(&tracker, false, &url, |url| {
+                        // Every attempt downloads the file from the start
+                        let source = Source {
+                            client: self.inner.client.clone(),
+                            url: String::from(source_url),
+                            response: None,
+                            offset: 0,
+                            etag: None,
+                            resumes: 0,
+                            max_resumes: options.max_resumes,
+                            failed: false,
+                        };
+                        let body = reqwest::Body::wrap_stream(source.into_stream());
+                        let part = reqwest::multipart::Part::stream(body)
+                            .file_name(String::from(file_name));
+                        let form = reqwest::multipart::Form::new().part("data", part);
+                        self.inner.client.post(url).multipart(form)
+                    })
+                    .await?;
+                let res = self.inner.retry.body(error_for_status(res).await?).await?;
+
+                let stats = tracker.finish(url.len(), res.len());
+                self.observe("/imp", table, &stats);
+
+                Ok(serde_json::from_slice::<ImportResult>(&res)?)
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ImportOptions, ImportResult};
+    use crate::types::Atomicity;
+
+    #[test]
+    fn test_worst_offenders() {
+        let result: ImportResult = serde_json::from_str(
+            r#"{
+                "status": "OK",
+                "location": "readings",
+                "rowsRejected": 7,
+                "rowsImported": 93,
+                "header": true,
+                "partitionBy": "DAY",
+                "timestamp": "ts",
+                "columns": [
+                    {"name": "id", "type": "INT", "size": 4, "errors": 2},
+                    {"name": "ts", "type": "TIMESTAMP", "size": 8, "errors": 0},
+                    {"name": "temp", "type": "DOUBLE", "size": 8, "errors": 5}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(result.is_partial());
+        assert_eq!(result.rows_imported, 93);
+        assert_eq!(result.partition_by.as_deref(), Some("DAY"));
+
+        let worst: Vec<&str> = result
+            .worst_offenders(5)
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(worst, ["temp", "id"]);
+        assert_eq!(result.worst_offenders(1)[0].column_type, "DOUBLE");
+    }
+
+    #[test]
+    fn test_import_options() {
+        assert_eq!(ImportOptions::new().params(), "");
+        assert_eq!(
+            ImportOptions::new()
+                .overwrite(true)
+                .atomicity(Atomicity::Strict)
+                .params(),
+            "&overwrite=true&atomicity=strict"
+        );
+    }
+}
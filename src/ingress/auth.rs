@@ -0,0 +1,161 @@
+use crate::redact::Redacted;
+use crate::Error;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use std::fmt;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Longest challenge accepted from the server
+const MAX_CHALLENGE_LENGTH: usize = 1024;
+
+/// Key the sender authenticates with on servers requiring token authentication for line
+/// protocol
+///
+/// The parameters are the fields of the JSON Web Key created for the user: `kid` identifies the
+/// key, `d` is the private key and `x` and `y` the public key, all base64url encoded. On
+/// connecting the server sends a challenge which the sender signs with the private key.
+///
+/// # Example
+/// ```no-test
+/// use questdb::ingress::{IlpAuth, SenderConfig};
+///
+/// let auth = IlpAuth::new(
+///     "testUser1",
+///     "5UjEMuA0Pj5pjK8a-fa24dyIf-Es5mYny3oE_Wmus48",
+///     "fLKYEaoEb9lrn3nkwLDA-M_xnuFOdSt9y0Z7_vWSHLU",
+///     "Dt5tbS1dEDMSYfym3fgMv0B99szno-dFc1rYF9t0aac",
+/// )?;
+/// let sender = SenderConfig::new("192.168.1.37:9009").auth(auth).connect().await?;
+/// ```
+#[derive(Clone)]
+pub struct IlpAuth {
+    key_id: String,
+    key: SigningKey,
+}
+
+impl fmt::Debug for IlpAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IlpAuth")
+            .field("key_id", &self.key_id)
+            .field("key", &Redacted)
+            .finish()
+    }
+}
+
+impl IlpAuth {
+    /// Creates the key from its `kid`, `d`, `x` and `y` parameters, failing with
+    /// [`Error::AuthError`] when they aren't a valid P-256 key pair
+    pub fn new(key_id: &str, d: &str, x: &str, y: &str) -> Result<Self, Error> {
+        let decode = |name: &str, value: &str| {
+            URL_SAFE_NO_PAD
+                .decode(value.trim_end_matches('='))
+                .map_err(|e| {
+                    Error::AuthError(format!("invalid '{}' of key {}: {}", name, key_id, e))
+                })
+        };
+        let key = SigningKey::from_slice(&decode("d", d)?)
+            .map_err(|_| Error::AuthError(format!("invalid private key of key {}", key_id)))?;
+
+        let public = key.verifying_key().to_encoded_point(false);
+        if public.x().map(|x| &x[..]) != Some(&decode("x", x)?[..])
+            || public.y().map(|y| &y[..]) != Some(&decode("y", y)?[..])
+        {
+            return Err(Error::AuthError(format!(
+                "public key of key {} doesn't match its private key",
+                key_id
+            )));
+        }
+
+        Ok(IlpAuth {
+            key_id: String::from(key_id),
+            key,
+        })
+    }
+
+    /// Runs the challenge-response handshake on a new connection
+    pub(crate) async fn authenticate<S>(&self, stream: &mut S) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        stream
+            .write_all(format!("{}\n", self.key_id).as_bytes())
+            .await?;
+        stream.flush().await?;
+
+        let challenge = read_line(stream).await?;
+        let signature: Signature = self.key.sign(&challenge);
+        let mut response = STANDARD.encode(signature.to_bytes());
+        response.push('\n');
+        stream.write_all(response.as_bytes()).await?;
+        stream.flush().await
+    }
+}
+
+/// Reads up to the next newline, which isn't part of the result
+async fn read_line<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    loop {
+        match stream.read_u8().await? {
+            b'\n' => return Ok(line),
+            _ if line.len() == MAX_CHALLENGE_LENGTH => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "authentication challenge too long",
+                ))
+            }
+            b => line.push(b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IlpAuth;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use p256::ecdsa::signature::Verifier;
+    use p256::ecdsa::Signature;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    const D: &str = "5UjEMuA0Pj5pjK8a-fa24dyIf-Es5mYny3oE_Wmus48";
+    const X: &str = "fLKYEaoEb9lrn3nkwLDA-M_xnuFOdSt9y0Z7_vWSHLU";
+    const Y: &str = "Dt5tbS1dEDMSYfym3fgMv0B99szno-dFc1rYF9t0aac";
+
+    #[test]
+    fn test_key() {
+        assert!(IlpAuth::new("testUser1", D, X, Y).is_ok());
+        assert!(IlpAuth::new("testUser1", D, Y, X).is_err());
+        assert!(IlpAuth::new("testUser1", "not base64!", X, Y).is_err());
+        assert!(!format!("{:?}", IlpAuth::new("testUser1", D, X, Y).unwrap()).contains(D));
+    }
+
+    #[tokio::test]
+    async fn test_handshake() {
+        let auth = IlpAuth::new("testUser1", D, X, Y).unwrap();
+        let (mut client, server) = tokio::io::duplex(1024);
+
+        let server = async {
+            let mut server = BufReader::new(server);
+            let mut kid = String::new();
+            server.read_line(&mut kid).await.unwrap();
+            server.write_all(b"challenge123\n").await.unwrap();
+            let mut signature = String::new();
+            server.read_line(&mut signature).await.unwrap();
+            (kid, signature)
+        };
+        let (res, (kid, signature)) = tokio::join!(auth.authenticate(&mut client), server);
+        res.unwrap();
+
+        assert_eq!(kid, "testUser1\n");
+        let signature = STANDARD.decode(signature.trim_end()).unwrap();
+        let signature = Signature::from_slice(&signature).unwrap();
+        assert!(auth
+            .key
+            .verifying_key()
+            .verify(b"challenge123", &signature)
+            .is_ok());
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+/// When a [`Sender`](crate::ingress::Sender) flushes without being asked to
+///
+/// A flush is triggered as soon as one of the limits is reached. Without limits lines are only
+/// sent when flushing explicitly, or when the socket's write buffer is full.
+///
+/// # Example
+/// ```no-test
+/// use questdb::ingress::{AutoFlush, SenderConfig};
+/// use std::time::Duration;
+///
+/// let mut sender = SenderConfig::new("192.168.1.37:9009")
+///     .auto_flush(AutoFlush::new().rows(1000).interval(Duration::from_millis(100)))
+///     .connect()
+///     .await?;
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AutoFlush {
+    rows: Option<usize>,
+    bytes: Option<usize>,
+    interval: Option<Duration>,
+}
+
+impl AutoFlush {
+    /// Never flushes automatically
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flushes once `rows` lines are queued
+    pub fn rows(mut self, rows: usize) -> Self {
+        self.rows = Some(rows);
+        self
+    }
+
+    /// Flushes once `bytes` bytes are queued
+    pub fn bytes(mut self, bytes: usize) -> Self {
+        self.bytes = Some(bytes);
+        self
+    }
+
+    /// Flushes lines queued for longer than `interval`. Checked when lines are written and by
+    /// [`Sender::flush_if_due`](crate::ingress::Sender::flush_if_due), which a sender moved to a
+    /// background task with [`Sender::spawn`](crate::ingress::Sender::spawn) calls every
+    /// `interval`. A sender used directly has no background timer.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub(crate) fn max_age(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    /// Whether `rows` lines of `bytes` bytes, the oldest queued for `age`, must be flushed
+    pub(crate) fn due(&self, rows: usize, bytes: usize, age: Duration) -> bool {
+        rows > 0
+            && (self.rows.is_some_and(|max| rows >= max)
+                || self.bytes.is_some_and(|max| bytes >= max)
+                || self.interval.is_some_and(|max| age >= max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AutoFlush;
+    use std::time::Duration;
+
+    #[test]
+    fn test_due() {
+        let secs = Duration::from_secs;
+        let policy = AutoFlush::new().rows(10).bytes(100).interval(secs(1));
+        assert!(!policy.due(9, 99, secs(0)));
+        assert!(policy.due(10, 50, secs(0)));
+        assert!(policy.due(1, 100, secs(0)));
+        assert!(policy.due(1, 10, secs(2)));
+        assert!(!policy.due(0, 0, secs(2)));
+        assert!(!AutoFlush::new().due(1_000_000, 1_000_000, secs(3600)));
+    }
+}
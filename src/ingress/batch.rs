@@ -0,0 +1,290 @@
+use super::validate;
+use crate::options::Priority;
+use crate::stats::Tracker;
+use crate::{Error, QuestDB};
+use serde::Deserialize;
+use std::fmt;
+
+/// Lines of a single ILP over HTTP request
+///
+/// QuestDB commits the lines of a request as one transaction: either all rows are stored, or
+/// none are and [`Error::WriteRejected`] reports the lines that failed. The limits are checked
+/// when adding lines, so oversized requests are caught before being sent.
+///
+/// # Example
+/// ```no-test
+/// use questdb::ingress::Batch;
+///
+/// let mut batch = Batch::new().max_rows(10_000);
+/// batch.push("readings,sensor=a temp=16.4 1571270400000000000")?;
+/// batch.push("readings,sensor=b temp=oops 1571270400000000000")?;
+///
+/// match connection.write_batch(&batch).await {
+///     Err(Error::WriteRejected { lines, .. }) => {
+///         for n in lines {
+///             eprintln!("rejected: {}", batch.line(n).unwrap_or_default());
+///         }
+///     }
+///     res => res?,
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    buffer: String,
+    /// Start of every line in the buffer
+    offsets: Vec<usize>,
+    max_rows: Option<usize>,
+    max_bytes: Option<usize>,
+    precision: Option<Precision>,
+}
+
+/// Unit of the timestamps of the lines sent over HTTP, the server assumes nanoseconds
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Precision {
+    Nanos,
+    Micros,
+    Millis,
+    Seconds,
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Precision::Nanos => write!(f, "n"),
+            Precision::Micros => write!(f, "u"),
+            Precision::Millis => write!(f, "ms"),
+            Precision::Seconds => write!(f, "s"),
+        }
+    }
+}
+
+impl Batch {
+    /// Creates an empty batch without limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of lines, further lines are refused with [`Error::BatchFull`]
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    /// Maximum size of the request body in bytes, lines that don't fit are refused with
+    /// [`Error::BatchFull`]. Should stay below the server's `line.http.max.recv.buffer.size`.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Unit of the timestamps of the lines, nanoseconds when not set
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+
+    /// Adds a formatted line, with or without the trailing newline. The batch is left unchanged
+    /// when the line doesn't fit.
+    pub fn push(&mut self, line: &str) -> Result<(), Error> {
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        if line.contains('\n') {
+            return Err(Error::InvalidLine(String::from(line)));
+        }
+        if self.max_rows.is_some_and(|max| self.offsets.len() >= max) {
+            return Err(Error::BatchFull(format!("{} rows", self.offsets.len())));
+        }
+        if let Some(max) = self.max_bytes {
+            if self.buffer.len() + line.len() + 1 > max {
+                return Err(Error::BatchFull(format!("{} bytes", max)));
+            }
+        }
+
+        self.offsets.push(self.buffer.len());
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+        Ok(())
+    }
+
+    /// Line `n`, counting from 1 like the line numbers reported by the server
+    pub fn line(&self, n: usize) -> Option<&str> {
+        let start = *self.offsets.get(n.checked_sub(1)?)?;
+        let end = self.offsets.get(n).copied().unwrap_or(self.buffer.len());
+        Some(&self.buffer[start..end - 1])
+    }
+
+    /// Number of lines
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether the batch has no lines
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Removes all lines, keeping the limits and the allocated memory
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.offsets.clear();
+    }
+
+    /// Request body
+    pub fn as_str(&self) -> &str {
+        &self.buffer
+    }
+}
+
+/// Error response of the /write endpoint
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteError {
+    code: String,
+    message: String,
+    line: Option<usize>,
+    error_id: Option<String>,
+}
+
+impl WriteError {
+    /// Numbers of all lines mentioned in the message, which lists every line that failed as
+    /// `error in line <n>: ...`
+    fn lines(&self) -> Vec<usize> {
+        let mut lines: Vec<usize> = self
+            .message
+            .match_indices("error in line ")
+            .filter_map(|(i, m)| {
+                let rest = &self.message[i + m.len()..];
+                let end = rest.find(|c: char| !c.is_ascii_digit())?;
+                rest[..end].parse().ok()
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.extend(self.line);
+        }
+        lines
+    }
+}
+
+impl QuestDB {
+    /// Writes formatted lines over HTTP, for networks where only the HTTP port of the server is
+    /// reachable. The lines are sent in a single transaction like a [`Batch`].
+    ///
+    /// # Example
+    /// ```no-test
+    /// use questdb::ingress::Precision;
+    ///
+    /// connection
+    ///     .ilp_write("readings,sensor=a temp=16.4 1571270400\n", Precision::Seconds)
+    ///     .await?;
+    /// ```
+    pub async fn ilp_write(&self, lines: &str, precision: Precision) -> Result<(), Error> {
+        let mut batch = Batch::new().precision(precision);
+        for line in lines.lines().filter(|l| !l.trim().is_empty()) {
+            batch.push(line)?;
+        }
+        self.write_batch(&batch).await
+    }
+
+    /// Writes the lines of the batch over HTTP in a single transaction
+    ///
+    /// Returns [`Error::WriteRejected`] with the numbers of the offending lines when the server
+    /// refuses the batch, in which case no row of the batch was stored. The request is never
+    /// retried since its outcome is unknown after a connection failure.
+    pub async fn write_batch(&self, batch: &Batch) -> Result<(), Error> {
+        let tracker = Tracker::start();
+        if self.inner.read_only {
+            return Err(Error::WriteForbidden(String::from("write")));
+        }
+        if batch.is_empty() {
+            return Ok(());
+        }
+        if self.inner.validate_writes {
+            validate::validate(self, batch).await?;
+        }
+
+        let path = match batch.precision {
+            Some(precision) => format!("/write?precision={}", precision),
+            None => String::from("/write"),
+        };
+        let _permit = self.inner.limits.acquire(Priority::Batch).await;
+        let res = self
+            .send(&tracker, false, &path, |url| {
+                self.inner.client.post(url).body(batch.buffer.clone())
+            })
+            .await?;
+
+        let status = res.status();
+        let body = res.text().await?;
+        let stats = tracker.finish(path.len() + batch.buffer.len(), body.len());
+        self.observe("/write", &format!("{} lines", batch.len()), &stats);
+
+        if status.is_success() {
+            self.inner.metadata.written();
+            return Ok(());
+        }
+        let error = match serde_json::from_str::<WriteError>(&body) {
+            Ok(e) => Error::WriteRejected {
+                lines: e.lines(),
+                code: e.code,
+                message: e.message,
+                error_id: e.error_id,
+            },
+            Err(_) => Error::HttpError {
+                status: status.as_u16(),
+                message: String::from(body.trim()),
+            },
+        };
+        self.inner.metadata.failed(&error);
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Batch, Precision, WriteError};
+
+    #[test]
+    fn test_batch_limits() {
+        let mut batch = Batch::new().max_rows(2).max_bytes(40);
+        batch.push("t a=1i 1\n").unwrap();
+        batch.push("t a=2i 2").unwrap();
+        assert!(batch.push("t a=3i 3").is_err());
+        assert_eq!(batch.as_str(), "t a=1i 1\nt a=2i 2\n");
+        assert_eq!(batch.line(2), Some("t a=2i 2"));
+        assert_eq!(batch.line(0), None);
+        assert_eq!(batch.line(3), None);
+
+        let mut batch = Batch::new().max_bytes(12);
+        batch.push("t a=1i 1").unwrap();
+        assert!(batch.push("t a=2i 2").is_err());
+        assert_eq!(batch.len(), 1);
+        assert!(batch.push("t\na=1i").is_err());
+    }
+
+    #[test]
+    fn test_precision() {
+        let names: Vec<String> = [
+            Precision::Nanos,
+            Precision::Micros,
+            Precision::Millis,
+            Precision::Seconds,
+        ]
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+        assert_eq!(names, ["n", "u", "ms", "s"]);
+    }
+
+    #[test]
+    fn test_rejected_lines() {
+        let error: WriteError = serde_json::from_str(
+            r#"{
+                "code": "invalid",
+                "message": "failed to parse line protocol:errors encountered on line(s):\nerror in line 2: table: t, column: a; cast error\nerror in line 5: table: t, column: a; cast error",
+                "line": 2,
+                "errorId": "9b7a-1"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(error.lines(), [2, 5]);
+    }
+}
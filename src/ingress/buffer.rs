@@ -0,0 +1,336 @@
+use crate::ingress::IlpRow;
+use crate::{Error, Timestamp};
+use std::fmt::Write;
+
+/// Position in the row being built, which decides what may be added next
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    /// Before the table of the next row
+    Table,
+    /// After the table or a symbol, more symbols or a column may follow
+    Symbols,
+    /// After a column, only columns or the timestamp may follow
+    Columns,
+}
+
+/// Rows in line protocol, built column by column
+///
+/// Names and values are escaped as the protocol requires, a row ends with its timestamp. Send
+/// the rows with [`Sender::flush_buffer`](crate::ingress::Sender::flush_buffer).
+///
+/// # Example
+/// ```
+/// use questdb::ingress::Buffer;
+/// use questdb::Timestamp;
+///
+/// let mut buffer = Buffer::new();
+/// buffer
+///     .table("readings")?
+///     .symbol("sensor", "a")?
+///     .column_f64("temp", 16.4)?
+///     .column_str("note", "checked \"manually\"")?
+///     .at(Timestamp::from_micros(1_571_270_400_000_000))?;
+///
+/// assert_eq!(
+///     buffer.as_str(),
+///     "readings,sensor=a temp=16.4,note=\"checked \\\"manually\\\"\" 1571270400000000000\n"
+/// );
+/// # Ok::<(), questdb::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Buffer {
+    output: String,
+    /// Start of the row being built
+    row_start: usize,
+    rows: usize,
+    state: State,
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Buffer {
+            output: String::new(),
+            row_start: 0,
+            rows: 0,
+            state: State::Table,
+        }
+    }
+}
+
+impl Buffer {
+    /// Creates an empty buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a row of `table`
+    pub fn table(&mut self, table: &str) -> Result<&mut Self, Error> {
+        self.expect(self.state == State::Table, "table", table)?;
+        check_name(table)?;
+        escape(&mut self.output, table, &[',', ' ']);
+        self.state = State::Symbols;
+        Ok(self)
+    }
+
+    /// Adds a symbol, symbols must come before the other columns
+    pub fn symbol(&mut self, name: &str, value: &str) -> Result<&mut Self, Error> {
+        self.expect(self.state == State::Symbols, "symbol", name)?;
+        check_name(name)?;
+        check_name(value)?;
+        self.output.push(',');
+        escape(&mut self.output, name, &[',', '=', ' ']);
+        self.output.push('=');
+        escape(&mut self.output, value, &[',', '=', ' ']);
+        Ok(self)
+    }
+
+    /// Adds a boolean column
+    pub fn column_bool(&mut self, name: &str, value: bool) -> Result<&mut Self, Error> {
+        self.column(name)?;
+        self.output.push(if value { 't' } else { 'f' });
+        Ok(self)
+    }
+
+    /// Adds a long column
+    pub fn column_i64(&mut self, name: &str, value: i64) -> Result<&mut Self, Error> {
+        self.column(name)?;
+        let _ = write!(self.output, "{}i", value);
+        Ok(self)
+    }
+
+    /// Adds a double column, NaN and the infinities included
+    pub fn column_f64(&mut self, name: &str, value: f64) -> Result<&mut Self, Error> {
+        self.column(name)?;
+        match value {
+            v if v.is_nan() => self.output.push_str("NaN"),
+            f64::INFINITY => self.output.push_str("Infinity"),
+            f64::NEG_INFINITY => self.output.push_str("-Infinity"),
+            v => {
+                let _ = write!(self.output, "{:?}", v);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Adds a string column. Quotes and backslashes are escaped, line breaks would end the row
+    /// and are rejected.
+    pub fn column_str(&mut self, name: &str, value: &str) -> Result<&mut Self, Error> {
+        if value.contains(['\n', '\r']) {
+            return Err(Error::InvalidLine(format!(
+                "string value of '{}' spans lines",
+                name
+            )));
+        }
+        self.column(name)?;
+        self.output.push('"');
+        escape(&mut self.output, value, &['"', '\\']);
+        self.output.push('"');
+        Ok(self)
+    }
+
+    /// Adds a timestamp column, which isn't the designated timestamp of the row
+    pub fn column_ts(&mut self, name: &str, value: Timestamp) -> Result<&mut Self, Error> {
+        self.column(name)?;
+        let _ = write!(self.output, "{}t", value.as_micros());
+        Ok(self)
+    }
+
+    /// Ends the row with its designated timestamp
+    pub fn at(&mut self, ts: Timestamp) -> Result<(), Error> {
+        self.expect(self.state == State::Columns, "timestamp", "")?;
+        let _ = writeln!(self.output, " {}", ts.as_micros() as i128 * 1000);
+        self.end_row();
+        Ok(())
+    }
+
+    /// Ends the row, the server stores it with the time it receives it at
+    pub fn at_now(&mut self) -> Result<(), Error> {
+        self.expect(self.state == State::Columns, "timestamp", "")?;
+        self.output.push('\n');
+        self.end_row();
+        Ok(())
+    }
+
+    /// Adds a whole row, which is discarded again if it fails
+    pub fn row<T: IlpRow + ?Sized>(&mut self, row: &T) -> Result<&mut Self, Error> {
+        self.expect(self.state == State::Table, "row", "")?;
+        let res = row.write_row(self).and_then(|_| match self.in_row() {
+            true => Err(Error::InvalidLine(String::from("row without timestamp"))),
+            false => Ok(()),
+        });
+        if let Err(e) = res {
+            self.output.truncate(self.row_start);
+            self.state = State::Table;
+            return Err(e);
+        }
+        Ok(self)
+    }
+
+    /// Number of complete rows
+    pub fn row_count(&self) -> usize {
+        self.rows
+    }
+
+    /// Size of the complete rows in bytes
+    pub fn len(&self) -> usize {
+        self.row_start
+    }
+
+    /// Whether the buffer has no complete rows, an unfinished row doesn't count
+    pub fn is_empty(&self) -> bool {
+        self.row_start == 0
+    }
+
+    /// Whether a row was started and not ended yet
+    pub fn in_row(&self) -> bool {
+        self.state != State::Table
+    }
+
+    /// Complete rows, one per line
+    pub fn as_str(&self) -> &str {
+        &self.output[..self.row_start]
+    }
+
+    /// Removes all rows, including an unfinished one
+    pub fn clear(&mut self) {
+        self.output.clear();
+        self.row_start = 0;
+        self.rows = 0;
+        self.state = State::Table;
+    }
+
+    fn column(&mut self, name: &str) -> Result<(), Error> {
+        self.expect(self.state != State::Table, "column", name)?;
+        check_name(name)?;
+        self.output.push(if self.state == State::Symbols {
+            ' '
+        } else {
+            ','
+        });
+        escape(&mut self.output, name, &[',', '=', ' ']);
+        self.output.push('=');
+        self.state = State::Columns;
+        Ok(())
+    }
+
+    fn end_row(&mut self) {
+        self.row_start = self.output.len();
+        self.rows += 1;
+        self.state = State::Table;
+    }
+
+    /// Fails with a description of the misplaced part of the row
+    fn expect(&self, allowed: bool, part: &str, name: &str) -> Result<(), Error> {
+        if allowed {
+            return Ok(());
+        }
+        let expected = match self.state {
+            State::Table => "a table",
+            State::Symbols => "a symbol or column",
+            State::Columns => "a column or the timestamp",
+        };
+        let part = match name {
+            "" => String::from(part),
+            name => format!("{} '{}'", part, name),
+        };
+        Err(Error::InvalidLine(format!(
+            "{} where {} was expected",
+            part, expected
+        )))
+    }
+}
+
+/// Names and symbols must be single line and non-empty
+fn check_name(name: &str) -> Result<(), Error> {
+    if name.is_empty() || name.contains(['\n', '\r']) {
+        return Err(Error::InvalidLine(format!("invalid name {:?}", name)));
+    }
+    Ok(())
+}
+
+/// Appends `s` with a backslash before every special character
+fn escape(output: &mut String, s: &str, special: &[char]) {
+    for c in s.chars() {
+        if special.contains(&c) {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Buffer;
+    use crate::Timestamp;
+
+    #[test]
+    fn test_rows() {
+        let mut buffer = Buffer::new();
+        buffer
+            .table("my table")
+            .unwrap()
+            .symbol("city", "New York, NY")
+            .unwrap()
+            .column_i64("count", -3)
+            .unwrap()
+            .column_f64("ratio", 1.0)
+            .unwrap()
+            .column_bool("ok", true)
+            .unwrap()
+            .column_ts("seen", Timestamp::from_micros(5))
+            .unwrap()
+            .at_now()
+            .unwrap();
+        buffer
+            .table("readings")
+            .unwrap()
+            .column_f64("temp", f64::NAN)
+            .unwrap()
+            .at(Timestamp::from_micros(1))
+            .unwrap();
+
+        assert_eq!(
+            buffer.as_str(),
+            "my\\ table,city=New\\ York\\,\\ NY count=-3i,ratio=1.0,ok=t,seen=5t\n\
+             readings temp=NaN 1000\n"
+        );
+        assert_eq!(buffer.row_count(), 2);
+    }
+
+    #[test]
+    fn test_order() {
+        let mut buffer = Buffer::new();
+        assert!(buffer.symbol("sensor", "a").is_err());
+        assert!(buffer.at_now().is_err());
+
+        let row = buffer.table("readings").unwrap();
+        assert!(row.at_now().is_err());
+        let row = row.column_f64("temp", 16.4).unwrap();
+        assert!(row.symbol("sensor", "a").is_err());
+        assert!(row.column_str("note\n", "").is_err());
+
+        // The unfinished row isn't part of the output
+        assert!(buffer.in_row());
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.as_str(), "");
+    }
+
+    #[test]
+    fn test_multiline_string() {
+        let mut buffer = Buffer::new();
+        let row = buffer.table("events").unwrap();
+        assert!(row.column_str("message", "line 1\nline 2").is_err());
+        assert!(row.column_str("message", "line 1\r").is_err());
+        // The rejected value left the row as it was
+        row.column_str("message", "a \"quoted\" \\ path")
+            .unwrap()
+            .at_now()
+            .unwrap();
+        assert_eq!(
+            buffer.as_str(),
+            "events message=\"a \\\"quoted\\\" \\\\ path\"\n"
+        );
+        assert_eq!(buffer.row_count(), 1);
+        assert!(!buffer.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::ingress::offline::OfflineBuffer;
+use crate::ingress::stream::IlpStream;
+use crate::ingress::{AutoFlush, Sender};
+use crate::{Error, QuestDB};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::net::{lookup_host, TcpSocket, TcpStream};
+
+/// Configuration of the connection used by a [`Sender`]
+#[derive(Clone, Debug)]
+pub struct SenderConfig {
+    addr: String,
+    nodelay: bool,
+    send_buffer_size: Option<u32>,
+    connect_timeout: Option<Duration>,
+    pub(crate) reconnect_timeout: Option<Duration>,
+    offline_buffer: Option<(PathBuf, u64)>,
+    spool: Option<(PathBuf, u64, u64)>,
+    pub(crate) auto_flush: AutoFlush,
+    pub(crate) validation: Option<QuestDB>,
+    #[cfg(feature = "ilp-auth")]
+    auth: Option<crate::ingress::IlpAuth>,
+    #[cfg(feature = "ilp-tls")]
+    tls: Option<crate::ingress::TlsConfig>,
+}
+
+impl SenderConfig {
+    /// Creates the configuration for the server listening at `addr`, e.g. `localhost:9009`
+    pub fn new(addr: &str) -> Self {
+        SenderConfig {
+            addr: String::from(addr),
+            nodelay: true,
+            send_buffer_size: None,
+            connect_timeout: None,
+            reconnect_timeout: None,
+            offline_buffer: None,
+            spool: None,
+            auto_flush: AutoFlush::default(),
+            validation: None,
+            #[cfg(feature = "ilp-auth")]
+            auth: None,
+            #[cfg(feature = "ilp-tls")]
+            tls: None,
+        }
+    }
+
+    /// Sets TCP_NODELAY on the socket. Enabled by default, as Nagle's algorithm delays small
+    /// batches waiting for more data to send.
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Sets the size of the socket's send buffer (SO_SNDBUF). The operating system default is
+    /// used when not set.
+    pub fn send_buffer_size(mut self, size: u32) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Gives up connecting after `timeout`. Waits for the operating system to give up when not
+    /// set.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Reconnects when the connection broke, retrying with exponential backoff and jitter for
+    /// up to `timeout`. The lines written since the last flush are kept, in memory or in the
+    /// offline buffer, and resent over the new connection by [`Sender::flush`]. Without an
+    /// offline buffer they are dropped once reconnecting failed, see
+    /// [`SenderStats`](crate::ingress::SenderStats).
+    pub fn reconnect_timeout(mut self, timeout: Duration) -> Self {
+        self.reconnect_timeout = Some(timeout);
+        self
+    }
+
+    /// Authenticates with the key on connecting, for servers requiring token authentication
+    #[cfg(feature = "ilp-auth")]
+    pub fn auth(mut self, auth: crate::ingress::IlpAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Encrypts the connection, for servers reached over untrusted networks. Authentication
+    /// happens after the TLS handshake.
+    #[cfg(feature = "ilp-tls")]
+    pub fn tls(mut self, tls: crate::ingress::TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Flushes automatically once the policy's limits are reached
+    pub fn auto_flush(mut self, auto_flush: AutoFlush) -> Self {
+        self.auto_flush = auto_flush;
+        self
+    }
+
+    /// Checks the lines against the columns of their tables before queueing them, like
+    /// [`QuestDBBuilder::validate_writes`](crate::QuestDBBuilder::validate_writes) does for
+    /// HTTP writes. The columns are fetched over `connection` and cached there, a line that
+    /// doesn't match fails [`Sender::write_lines`] with [`Error::SchemaViolation`] and nothing
+    /// of the call is queued.
+    pub fn validate_writes(mut self, connection: &QuestDB) -> Self {
+        self.validation = Some(connection.clone());
+        self
+    }
+
+    /// Keeps lines in the append-only file at `path` until the server received them, up to
+    /// `max_bytes`. Lines survive server outages and restarts of the process, they are replayed
+    /// in order once connected again, see [`Sender`].
+    ///
+    /// The file should be on local storage and used by a single sender.
+    pub fn offline_buffer<P: AsRef<Path>>(mut self, path: P, max_bytes: u64) -> Self {
+        self.spool = None;
+        self.offline_buffer = Some((path.as_ref().to_path_buf(), max_bytes));
+        self
+    }
+
+    /// Like an [offline buffer](SenderConfig::offline_buffer), but keeps the lines in a
+    /// directory of segment files of up to `segment_bytes` each. Segments are replayed and
+    /// deleted one at a time after reconnecting, so a large backlog is sent without reading it
+    /// into memory and what was replayed before the connection broke again isn't sent twice.
+    ///
+    /// Replaces an offline buffer configured before.
+    pub fn spool<P: AsRef<Path>>(mut self, dir: P, segment_bytes: u64, max_bytes: u64) -> Self {
+        self.offline_buffer = None;
+        self.spool = Some((dir.as_ref().to_path_buf(), segment_bytes, max_bytes));
+        self
+    }
+
+    /// Connects to the server
+    ///
+    /// With an offline buffer an unreachable server doesn't fail, the sender starts
+    /// disconnected and connects on [`Sender::flush`]. Lines left in the buffer by a previous
+    /// run are sent right away.
+    pub async fn connect(self) -> Result<Sender, Error> {
+        let offline = match (&self.offline_buffer, &self.spool) {
+            (Some((path, max_bytes)), _) => OfflineBuffer::open(path, *max_bytes).await?,
+            (_, Some((dir, segment_bytes, max_bytes))) => {
+                OfflineBuffer::open_spool(dir, *segment_bytes, *max_bytes).await?
+            }
+            (None, None) => {
+                let stream = self.connect_stream_timeout().await?;
+                return Ok(Sender::new(Some(stream), self, None));
+            }
+        };
+        if offline.is_empty() {
+            let stream = self.connect_stream_timeout().await.ok();
+            return Ok(Sender::new(stream, self, Some(offline)));
+        }
+
+        // Flushing a disconnected sender connects and replays the buffer
+        let mut sender = Sender::new(None, self, Some(offline));
+        let _ = sender.flush().await;
+        Ok(sender)
+    }
+
+    /// Connects and authenticates, giving up after the connect timeout
+    pub(crate) async fn connect_stream_timeout(&self) -> Result<IlpStream, Error> {
+        let connect = async {
+            let stream = self.connect_stream().await?;
+            #[cfg(feature = "ilp-tls")]
+            let stream = match &self.tls {
+                Some(tls) => tls.connect(&self.addr, stream).await?,
+                None => IlpStream::Tcp(stream),
+            };
+            #[cfg(not(feature = "ilp-tls"))]
+            let stream = IlpStream::Tcp(stream);
+            #[allow(unused_mut)]
+            let mut stream = stream;
+            #[cfg(feature = "ilp-auth")]
+            if let Some(auth) = &self.auth {
+                auth.authenticate(&mut stream).await?;
+            }
+            Ok(stream)
+        };
+        match self.connect_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, connect)
+                .await
+                .unwrap_or_else(|_| Err(io::Error::from(io::ErrorKind::TimedOut))),
+            None => connect.await,
+        }
+        .map_err(Error::ConnectionError)
+    }
+
+    /// Tries every address `addr` resolves to until one accepts the connection
+    async fn connect_stream(&self) -> io::Result<TcpStream> {
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, "address resolved to nothing");
+
+        for addr in lookup_host(self.addr.as_str()).await? {
+            let socket = match addr {
+                std::net::SocketAddr::V4(_) => TcpSocket::new_v4()?,
+                std::net::SocketAddr::V6(_) => TcpSocket::new_v6()?,
+            };
+            if let Some(size) = self.send_buffer_size {
+                socket.set_send_buffer_size(size)?;
+            }
+
+            match socket.connect(addr).await {
+                Ok(stream) => {
+                    stream.set_nodelay(self.nodelay)?;
+                    return Ok(stream);
+                }
+                Err(e) => last_err = e,
+            }
+        }
+
+        Err(last_err)
+    }
+}
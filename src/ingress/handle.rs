@@ -0,0 +1,215 @@
+use crate::ingress::{Buffer, IlpRow, Sender};
+use crate::Error;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{self, MissedTickBehavior};
+
+enum Command {
+    Lines(String),
+    Flush(oneshot::Sender<Result<(), Error>>),
+}
+
+/// Cloneable handle queueing lines for a [`Sender`] running in a background task
+///
+/// The queue holds a bounded number of batches, so producers outpacing the server are slowed
+/// down by [`send`](SenderHandle::send) or turned away by [`try_send`](SenderHandle::try_send)
+/// instead of growing memory without limit. Created with [`Sender::spawn`].
+///
+/// The task flushes whatever is left and stops once all handles are dropped.
+///
+/// # Example
+/// ```no-test
+/// use questdb::ingress::SenderConfig;
+///
+/// let handle = SenderConfig::new("192.168.1.37:9009")
+///     .connect()
+///     .await?
+///     .spawn(1024);
+///
+/// match handle.try_send("readings,sensor=a temp=16.4\n") {
+///     Err(questdb::Error::QueueFull(_)) => eprintln!("dropping a reading, QuestDB falls behind"),
+///     res => res?,
+/// }
+/// handle.flush().await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct SenderHandle {
+    commands: mpsc::Sender<Command>,
+}
+
+impl Sender {
+    /// Moves the sender to a background task fed by a queue of up to `capacity` batches. A
+    /// `capacity` of 0 is treated as 1.
+    pub fn spawn(self, capacity: usize) -> SenderHandle {
+        let (commands, receiver) = mpsc::channel(capacity.max(1));
+        tokio::spawn(run(self, receiver));
+        SenderHandle { commands }
+    }
+}
+
+impl SenderHandle {
+    /// Queues formatted lines like [`Sender::write_lines`], waiting while the queue is full
+    pub async fn send(&self, lines: impl Into<String>) -> Result<(), Error> {
+        self.commands
+            .send(Command::Lines(lines.into()))
+            .await
+            .map_err(|_| Error::SenderClosed)
+    }
+
+    /// Queues formatted lines, failing with [`Error::QueueFull`] instead of waiting while the
+    /// queue is full
+    pub fn try_send(&self, lines: impl Into<String>) -> Result<(), Error> {
+        match self.commands.try_send(Command::Lines(lines.into())) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(Error::QueueFull(self.commands.max_capacity())),
+            Err(TrySendError::Closed(_)) => Err(Error::SenderClosed),
+        }
+    }
+
+    /// Queues a row like [`Sender::write`], failing when the queue is full
+    pub fn try_write<T: IlpRow + ?Sized>(&self, row: &T) -> Result<(), Error> {
+        let mut buffer = Buffer::new();
+        buffer.row(row)?;
+        self.try_send(buffer.as_str())
+    }
+
+    /// Waits until everything queued before was flushed. Reports the first error of writing the
+    /// lines queued since the previous flush.
+    pub async fn flush(&self) -> Result<(), Error> {
+        let (done, result) = oneshot::channel();
+        self.commands
+            .send(Command::Flush(done))
+            .await
+            .map_err(|_| Error::SenderClosed)?;
+        result.await.map_err(|_| Error::SenderClosed)?
+    }
+
+    /// Free places in the queue
+    pub fn capacity(&self) -> usize {
+        self.commands.capacity()
+    }
+}
+
+async fn run(mut sender: Sender, mut commands: mpsc::Receiver<Command>) {
+    // Nobody waits for the lines, the error is kept for the next flush
+    let mut failed = None;
+    // Time based flushes happen even while no commands arrive
+    let mut ticks = sender.flush_interval().map(|period| {
+        let mut ticks = time::interval(period);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticks
+    });
+    loop {
+        let command = match &mut ticks {
+            Some(ticks) => tokio::select! {
+                command = commands.recv() => command,
+                _ = ticks.tick() => {
+                    if let Err(e) = sender.flush_if_due().await {
+                        failed.get_or_insert(e);
+                    }
+                    continue;
+                }
+            },
+            None => commands.recv().await,
+        };
+        let Some(command) = command else {
+            break;
+        };
+        match command {
+            Command::Lines(lines) => {
+                if let Err(e) = sender.write_lines(&lines).await {
+                    failed.get_or_insert(e);
+                }
+            }
+            Command::Flush(done) => {
+                // Flush the lines written since, then report the earlier error
+                let flushed = sender.flush().await;
+                let result = match failed.take() {
+                    Some(e) => Err(e),
+                    None => flushed,
+                };
+                let _ = done.send(result);
+            }
+        }
+    }
+    let _ = sender.flush().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ingress::{AutoFlush, SenderConfig};
+    use crate::Error;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_handle() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sender = SenderConfig::new(&addr.to_string())
+            .connect()
+            .await
+            .unwrap();
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        // The task doesn't run before this test yields
+        let handle = sender.spawn(2);
+        handle.try_send("readings temp=16.4 1\n").unwrap();
+        handle.try_send("readings temp=16.5 2\n").unwrap();
+        assert!(matches!(
+            handle.try_send("readings temp=16.6 3\n"),
+            Err(Error::QueueFull(2))
+        ));
+        handle.send("readings temp=16.7 4\n").await.unwrap();
+        handle.flush().await.unwrap();
+        drop(handle);
+
+        let mut received = String::new();
+        socket.read_to_string(&mut received).await.unwrap();
+        assert_eq!(
+            received,
+            "readings temp=16.4 1\nreadings temp=16.5 2\nreadings temp=16.7 4\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interval_flush() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sender = SenderConfig::new(&addr.to_string())
+            .auto_flush(AutoFlush::new().interval(Duration::from_millis(50)))
+            .connect()
+            .await
+            .unwrap();
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        // Nothing follows the line, the background task flushes it on its own
+        let handle = sender.spawn(2);
+        handle.send("readings temp=16.4 1\n").await.unwrap();
+        let mut received = [0; 21];
+        tokio::time::timeout(Duration::from_secs(2), socket.read_exact(&mut received))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&received, b"readings temp=16.4 1\n");
+        drop(handle);
+    }
+
+    #[tokio::test]
+    async fn test_zero_capacity() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sender = SenderConfig::new(&addr.to_string())
+            .connect()
+            .await
+            .unwrap();
+
+        let handle = sender.spawn(0);
+        handle.try_send("readings temp=16.4 1\n").unwrap();
+        assert!(matches!(
+            handle.try_send("readings temp=16.5 2\n"),
+            Err(Error::QueueFull(1))
+        ));
+    }
+}
@@ -0,0 +1,55 @@
+//! Ingestion over the InfluxDB Line Protocol (ILP)
+//!
+//! QuestDB ingests line protocol on port 9009, which is far faster than inserting through the
+//! HTTP /exec endpoint. Newer versions also accept it over HTTP, see [`Batch`].
+//!
+//! # Example
+//! ```no-test
+//! use questdb::ingress::{Buffer, SenderConfig};
+//! use questdb::Timestamp;
+//! use std::time::Duration;
+//!
+//! let mut sender = SenderConfig::new("192.168.1.37:9009")
+//!     .connect_timeout(Duration::from_secs(5))
+//!     .connect()
+//!     .await?;
+//!
+//! let mut buffer = Buffer::new();
+//! buffer
+//!     .table("readings")?
+//!     .symbol("sensor", "a")?
+//!     .column_f64("temp", 16.4)?
+//!     .at(Timestamp::now())?;
+//! sender.flush_buffer(&mut buffer).await?;
+//! ```
+
+#[cfg(feature = "ilp-auth")]
+mod auth;
+mod autoflush;
+mod batch;
+mod buffer;
+mod config;
+mod handle;
+mod offline;
+mod row;
+mod sender;
+mod stream;
+#[cfg(feature = "ilp-tls")]
+mod tls;
+mod udp;
+mod validate;
+
+#[cfg(feature = "ilp-auth")]
+pub use auth::IlpAuth;
+pub use autoflush::AutoFlush;
+pub use batch::{Batch, Precision};
+pub use buffer::Buffer;
+pub use config::SenderConfig;
+pub use handle::SenderHandle;
+#[cfg(feature = "derive")]
+pub use questdb_derive::IlpRow;
+pub use row::{IlpRow, IlpValue};
+pub use sender::{Sender, SenderStats};
+#[cfg(feature = "ilp-tls")]
+pub use tls::TlsConfig;
+pub use udp::UdpSender;
@@ -0,0 +1,207 @@
+use crate::ingress::stream::IlpStream;
+use crate::Error;
+use std::collections::VecDeque;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter};
+
+/// Append-only storage holding the lines sent since the last successful flush
+///
+/// Lines are written to disk before the socket, so lines the server may not have received are
+/// always on disk, even when the process dies, and are replayed after reconnecting.
+///
+/// The lines are either kept in a single file, or in a directory of numbered segment files
+/// when spooling. Full segments are replayed and deleted one by one, so a large backlog is
+/// never read into memory at once and the progress of an interrupted replay is kept.
+#[derive(Debug)]
+pub(crate) struct OfflineBuffer {
+    /// Full segments, oldest first, with their sizes
+    segments: VecDeque<(PathBuf, u64)>,
+    /// The file lines are appended to
+    current: File,
+    current_path: PathBuf,
+    current_len: u64,
+    /// Directory and size of the segments when spooling, the current file never rotates
+    /// otherwise
+    spool: Option<(PathBuf, u64)>,
+    next_segment: u64,
+    len: u64,
+    max_bytes: u64,
+}
+
+impl OfflineBuffer {
+    /// Opens the file, keeping the lines left by a previous run
+    pub(crate) async fn open(path: &Path, max_bytes: u64) -> Result<Self, Error> {
+        let current = open_segment(path).await?;
+        let len = current.metadata().await?.len();
+        Ok(OfflineBuffer {
+            segments: VecDeque::new(),
+            current,
+            current_path: path.to_path_buf(),
+            current_len: len,
+            spool: None,
+            next_segment: 0,
+            len,
+            max_bytes,
+        })
+    }
+
+    /// Opens the spool directory, keeping the segments left by a previous run
+    pub(crate) async fn open_spool(
+        dir: &Path,
+        segment_bytes: u64,
+        max_bytes: u64,
+    ) -> Result<Self, Error> {
+        tokio::fs::create_dir_all(dir).await?;
+        let mut found = Vec::new();
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let seq = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".ilp"))
+                .and_then(|seq| seq.parse::<u64>().ok());
+            if let Some(seq) = seq {
+                found.push((seq, path, entry.metadata().await?.len()));
+            }
+        }
+        found.sort_by_key(|(seq, _, _)| *seq);
+
+        // Keep appending to the newest segment
+        let (current_seq, current_path, current_len) =
+            found.pop().unwrap_or_else(|| (0, segment_path(dir, 0), 0));
+        let segments: VecDeque<_> = found.into_iter().map(|(_, p, len)| (p, len)).collect();
+        let len = segments.iter().map(|(_, len)| len).sum::<u64>() + current_len;
+        Ok(OfflineBuffer {
+            segments,
+            current: open_segment(&current_path).await?,
+            current_path,
+            current_len,
+            spool: Some((dir.to_path_buf(), segment_bytes)),
+            next_segment: current_seq + 1,
+            len,
+            max_bytes,
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Appends the lines, refusing them with [`Error::BatchFull`] when the buffer would grow
+    /// beyond its limit
+    pub(crate) async fn append(&mut self, lines: &str) -> Result<(), Error> {
+        if self.len + lines.len() as u64 > self.max_bytes {
+            return Err(Error::BatchFull(format!("{} bytes", self.max_bytes)));
+        }
+        if let Some((dir, segment_bytes)) = &self.spool {
+            if self.current_len > 0 && self.current_len + lines.len() as u64 > *segment_bytes {
+                let path = segment_path(dir, self.next_segment);
+                self.current = open_segment(&path).await?;
+                let full = std::mem::replace(&mut self.current_path, path);
+                self.segments.push_back((full, self.current_len));
+                self.next_segment += 1;
+                self.current_len = 0;
+            }
+        }
+        self.current.write_all(lines.as_bytes()).await?;
+        self.current.flush().await?;
+        self.current_len += lines.len() as u64;
+        self.len += lines.len() as u64;
+        Ok(())
+    }
+
+    /// Sends the buffered lines, oldest first, over a new connection. Full segments are deleted
+    /// as soon as they were sent, the rest stays until [`clear`](OfflineBuffer::clear).
+    pub(crate) async fn replay(&mut self, stream: &mut BufWriter<IlpStream>) -> Result<(), Error> {
+        while let Some((path, len)) = self.segments.front() {
+            let lines = tokio::fs::read(path).await?;
+            send(stream, &lines).await?;
+            tokio::fs::remove_file(path).await?;
+            self.len -= len;
+            self.segments.pop_front();
+        }
+        if self.current_len > 0 {
+            let mut lines = Vec::with_capacity(self.current_len as usize);
+            self.current.seek(SeekFrom::Start(0)).await?;
+            self.current.read_to_end(&mut lines).await?;
+            send(stream, &lines).await?;
+        }
+        Ok(())
+    }
+
+    /// Forgets the buffered lines once the server received them
+    pub(crate) async fn clear(&mut self) -> Result<(), Error> {
+        while let Some((path, len)) = self.segments.pop_front() {
+            tokio::fs::remove_file(path).await?;
+            self.len -= len;
+        }
+        self.current.set_len(0).await?;
+        self.current_len = 0;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+/// Segments are numbered, zero padded so their names sort in the order they were written
+fn segment_path(dir: &Path, seq: u64) -> PathBuf {
+    dir.join(format!("{:020}.ilp", seq))
+}
+
+async fn open_segment(path: &Path) -> Result<File, Error> {
+    let file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .await?;
+    Ok(file)
+}
+
+async fn send(stream: &mut BufWriter<IlpStream>, lines: &[u8]) -> Result<(), Error> {
+    stream
+        .write_all(lines)
+        .await
+        .map_err(Error::ConnectionError)?;
+    stream.flush().await.map_err(Error::ConnectionError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OfflineBuffer;
+
+    #[tokio::test]
+    async fn test_spool_segments() {
+        let dir = std::env::temp_dir().join(format!("questdb-spool-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut spool = OfflineBuffer::open_spool(&dir, 30, 1000).await.unwrap();
+        spool.append("readings temp=16.4 1\n").await.unwrap();
+        spool.append("readings temp=16.5 2\n").await.unwrap();
+        spool.append("readings temp=16.6 3\n").await.unwrap();
+        assert_eq!(spool.len(), 63);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+        drop(spool);
+
+        // A restarted process finds the segments again
+        let mut spool = OfflineBuffer::open_spool(&dir, 30, 1000).await.unwrap();
+        assert_eq!(spool.len(), 63);
+        spool.append("readings temp=16.7 4\n").await.unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 4);
+        assert_eq!(
+            std::fs::read_to_string(dir.join(format!("{:020}.ilp", 3))).unwrap(),
+            "readings temp=16.7 4\n"
+        );
+
+        spool.clear().await.unwrap();
+        assert!(spool.is_empty());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
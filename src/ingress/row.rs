@@ -0,0 +1,163 @@
+use crate::ingress::Buffer;
+use crate::{Error, Timestamp};
+
+/// A type that is written as one row of line protocol
+///
+/// Usually derived, the `derive` feature provides `#[derive(IlpRow)]`.
+///
+/// # Example
+/// ```no-test
+/// use questdb::ingress::IlpRow;
+/// use questdb::Timestamp;
+///
+/// #[derive(IlpRow)]
+/// #[ilp(table = "readings")]
+/// struct Reading {
+///     #[ilp(symbol)]
+///     sensor: String,
+///     temp: f64,
+///     #[ilp(rename = "humidity_pct")]
+///     humidity: Option<i64>,
+///     #[ilp(timestamp)]
+///     ts: Timestamp,
+/// }
+///
+/// sender.write(&reading).await?;
+/// ```
+pub trait IlpRow {
+    /// Adds the row to the buffer, including its timestamp
+    fn write_row(&self, buffer: &mut Buffer) -> Result<(), Error>;
+}
+
+/// A value of a column in line protocol
+pub trait IlpValue {
+    fn write_column(&self, buffer: &mut Buffer, name: &str) -> Result<(), Error>;
+}
+
+impl IlpValue for bool {
+    fn write_column(&self, buffer: &mut Buffer, name: &str) -> Result<(), Error> {
+        buffer.column_bool(name, *self).map(drop)
+    }
+}
+
+macro_rules! integer_value {
+    ($($ty:ty),*) => {
+        $(impl IlpValue for $ty {
+            fn write_column(&self, buffer: &mut Buffer, name: &str) -> Result<(), Error> {
+                buffer.column_i64(name, i64::from(*self)).map(drop)
+            }
+        })*
+    };
+}
+
+integer_value!(i8, i16, i32, i64, u8, u16, u32);
+
+impl IlpValue for f32 {
+    fn write_column(&self, buffer: &mut Buffer, name: &str) -> Result<(), Error> {
+        buffer.column_f64(name, f64::from(*self)).map(drop)
+    }
+}
+
+impl IlpValue for f64 {
+    fn write_column(&self, buffer: &mut Buffer, name: &str) -> Result<(), Error> {
+        buffer.column_f64(name, *self).map(drop)
+    }
+}
+
+impl IlpValue for str {
+    fn write_column(&self, buffer: &mut Buffer, name: &str) -> Result<(), Error> {
+        buffer.column_str(name, self).map(drop)
+    }
+}
+
+impl IlpValue for String {
+    fn write_column(&self, buffer: &mut Buffer, name: &str) -> Result<(), Error> {
+        buffer.column_str(name, self).map(drop)
+    }
+}
+
+impl IlpValue for Timestamp {
+    fn write_column(&self, buffer: &mut Buffer, name: &str) -> Result<(), Error> {
+        buffer.column_ts(name, *self).map(drop)
+    }
+}
+
+/// Missing values are left out of the row, the server stores null
+impl<T: IlpValue> IlpValue for Option<T> {
+    fn write_column(&self, buffer: &mut Buffer, name: &str) -> Result<(), Error> {
+        match self {
+            Some(value) => value.write_column(buffer, name),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: IlpValue + ?Sized> IlpValue for &T {
+    fn write_column(&self, buffer: &mut Buffer, name: &str) -> Result<(), Error> {
+        (**self).write_column(buffer, name)
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use crate::ingress::{Buffer, IlpRow};
+    use crate::Timestamp;
+
+    #[derive(IlpRow)]
+    #[ilp(table = "readings")]
+    struct Reading<'a> {
+        #[ilp(symbol)]
+        sensor: &'a str,
+        temp: f64,
+        #[ilp(rename = "humidity_pct")]
+        humidity: Option<i64>,
+        ok: bool,
+        #[ilp(skip)]
+        _note: &'a str,
+        #[ilp(timestamp)]
+        ts: Timestamp,
+    }
+
+    #[derive(IlpRow)]
+    struct SensorEvent {
+        message: String,
+    }
+
+    #[test]
+    fn test_derive() {
+        let mut buffer = Buffer::new();
+        buffer
+            .row(&Reading {
+                sensor: "a",
+                temp: 16.5,
+                humidity: None,
+                ok: true,
+                _note: "ignored",
+                ts: Timestamp::from_micros(1),
+            })
+            .unwrap();
+        buffer
+            .row(&SensorEvent {
+                message: String::from("restart"),
+            })
+            .unwrap();
+        assert_eq!(
+            buffer.as_str(),
+            "readings,sensor=a temp=16.5,ok=t 1000\nsensor_event message=\"restart\"\n"
+        );
+
+        // A failing row leaves the buffer as it was
+        assert!(buffer
+            .row(&Reading {
+                sensor: "a\nb",
+                temp: 1.0,
+                humidity: Some(2),
+                ok: false,
+                _note: "",
+                ts: Timestamp::from_micros(2),
+            })
+            .is_err());
+        assert_eq!(buffer.row_count(), 2);
+        assert!(!buffer.in_row());
+    }
+}
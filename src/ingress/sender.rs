@@ -0,0 +1,424 @@
+use crate::ingress::offline::OfflineBuffer;
+use crate::ingress::stream::IlpStream;
+use crate::ingress::{validate, Buffer, IlpRow, SenderConfig};
+use crate::{retry, Error};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// Connection sending line protocol to QuestDB over TCP
+///
+/// Created with [`SenderConfig::connect`](crate::ingress::SenderConfig::connect). With an
+/// [offline buffer](SenderConfig::offline_buffer) the sender outlives the connection: lines
+/// are kept on disk while the server is unreachable and replayed in order by the next
+/// successful [`flush`](Sender::flush). With a [reconnect
+/// timeout](SenderConfig::reconnect_timeout) a broken connection is opened again and the lines
+/// since the last flush are resent.
+pub struct Sender {
+    /// `None` while disconnected, which only happens with an offline buffer
+    stream: Option<BufWriter<IlpStream>>,
+    config: SenderConfig,
+    offline: Option<OfflineBuffer>,
+    /// Lines since the last flush, kept for resending when reconnecting without an offline
+    /// buffer
+    retained: Option<Vec<u8>>,
+    stats: SenderStats,
+    /// Lines and bytes queued since the last flush
+    pending_rows: usize,
+    pending_bytes: usize,
+    /// When the oldest line queued since the last flush was written
+    pending_since: Instant,
+}
+
+impl Sender {
+    pub(crate) fn new(
+        stream: Option<IlpStream>,
+        config: SenderConfig,
+        offline: Option<OfflineBuffer>,
+    ) -> Self {
+        let retained = match (&offline, config.reconnect_timeout) {
+            (None, Some(_)) => Some(Vec::new()),
+            _ => None,
+        };
+        Sender {
+            stream: stream.map(BufWriter::new),
+            config,
+            offline,
+            retained,
+            stats: SenderStats::default(),
+            pending_rows: 0,
+            pending_bytes: 0,
+            pending_since: Instant::now(),
+        }
+    }
+
+    /// Queues already formatted lines for sending. Every line, including the last one, must end
+    /// with a newline.
+    ///
+    /// With an offline buffer the lines are stored on disk first, a broken connection doesn't
+    /// fail the call, and [`Error::BatchFull`] is returned when the buffer is full.
+    ///
+    /// Flushes afterwards when the [auto flush](SenderConfig::auto_flush) policy says so.
+    pub async fn write_lines(&mut self, lines: &str) -> Result<(), Error> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        if let Some(connection) = &self.config.validation {
+            validate::validate_lines(connection, lines.lines()).await?;
+        }
+        self.queue(lines).await?;
+        if self.pending_rows == 0 {
+            self.pending_since = Instant::now();
+        }
+        self.pending_rows += lines.matches('\n').count();
+        self.pending_bytes += lines.len();
+        self.flush_if_due().await
+    }
+
+    /// Queues the complete rows of the buffer like [`write_lines`](Sender::write_lines) and
+    /// clears it
+    pub async fn write_buffer(&mut self, buffer: &mut Buffer) -> Result<(), Error> {
+        if buffer.in_row() {
+            return Err(Error::InvalidLine(String::from(
+                "buffer ends with an unfinished row",
+            )));
+        }
+        self.write_lines(buffer.as_str()).await?;
+        buffer.clear();
+        Ok(())
+    }
+
+    /// Flushes if the queued lines reached a limit of the auto flush policy, for callers that
+    /// stop writing for a while and want time based flushes to happen anyway
+    pub async fn flush_if_due(&mut self) -> Result<(), Error> {
+        let age = self.pending_since.elapsed();
+        match self
+            .config
+            .auto_flush
+            .due(self.pending_rows, self.pending_bytes, age)
+        {
+            true => self.flush().await,
+            false => Ok(()),
+        }
+    }
+
+    /// How often [`flush_if_due`](Sender::flush_if_due) needs calling for time based flushes
+    pub(crate) fn flush_interval(&self) -> Option<Duration> {
+        self.config.auto_flush.max_age()
+    }
+
+    async fn queue(&mut self, lines: &str) -> Result<(), Error> {
+        if let Some(offline) = &mut self.offline {
+            offline.append(lines).await?;
+        } else if let Some(retained) = &mut self.retained {
+            retained.extend_from_slice(lines.as_bytes());
+        }
+
+        // Only disconnected when the lines are kept for replaying
+        let Some(stream) = &mut self.stream else {
+            return Ok(());
+        };
+        if let Err(e) = stream.write_all(lines.as_bytes()).await {
+            if !self.can_replay() {
+                return Err(Error::ConnectionError(e));
+            }
+            // The lines are kept and replayed once reconnected
+            self.stream = None;
+        }
+        Ok(())
+    }
+
+    fn can_replay(&self) -> bool {
+        self.offline.is_some() || self.retained.is_some()
+    }
+
+    /// Sends all queued lines to the server
+    ///
+    /// With an offline buffer a lost connection is opened again first and the buffered lines
+    /// are replayed, since the server may not have received them. Lines that did arrive before
+    /// the connection broke are sent twice, deduplication on the table makes this harmless. On
+    /// error the lines stay buffered for the next call.
+    pub async fn flush(&mut self) -> Result<(), Error> {
+        self.flush_stream().await?;
+        self.pending_rows = 0;
+        self.pending_bytes = 0;
+        if let Some(connection) = &self.config.validation {
+            connection.inner.metadata.written();
+        }
+        Ok(())
+    }
+
+    async fn flush_stream(&mut self) -> Result<(), Error> {
+        if let Some(stream) = &mut self.stream {
+            match stream.flush().await {
+                Ok(()) => return self.sent().await,
+                Err(e) if !self.can_replay() => return Err(Error::ConnectionError(e)),
+                Err(_) => self.stream = None,
+            }
+        }
+
+        let mut stream = match self.reconnect().await {
+            Ok(stream) => stream,
+            Err(e) => {
+                // Without an offline buffer the lines can't be kept forever
+                if let Some(retained) = self.retained.as_mut().filter(|r| !r.is_empty()) {
+                    retained.clear();
+                    self.stats.dropped_batches += 1;
+                }
+                return Err(e);
+            }
+        };
+        if let Some(offline) = &mut self.offline {
+            if !offline.is_empty() {
+                offline.replay(&mut stream).await?;
+                self.stats.resent_batches += 1;
+            }
+        } else if let Some(retained) = self.retained.as_ref().filter(|r| !r.is_empty()) {
+            stream
+                .write_all(retained)
+                .await
+                .map_err(Error::ConnectionError)?;
+            stream.flush().await.map_err(Error::ConnectionError)?;
+            self.stats.resent_batches += 1;
+        }
+        self.stream = Some(stream);
+        self.sent().await
+    }
+
+    /// Connects again, retrying with backoff until the reconnect timeout is used up
+    async fn reconnect(&mut self) -> Result<BufWriter<IlpStream>, Error> {
+        let deadline = Instant::now() + self.config.reconnect_timeout.unwrap_or_default();
+        let mut attempt = 0;
+        loop {
+            match self.config.connect_stream_timeout().await {
+                Ok(stream) => {
+                    self.stats.reconnects += 1;
+                    return Ok(BufWriter::new(stream));
+                }
+                Err(e) => {
+                    let wait = retry::backoff(attempt);
+                    if Instant::now() + wait >= deadline {
+                        return Err(e);
+                    }
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Forgets the lines kept for replaying once they were sent
+    async fn sent(&mut self) -> Result<(), Error> {
+        if let Some(retained) = &mut self.retained {
+            retained.clear();
+        }
+        match &mut self.offline {
+            Some(offline) => offline.clear().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Queues a row like [`write_lines`](Sender::write_lines)
+    pub async fn write<T: IlpRow + ?Sized>(&mut self, row: &T) -> Result<(), Error> {
+        let mut buffer = Buffer::new();
+        buffer.row(row)?;
+        self.write_lines(buffer.as_str()).await
+    }
+
+    /// Sends the complete rows of the buffer and clears it. Fails without sending anything
+    /// while a row of the buffer is unfinished.
+    pub async fn flush_buffer(&mut self, buffer: &mut Buffer) -> Result<(), Error> {
+        if buffer.in_row() {
+            return Err(Error::InvalidLine(String::from(
+                "buffer ends with an unfinished row",
+            )));
+        }
+        self.queue(buffer.as_str()).await?;
+        self.flush().await?;
+        buffer.clear();
+        Ok(())
+    }
+
+    /// Whether the sender currently has a connection to the server
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Bytes of lines kept in the offline buffer until they were flushed to the socket
+    pub fn buffered_bytes(&self) -> u64 {
+        self.offline.as_ref().map_or(0, |o| o.len())
+    }
+
+    /// Reconnections and the batches they resent or lost so far
+    pub fn stats(&self) -> SenderStats {
+        self.stats.clone()
+    }
+}
+
+/// Counters of a [`Sender`]'s connection problems. A batch is the lines written between two
+/// flushes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SenderStats {
+    /// Connections opened after the sender was created
+    pub reconnects: u64,
+    /// Batches sent again after reconnecting, the server may have received them before
+    pub resent_batches: u64,
+    /// Batches discarded because reconnecting failed and there is no offline buffer to keep
+    /// them in
+    pub dropped_batches: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ingress::{AutoFlush, SenderConfig};
+    use crate::schema::TableColumn;
+    use crate::{Error, QuestDB};
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut sender = SenderConfig::new(&addr.to_string())
+            .reconnect_timeout(Duration::from_secs(5))
+            .connect()
+            .await
+            .unwrap();
+        drop(listener.accept().await.unwrap());
+
+        // Writing to the closed connection fails after a few attempts
+        let mut last = String::new();
+        for i in 0..100 {
+            last = format!("readings temp=16.4 {}\n", i);
+            sender.write_lines(&last).await.unwrap();
+            sender.flush().await.unwrap();
+            if sender.stats().reconnects > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(sender.stats().reconnects, 1);
+        assert_eq!(sender.stats().resent_batches, 1);
+        drop(sender);
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = String::new();
+        socket.read_to_string(&mut received).await.unwrap();
+        assert_eq!(received, last);
+    }
+
+    #[tokio::test]
+    async fn test_validate_writes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Nothing listens there, the columns come from the cache
+        let connection = QuestDB::new("http://127.0.0.1:1");
+        let column = |name: &str, column_type: &str, designated| TableColumn {
+            name: String::from(name),
+            column_type: String::from(column_type),
+            indexed: false,
+            designated,
+        };
+        connection.inner.metadata.insert(
+            "readings",
+            Some(vec![
+                column("sensor", "SYMBOL", false),
+                column("temp", "DOUBLE", false),
+                column("ts", "TIMESTAMP", true),
+            ]),
+        );
+        connection.inner.metadata.insert("other", None);
+
+        let mut sender = SenderConfig::new(&addr.to_string())
+            .validate_writes(&connection)
+            .connect()
+            .await
+            .unwrap();
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        let written = sender
+            .write_lines("readings,sensor=a temp=16.4 1\nreadings temp=\"hot\" 2\n")
+            .await;
+        assert!(matches!(
+            written,
+            Err(Error::SchemaViolation { line: 2, .. })
+        ));
+        sender
+            .write_lines("readings,sensor=a temp=16.4 1\nother tmep=1i\n")
+            .await
+            .unwrap();
+        sender.flush().await.unwrap();
+        drop(sender);
+
+        let mut received = String::new();
+        socket.read_to_string(&mut received).await.unwrap();
+        assert_eq!(received, "readings,sensor=a temp=16.4 1\nother tmep=1i\n");
+    }
+
+    #[tokio::test]
+    async fn test_offline_buffer() {
+        let path = std::env::temp_dir().join(format!("questdb-offline-{}.ilp", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // Reserve a port nothing listens on yet
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let mut sender = SenderConfig::new(&addr.to_string())
+            .offline_buffer(&path, 64)
+            .connect()
+            .await
+            .unwrap();
+        assert!(!sender.is_connected());
+        sender.write_lines("readings temp=16.4 1\n").await.unwrap();
+        sender.write_lines("readings temp=16.5 2\n").await.unwrap();
+        assert!(sender.flush().await.is_err());
+        assert!(sender
+            .write_lines("readings temp=16.6 3\nreadings temp=16.7 4\n")
+            .await
+            .is_err());
+        assert_eq!(sender.buffered_bytes(), 42);
+
+        // The server comes back
+        let listener = TcpListener::bind(addr).await.unwrap();
+        sender.flush().await.unwrap();
+        assert!(sender.is_connected());
+        assert_eq!(sender.buffered_bytes(), 0);
+        drop(sender);
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = String::new();
+        socket.read_to_string(&mut received).await.unwrap();
+        assert_eq!(received, "readings temp=16.4 1\nreadings temp=16.5 2\n");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_auto_flush() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut sender = SenderConfig::new(&addr.to_string())
+            .auto_flush(AutoFlush::new().rows(2))
+            .connect()
+            .await
+            .unwrap();
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        sender.write_lines("readings temp=16.4 1\n").await.unwrap();
+        sender.write_lines("readings temp=16.5 2\n").await.unwrap();
+        let mut received = vec![0; 42];
+        socket.read_exact(&mut received).await.unwrap();
+        assert_eq!(
+            received,
+            b"readings temp=16.4 1\nreadings temp=16.5 2\n".to_vec()
+        );
+    }
+}
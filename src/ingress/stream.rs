@@ -0,0 +1,57 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+/// Connection of a [`Sender`](crate::ingress::Sender), plain or wrapped in TLS
+#[derive(Debug)]
+pub(crate) enum IlpStream {
+    Tcp(TcpStream),
+    #[cfg(feature = "ilp-tls")]
+    Tls(Box<tokio_rustls::client::TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for IlpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            IlpStream::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+            #[cfg(feature = "ilp-tls")]
+            IlpStream::Tls(s) => Pin::new(s.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for IlpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            IlpStream::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+            #[cfg(feature = "ilp-tls")]
+            IlpStream::Tls(s) => Pin::new(s.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            IlpStream::Tcp(s) => Pin::new(s).poll_flush(cx),
+            #[cfg(feature = "ilp-tls")]
+            IlpStream::Tls(s) => Pin::new(s.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            IlpStream::Tcp(s) => Pin::new(s).poll_shutdown(cx),
+            #[cfg(feature = "ilp-tls")]
+            IlpStream::Tls(s) => Pin::new(s.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
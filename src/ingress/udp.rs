@@ -0,0 +1,166 @@
+use crate::ingress::Buffer;
+use crate::Error;
+use std::io;
+use tokio::net::{lookup_host, UdpSocket};
+
+/// Largest datagram sent by default, leaves room for the IP and UDP headers within the usual
+/// 1500 bytes MTU
+const DEFAULT_MAX_DATAGRAM_SIZE: usize = 1400;
+
+/// Sends line protocol in UDP datagrams, without any acknowledgement
+///
+/// Datagrams may be dropped or reordered, so this only suits metrics where losing a sample is
+/// acceptable. Lines are never split across datagrams, queued lines are packed into as few
+/// datagrams as the size limit allows.
+///
+/// # Example
+/// ```no-test
+/// use questdb::ingress::UdpSender;
+///
+/// let mut sender = UdpSender::connect("192.168.1.37:9009").await?.max_datagram_size(1200);
+/// sender.write_lines("readings,sensor=a temp=16.4\n");
+/// sender.flush().await?;
+/// ```
+#[derive(Debug)]
+pub struct UdpSender {
+    socket: UdpSocket,
+    pending: String,
+    max_datagram_size: usize,
+}
+
+impl UdpSender {
+    /// Creates a socket sending to the server listening at `addr`, e.g. `localhost:9009`
+    pub async fn connect(addr: &str) -> Result<Self, Error> {
+        let target = lookup_host(addr)
+            .await
+            .map_err(Error::ConnectionError)?
+            .next()
+            .ok_or_else(|| {
+                Error::ConnectionError(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "address resolved to nothing",
+                ))
+            })?;
+        let local = match target {
+            std::net::SocketAddr::V4(_) => "0.0.0.0:0",
+            std::net::SocketAddr::V6(_) => "[::]:0",
+        };
+        let socket = UdpSocket::bind(local)
+            .await
+            .map_err(Error::ConnectionError)?;
+        socket
+            .connect(target)
+            .await
+            .map_err(Error::ConnectionError)?;
+
+        Ok(UdpSender {
+            socket,
+            pending: String::new(),
+            max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
+        })
+    }
+
+    /// Largest datagram to send in bytes, 1400 by default. Should stay below the path MTU so
+    /// datagrams aren't fragmented, and below the server's receive buffer.
+    pub fn max_datagram_size(mut self, size: usize) -> Self {
+        self.max_datagram_size = size;
+        self
+    }
+
+    /// Queues already formatted lines for sending. Every line, including the last one, must end
+    /// with a newline.
+    pub fn write_lines(&mut self, lines: &str) {
+        self.pending.push_str(lines);
+    }
+
+    /// Sends the queued lines
+    ///
+    /// Fails with [`Error::BatchFull`] without sending anything when a line doesn't fit in a
+    /// datagram. The queue is emptied even if sending fails, lines are never sent twice.
+    pub async fn flush(&mut self) -> Result<(), Error> {
+        let pending = std::mem::take(&mut self.pending);
+        for datagram in datagrams(&pending, self.max_datagram_size)? {
+            self.socket
+                .send(datagram.as_bytes())
+                .await
+                .map_err(Error::ConnectionError)?;
+        }
+        Ok(())
+    }
+
+    /// Sends the complete rows of the buffer and clears it
+    pub async fn flush_buffer(&mut self, buffer: &mut Buffer) -> Result<(), Error> {
+        if buffer.in_row() {
+            return Err(Error::InvalidLine(String::from(
+                "buffer ends with an unfinished row",
+            )));
+        }
+        self.write_lines(buffer.as_str());
+        self.flush().await?;
+        buffer.clear();
+        Ok(())
+    }
+}
+
+/// Packs whole lines into chunks of at most `max_size` bytes
+fn datagrams(lines: &str, max_size: usize) -> Result<Vec<&str>, Error> {
+    let mut datagrams = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+
+    for line in lines.split_inclusive('\n') {
+        if line.len() > max_size {
+            return Err(Error::BatchFull(format!(
+                "{} bytes per datagram, got a line of {} bytes",
+                max_size,
+                line.len()
+            )));
+        }
+        if end + line.len() - start > max_size {
+            datagrams.push(&lines[start..end]);
+            start = end;
+        }
+        end += line.len();
+    }
+    if end > start {
+        datagrams.push(&lines[start..end]);
+    }
+    Ok(datagrams)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{datagrams, UdpSender};
+    use tokio::net::UdpSocket;
+
+    #[test]
+    fn test_datagrams() {
+        let lines = "t a=1i\nt a=22i\nt a=3i\n";
+        assert_eq!(
+            datagrams(lines, 15).unwrap(),
+            ["t a=1i\nt a=22i\n", "t a=3i\n"]
+        );
+        assert_eq!(datagrams(lines, 100).unwrap(), [lines]);
+        assert!(datagrams(lines, 7).is_err());
+        assert!(datagrams("", 10).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+
+        let mut sender = UdpSender::connect(&addr)
+            .await
+            .unwrap()
+            .max_datagram_size(10);
+        sender.write_lines("t a=1i\nt a=2i\n");
+        sender.flush().await.unwrap();
+
+        let mut buf = [0; 64];
+        for expected in ["t a=1i\n", "t a=2i\n"] {
+            let n = server.recv(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], expected.as_bytes());
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use crate::ingress::Batch;
+use crate::schema::TableColumn;
+use crate::{Error, QuestDB};
+
+/// Checks every line of the batch against the columns of its table. Lines for tables that don't
+/// exist yet are accepted, they create the table.
+pub(crate) async fn validate(connection: &QuestDB, batch: &Batch) -> Result<(), Error> {
+    let lines = (1..=batch.len()).map(|n| batch.line(n).unwrap_or_default());
+    validate_lines(connection, lines).await
+}
+
+/// Like [`validate`], for the lines written to a [`Sender`](crate::ingress::Sender), numbered
+/// from 1
+pub(crate) async fn validate_lines<'a>(
+    connection: &QuestDB,
+    lines: impl Iterator<Item = &'a str>,
+) -> Result<(), Error> {
+    let metadata = &connection.inner.metadata;
+    for (i, text) in lines.enumerate() {
+        let violation = |message: String| Error::SchemaViolation {
+            line: i + 1,
+            message,
+        };
+
+        let line = parse_line(text).map_err(violation)?;
+        if let Some(columns) = metadata.columns(connection, &line.table).await? {
+            check_line(&line, &columns).map_err(violation)?;
+        }
+    }
+    Ok(())
+}
+
+/// Type of an ILP value, as determined by its syntax
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ValueType {
+    Symbol,
+    Integer,
+    Long256,
+    Float,
+    String,
+    Boolean,
+    Timestamp,
+}
+
+impl ValueType {
+    fn of(value: &str) -> ValueType {
+        if value.starts_with('"') {
+            ValueType::String
+        } else if [
+            "t", "T", "true", "True", "TRUE", "f", "F", "false", "False", "FALSE",
+        ]
+        .contains(&value)
+        {
+            ValueType::Boolean
+        } else if value.starts_with("0x") && value.ends_with('i') {
+            ValueType::Long256
+        } else if value.ends_with('i') {
+            ValueType::Integer
+        } else if value.ends_with('t') {
+            ValueType::Timestamp
+        } else {
+            ValueType::Float
+        }
+    }
+
+    /// Column types the server stores values of this type in
+    fn column_types(&self) -> &'static [&'static str] {
+        match self {
+            ValueType::Symbol => &["SYMBOL", "STRING", "VARCHAR"],
+            ValueType::Integer => &[
+                "BYTE",
+                "SHORT",
+                "INT",
+                "LONG",
+                "DATE",
+                "TIMESTAMP",
+                "FLOAT",
+                "DOUBLE",
+            ],
+            ValueType::Long256 => &["LONG256"],
+            ValueType::Float => &["FLOAT", "DOUBLE"],
+            ValueType::String => &[
+                "STRING", "VARCHAR", "SYMBOL", "CHAR", "UUID", "GEOHASH", "IPV4",
+            ],
+            ValueType::Boolean => &["BOOLEAN"],
+            ValueType::Timestamp => &["TIMESTAMP", "DATE"],
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ValueType::Symbol => "symbol",
+            ValueType::Integer => "integer",
+            ValueType::Long256 => "long256",
+            ValueType::Float => "float",
+            ValueType::String => "string",
+            ValueType::Boolean => "boolean",
+            ValueType::Timestamp => "timestamp",
+        }
+    }
+}
+
+/// Table, columns and timestamp of a line
+#[derive(Debug, PartialEq)]
+struct Line {
+    table: String,
+    columns: Vec<(String, ValueType)>,
+    has_timestamp: bool,
+}
+
+/// Splits `s` on `separator` outside of escapes and double quoted strings
+fn split_unescaped(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    let mut quoted = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Splits `s` at the first `=` that isn't escaped
+fn split_key_value(s: &str) -> Option<(&str, &str)> {
+    match split_unescaped(s, '=')[..] {
+        [key, _, ..] => Some((key, &s[key.len() + 1..])),
+        _ => None,
+    }
+}
+
+/// Removes the backslashes escaping characters in names
+fn unescape(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+fn parse_line(text: &str) -> Result<Line, String> {
+    let sections: Vec<&str> = split_unescaped(text, ' ')
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect();
+    let (series, fields, has_timestamp) = match sections[..] {
+        [series, fields] => (series, fields, false),
+        [series, fields, _] => (series, fields, true),
+        _ => {
+            return Err(String::from(
+                "expected a table, columns and an optional timestamp",
+            ))
+        }
+    };
+
+    let mut series = split_unescaped(series, ',').into_iter();
+    let table = unescape(series.next().unwrap_or_default());
+    let mut columns = Vec::new();
+    for tag in series {
+        let (name, _) =
+            split_key_value(tag).ok_or_else(|| format!("symbol '{}' has no value", tag))?;
+        columns.push((unescape(name), ValueType::Symbol));
+    }
+    for field in split_unescaped(fields, ',') {
+        let (name, value) =
+            split_key_value(field).ok_or_else(|| format!("column '{}' has no value", field))?;
+        columns.push((unescape(name), ValueType::of(value)));
+    }
+
+    Ok(Line {
+        table,
+        columns,
+        has_timestamp,
+    })
+}
+
+fn check_line(line: &Line, columns: &[TableColumn]) -> Result<(), String> {
+    for (name, value_type) in &line.columns {
+        let column = columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("table '{}' has no column '{}'", line.table, name))?;
+
+        // Parameterized types such as GEOHASH(8c) are matched on their name
+        let column_type = column.column_type.split('(').next().unwrap_or_default();
+        if !value_type
+            .column_types()
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(column_type))
+        {
+            return Err(format!(
+                "column '{}' is {}, got a {} value",
+                name,
+                column.column_type,
+                value_type.name()
+            ));
+        }
+    }
+
+    if !line.has_timestamp {
+        if let Some(ts) = columns.iter().find(|c| c.designated) {
+            return Err(format!(
+                "missing value of designated timestamp '{}'",
+                ts.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_line, parse_line, ValueType};
+    use crate::schema::TableColumn;
+
+    fn column(name: &str, column_type: &str, designated: bool) -> TableColumn {
+        TableColumn {
+            name: String::from(name),
+            column_type: String::from(column_type),
+            indexed: false,
+            designated,
+        }
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let line =
+            parse_line(r#"my\ table,sensor=a\,b temp=16.4,note="x, y z",ok=t,n=3i 1000"#).unwrap();
+        assert_eq!(line.table, "my table");
+        assert!(line.has_timestamp);
+        assert_eq!(
+            line.columns,
+            [
+                (String::from("sensor"), ValueType::Symbol),
+                (String::from("temp"), ValueType::Float),
+                (String::from("note"), ValueType::String),
+                (String::from("ok"), ValueType::Boolean),
+                (String::from("n"), ValueType::Integer),
+            ]
+        );
+        assert!(parse_line("readings").is_err());
+
+        let line = parse_line(r#"readings,a\=b=x c\=d="e=f""#).unwrap();
+        assert_eq!(
+            line.columns,
+            [
+                (String::from("a=b"), ValueType::Symbol),
+                (String::from("c=d"), ValueType::String),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_line() {
+        let columns = [
+            column("sensor", "SYMBOL", false),
+            column("temp", "DOUBLE", false),
+            column("ts", "TIMESTAMP", true),
+        ];
+        let check = |text: &str| check_line(&parse_line(text).unwrap(), &columns);
+
+        assert_eq!(check("readings,sensor=a temp=16.4 1000"), Ok(()));
+        assert_eq!(
+            check("readings,sensor=a temp=16i,humidity=0.4 1000"),
+            Err(String::from("table 'readings' has no column 'humidity'"))
+        );
+        assert_eq!(
+            check("readings temp=\"hot\" 1000"),
+            Err(String::from("column 'temp' is DOUBLE, got a string value"))
+        );
+        assert_eq!(
+            check("readings temp=16.4"),
+            Err(String::from("missing value of designated timestamp 'ts'"))
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use crate::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Unit of a QuestDB duration literal, such as the `m` of `SAMPLE BY 15m`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SampleUnit {
+    Micros,
+    Millis,
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+    Months,
+    Years,
+}
+
+impl SampleUnit {
+    /// Letter of the unit in QuestDB's syntax
+    pub fn symbol(&self) -> char {
+        match self {
+            SampleUnit::Micros => 'U',
+            SampleUnit::Millis => 'T',
+            SampleUnit::Seconds => 's',
+            SampleUnit::Minutes => 'm',
+            SampleUnit::Hours => 'h',
+            SampleUnit::Days => 'd',
+            SampleUnit::Months => 'M',
+            SampleUnit::Years => 'y',
+        }
+    }
+
+    fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            'U' => Some(SampleUnit::Micros),
+            'T' => Some(SampleUnit::Millis),
+            's' => Some(SampleUnit::Seconds),
+            'm' => Some(SampleUnit::Minutes),
+            'h' => Some(SampleUnit::Hours),
+            'd' => Some(SampleUnit::Days),
+            'M' => Some(SampleUnit::Months),
+            'y' => Some(SampleUnit::Years),
+            _ => None,
+        }
+    }
+
+    /// Length of the unit, `None` for months and years whose length depends on the calendar
+    pub fn duration(&self) -> Option<Duration> {
+        let micros = match self {
+            SampleUnit::Micros => 1,
+            SampleUnit::Millis => 1_000,
+            SampleUnit::Seconds => 1_000_000,
+            SampleUnit::Minutes => 60_000_000,
+            SampleUnit::Hours => 3_600_000_000,
+            SampleUnit::Days => 86_400_000_000,
+            SampleUnit::Months | SampleUnit::Years => return None,
+        };
+        Some(Duration::from_micros(micros))
+    }
+}
+
+/// Duration in QuestDB's literal syntax, a count followed by the letter of a [`SampleUnit`],
+/// e.g. `15s`, `1m` or `1d`
+///
+/// # Example
+/// ```
+/// use questdb::{Interval, SampleUnit};
+/// use std::time::Duration;
+///
+/// let interval: Interval = "15m".parse().unwrap();
+/// assert_eq!(interval.unit(), SampleUnit::Minutes);
+/// assert_eq!(interval.to_duration(), Some(Duration::from_secs(900)));
+///
+/// let interval = Interval::try_from(Duration::from_secs(7200)).unwrap();
+/// assert_eq!(interval.to_string(), "2h");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Interval {
+    count: u32,
+    unit: SampleUnit,
+}
+
+impl Interval {
+    /// `count` times the unit
+    pub fn new(count: u32, unit: SampleUnit) -> Self {
+        Interval { count, unit }
+    }
+
+    /// `count` seconds
+    pub fn seconds(count: u32) -> Self {
+        Interval::new(count, SampleUnit::Seconds)
+    }
+
+    /// `count` minutes
+    pub fn minutes(count: u32) -> Self {
+        Interval::new(count, SampleUnit::Minutes)
+    }
+
+    /// `count` hours
+    pub fn hours(count: u32) -> Self {
+        Interval::new(count, SampleUnit::Hours)
+    }
+
+    /// `count` days
+    pub fn days(count: u32) -> Self {
+        Interval::new(count, SampleUnit::Days)
+    }
+
+    /// `count` calendar months
+    pub fn months(count: u32) -> Self {
+        Interval::new(count, SampleUnit::Months)
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn unit(&self) -> SampleUnit {
+        self.unit
+    }
+
+    /// Length of the interval, `None` for months and years
+    pub fn to_duration(&self) -> Option<Duration> {
+        Some(self.unit.duration()? * self.count)
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.count, self.unit.symbol())
+    }
+}
+
+impl FromStr for Interval {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidInterval(String::from(s));
+        let (split, symbol) = s.char_indices().last().ok_or_else(invalid)?;
+        let unit = SampleUnit::from_symbol(symbol).ok_or_else(invalid)?;
+        let count = s[..split].parse().map_err(|_| invalid())?;
+        Ok(Interval::new(count, unit))
+    }
+}
+
+impl TryFrom<Duration> for Interval {
+    type Error = Error;
+
+    /// Expresses the duration in the largest unit up to days it is a whole number of, fails for
+    /// durations that aren't a whole number of microseconds or don't fit
+    fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+        let invalid = || Error::InvalidInterval(format!("{:?}", duration));
+        if duration.is_zero() || !duration.subsec_nanos().is_multiple_of(1_000) {
+            return Err(invalid());
+        }
+
+        let micros = duration.as_micros();
+        let units = [
+            SampleUnit::Days,
+            SampleUnit::Hours,
+            SampleUnit::Minutes,
+            SampleUnit::Seconds,
+            SampleUnit::Millis,
+            SampleUnit::Micros,
+        ];
+        for unit in units {
+            let size = unit.duration().unwrap_or_default().as_micros();
+            if micros.is_multiple_of(size) {
+                if let Ok(count) = u32::try_from(micros / size) {
+                    return Ok(Interval::new(count, unit));
+                }
+            }
+        }
+        Err(invalid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Interval, SampleUnit};
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse() {
+        for s in ["1U", "250T", "15s", "1m", "4h", "1d", "3M", "1y"] {
+            assert_eq!(s.parse::<Interval>().unwrap().to_string(), s);
+        }
+        assert_eq!("1M".parse::<Interval>().unwrap().to_duration(), None);
+        assert!("".parse::<Interval>().is_err());
+        assert!("m".parse::<Interval>().is_err());
+        assert!("5x".parse::<Interval>().is_err());
+        assert!("-5m".parse::<Interval>().is_err());
+        assert!("5é".parse::<Interval>().is_err());
+        assert!("é".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn test_from_duration() {
+        let interval = |d| Interval::try_from(d).unwrap();
+        assert_eq!(interval(Duration::from_secs(90)), Interval::seconds(90));
+        assert_eq!(interval(Duration::from_secs(86_400)), Interval::days(1));
+        assert_eq!(
+            interval(Duration::from_micros(1500)),
+            Interval::new(1500, SampleUnit::Micros)
+        );
+        assert!(Interval::try_from(Duration::from_nanos(1)).is_err());
+        assert!(Interval::try_from(Duration::ZERO).is_err());
+    }
+}
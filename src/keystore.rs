@@ -0,0 +1,53 @@
+use crate::Error;
+
+/// Entry of the operating system credential store (Keychain, Credential Manager or the Secret
+/// Service) holding a QuestDB secret, a REST token or the password of basic authentication
+///
+/// The platform stores are enabled through the features of the `keyring` crate, e.g.
+/// `keyring = { version = "3", features = ["sync-secret-service"] }` on Linux.
+///
+/// # Example
+/// ```no-test
+/// use questdb::{Keyring, QuestDB};
+///
+/// // Done once, e.g. by a `login` subcommand
+/// Keyring::new("questdb", "analytics").set("my-rest-token")?;
+///
+/// let connection = QuestDB::builder("http://192.168.1.37:9000")
+///     .token_from_keyring(Keyring::new("questdb", "analytics"))
+///     .build()?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Keyring {
+    service: String,
+    account: String,
+}
+
+impl Keyring {
+    /// Refers to the secret stored for `account` under `service`
+    pub fn new(service: &str, account: &str) -> Self {
+        Keyring {
+            service: String::from(service),
+            account: String::from(account),
+        }
+    }
+
+    fn entry(&self) -> Result<keyring::Entry, Error> {
+        Ok(keyring::Entry::new(&self.service, &self.account)?)
+    }
+
+    /// Reads the secret
+    pub fn get(&self) -> Result<String, Error> {
+        Ok(self.entry()?.get_password()?)
+    }
+
+    /// Stores the secret, replacing the previous one
+    pub fn set(&self, secret: &str) -> Result<(), Error> {
+        Ok(self.entry()?.set_password(secret)?)
+    }
+
+    /// Removes the secret from the store
+    pub fn delete(&self) -> Result<(), Error> {
+        Ok(self.entry()?.delete_credential()?)
+    }
+}
@@ -2,17 +2,176 @@
 //!
 //! You can create a new connection using the QuestDB structure.
 
+// Lets the derive macros refer to this crate as `::questdb` inside it too
+extern crate self as questdb;
+
 mod api;
+mod auth;
+mod balance;
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+mod breaker;
+mod builder;
+#[cfg(not(target_arch = "wasm32"))]
+mod cancel;
+#[cfg(feature = "checked-queries")]
+mod checked;
+mod cursor;
+mod dsn;
+mod env;
 mod error;
+mod explain;
+#[cfg(not(target_arch = "wasm32"))]
+mod export;
+mod failover;
+mod health;
+mod hedge;
+mod import;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ingress;
+mod interval;
+#[cfg(feature = "keyring")]
+mod keystore;
+mod limit;
+mod metadata;
+mod metrics;
+#[cfg(feature = "oauth2")]
+mod oauth2;
+mod options;
+mod outcome;
+#[cfg(feature = "sqlx-adapter")]
+mod pgwire;
+mod platform;
+#[cfg(any(feature = "deadpool", feature = "bb8"))]
+mod pool;
+pub mod query;
+mod redact;
+mod request;
+mod retry;
+mod row;
+#[cfg(all(feature = "runtime-agnostic", not(target_arch = "wasm32")))]
+pub mod rt;
+mod schema;
+pub mod serde;
+pub mod sql;
+mod stale;
+mod stats;
+#[cfg(not(target_arch = "wasm32"))]
+mod stream;
+pub mod testing;
+mod timestamp;
 mod types;
+mod version;
 
 /// Object to connect to a questdb
 pub use api::QuestDB;
 
+/// Microsecond precision timestamp
+pub use timestamp::Timestamp;
+
+/// Duration literals such as `15m`
+pub use interval::{Interval, SampleUnit};
+
+/// Url type accepted by [`QuestDB::from`]
+pub use reqwest::Url;
+
+/// Cancellable queries
+#[cfg(not(target_arch = "wasm32"))]
+pub use cancel::QueryHandle;
+
+/// Injection-safe query templates
+#[cfg(feature = "derive")]
+pub use questdb_derive::sql;
+
+/// Queries checked at build time
+#[cfg(feature = "checked-queries")]
+pub use questdb_derive::query_as;
+
+/// Support code of the macros, not part of the public API
+#[cfg(feature = "checked-queries")]
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::checked::{column, query_as, FromColumn};
+    pub use serde_json::Value;
+}
+
+/// Builder for a configured connection
+pub use builder::QuestDBBuilder;
+
+/// Token based authentication
+pub use auth::{AccessToken, RefreshEndpoint, TokenFuture, TokenRefresher};
+
+/// OAuth2 client-credentials authentication
+#[cfg(feature = "oauth2")]
+pub use oauth2::ClientCredentials;
+
+/// System credential store integration
+#[cfg(feature = "keyring")]
+pub use keystore::Keyring;
+
+/// sqlx over the PostgreSQL wire protocol
+#[cfg(feature = "sqlx-adapter")]
+pub use pgwire::PgWire;
+
+#[cfg(all(any(feature = "deadpool", feature = "bb8"), feature = "sqlx-adapter"))]
+pub use pool::PgWireManager;
+/// Connection pool managers
+#[cfg(any(feature = "deadpool", feature = "bb8"))]
+pub use pool::QuestDBManager;
+
+/// Query results with the metadata of their columns, and rows of queries without a struct
+pub use row::{ColumnMeta, QueryResult, Row};
+
+/// Paging through large results
+pub use cursor::Cursor;
+
+/// Query plans
+pub use explain::{PlanNode, QueryPlan};
+
+/// Resumable exports
+#[cfg(not(target_arch = "wasm32"))]
+pub use export::ExportProgress;
+
+/// Outcome of an import
+pub use import::{ImportColumn, ImportOptions, ImportResult};
+
+/// Table definitions and management
+pub use schema::{ColumnSchema, PartitionBy, SchemaChange, SyncPolicy, TableColumn, TableSchema};
+
+/// Column types and import atomicity
+pub use types::{Atomicity, Schema};
+
 /// Custom error
-pub use error::Error;
+pub use error::{Error, SQLError, ServerErrorKind};
+
+/// Per-call options
+pub use options::{ExecOptions, Limit, Priority, RequestContext};
+
+/// Query builder
+pub use request::ExecRequest;
+
+/// Outcome of a statement
+pub use outcome::ExecOutcome;
+
+/// Health checks, server metrics and version
+pub use health::HealthStatus;
+pub use metrics::{MetricSample, ServerMetrics};
+pub use version::ServerVersion;
 
-use serde::Deserialize;
+/// Spreading reads over replicas
+pub use balance::LoadBalancing;
+
+/// Custom retry behavior
+pub use retry::{RetryAttempt, RetryPolicy};
+
+/// Execution statistics and monitoring of slow operations
+pub use stats::{QueryStats, QueryTimings, SlowQuery};
+
+/// Clock of the browser, in place of `std::time::Instant` which isn't available there
+#[cfg(target_arch = "wasm32")]
+pub use platform::wasm::Instant;
+
+use ::serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
 #[allow(dead_code)]
@@ -30,7 +189,685 @@ mod tests {
     use crate::TestData;
     use std::fs::File;
 
+    #[test]
+    fn test_shareable() {
+        fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+        assert_shareable::<QuestDB>();
+    }
+
+    #[test]
+    fn test_try_from_url() {
+        let connection = QuestDB::try_from("http://192.168.1.37:9000/").unwrap();
+        assert_eq!(connection.to_string(), "QuestDB(http://192.168.1.37:9000)");
+        assert!(matches!(
+            QuestDB::try_from("192.168.1.37:9000"),
+            Err(crate::Error::InvalidUrl(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        // A server that accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::new(&format!("http://{}", listener.local_addr().unwrap()));
+
+        let res = connection
+            .query("select * from readings")
+            .timeout(std::time::Duration::from_millis(50))
+            .fetch::<TestData>()
+            .await;
+        assert!(matches!(res, Err(crate::Error::Timeout(_))));
+    }
+
+    /// Answers a single request with an empty result, returns its request line and headers
+    async fn serve_once(listener: tokio::net::TcpListener) -> String {
+        let body = r#"{"query":"","columns":[],"dataset":[],"count":0}"#;
+        serve_once_with(listener, "200 OK", body).await
+    }
+
+    /// Answers one request with the status and the body, returns the head of the request
+    async fn serve_once_with(
+        listener: tokio::net::TcpListener,
+        status: &str,
+        body: &str,
+    ) -> String {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            stream.read_line(&mut head).await.unwrap();
+        }
+
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        head
+    }
+
+    #[tokio::test]
+    async fn test_post() {
+        for (query, post, method) in [
+            ("select 1", None, "GET"),
+            ("select 1", Some(true), "POST"),
+            (
+                &*format!("select 1 from x where id in ({})", "1,".repeat(5000) + "1"),
+                None,
+                "POST",
+            ),
+        ] {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let connection = QuestDB::new(&format!("http://{}", listener.local_addr().unwrap()));
+            let server = tokio::spawn(serve_once(listener));
+
+            let request = match post {
+                Some(post) => connection.query(query).post(post),
+                None => connection.query(query),
+            };
+            request.fetch::<(i32,)>().await.unwrap();
+            assert!(server.await.unwrap().starts_with(method));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builder_headers() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder(&format!("http://{}", listener.local_addr().unwrap()))
+            .default_header("X-Tenant-Id", "acme")
+            .user_agent("ingest-service/1.2")
+            .build()
+            .unwrap();
+        let server = tokio::spawn(serve_once(listener));
+
+        connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        let head = server.await.unwrap().to_ascii_lowercase();
+        assert!(head.contains("x-tenant-id: acme\r\n"));
+        assert!(head.contains("user-agent: ingest-service/1.2\r\n"));
+
+        // Headers of a single call replace the default ones
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder(&format!("http://{}", listener.local_addr().unwrap()))
+            .default_header("X-Tenant-Id", "acme")
+            .build()
+            .unwrap();
+        let server = tokio::spawn(serve_once(listener));
+
+        connection
+            .query("select 1")
+            .header("X-Tenant-Id", "globex")
+            .header("X-Api-Key", "k1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        let head = server.await.unwrap().to_ascii_lowercase();
+        assert!(head.contains("x-tenant-id: globex\r\n"));
+        assert!(!head.contains("acme"));
+        assert!(head.contains("x-api-key: k1\r\n"));
+
+        let res = QuestDB::builder("http://127.0.0.1:9000")
+            .default_header("X-Tenant Id", "acme")
+            .build();
+        assert!(matches!(res, Err(crate::Error::InvalidHeader(_))));
+    }
+
+    #[tokio::test]
+    async fn test_context() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::new(&format!("http://{}", listener.local_addr().unwrap()));
+        let server = tokio::spawn(serve_once(listener));
+
+        let context = crate::RequestContext::new()
+            .header("X-Tenant-Id", "acme")
+            .bearer_token("tenant-token");
+        let options = crate::ExecOptions::new().context(context);
+        connection
+            .exec_with::<(i32,)>("select 1", &options)
+            .await
+            .unwrap();
+        let head = server.await.unwrap().to_ascii_lowercase();
+        assert!(head.contains("x-tenant-id: acme\r\n"));
+        assert!(head.contains("authorization: bearer tenant-token\r\n"));
+    }
+
     #[tokio::test]
+    async fn test_read_and_total_timeout() {
+        use std::time::Duration;
+
+        // Accepts connections but never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let _server = tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        let connection = QuestDB::builder(&url)
+            .read_timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+        let res = connection.query("select 1").fetch::<(i32,)>().await;
+        assert!(
+            matches!(res, Err(crate::Error::ReadTimeout(_))),
+            "{:?}",
+            res
+        );
+        let res = connection.exp("select 1", None, &mut Vec::new()).await;
+        assert!(
+            matches!(res, Err(crate::Error::ReadTimeout(_))),
+            "{:?}",
+            res
+        );
+
+        // Retries don't extend the total timeout
+        let connection = QuestDB::builder(&url)
+            .request_timeout(Duration::from_millis(20))
+            .retry_timeout(Duration::from_secs(10))
+            .total_timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let res = connection.exp("select 1", None, &mut Vec::new()).await;
+        assert!(matches!(res, Err(crate::Error::Timeout(t)) if t == Duration::from_millis(100)));
+        let res = connection.query("select 1").fetch::<(i32,)>().await;
+        assert!(matches!(res, Err(crate::Error::Timeout(_))), "{:?}", res);
+    }
+
+    #[tokio::test]
+    async fn test_retry_attempts() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Fails every request with 503
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.read(&mut [0; 4096]).await;
+                let response = "HTTP/1.1 503 Service Unavailable\r\n\
+                                Content-Length: 0\r\nConnection: close\r\n\r\n";
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let connection = QuestDB::builder(&url)
+            .max_attempts(3)
+            .retry_backoff(Duration::from_millis(1), Duration::from_millis(5))
+            .retry_jitter(Duration::ZERO)
+            .build()
+            .unwrap();
+        let res = connection.query("select 1").fetch::<(i32,)>().await;
+        assert!(res.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        // Statements that may write are sent once
+        requests.store(0, Ordering::SeqCst);
+        assert!(connection
+            .execute("insert into t values (1)")
+            .await
+            .is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // A policy replaces the attempts
+        requests.store(0, Ordering::SeqCst);
+        let connection = QuestDB::builder(&url)
+            .max_attempts(3)
+            .retry_policy(|attempt: &crate::RetryAttempt| {
+                assert_eq!(attempt.status, Some(503));
+                (attempt.attempt < 4).then_some(Duration::ZERO)
+            })
+            .build()
+            .unwrap();
+        assert!(connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        use std::time::Duration;
+
+        // Nothing listens on the primary at first
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let primary = listener.local_addr().unwrap();
+        drop(listener);
+        let fallback = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder(&format!("http://{}", primary))
+            .failover(&format!("http://{}", fallback.local_addr().unwrap()))
+            .failover_probe_interval(Duration::ZERO)
+            .build()
+            .unwrap();
+
+        let server = tokio::spawn(serve_once(fallback));
+        connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        let fallback = server.await.unwrap();
+        assert_eq!(connection.inner.failover.current(), 1);
+        assert!(fallback.contains("select"));
+
+        // The primary is probed once it is up again, and requests return to it
+        let listener = tokio::net::TcpListener::bind(primary).await.unwrap();
+        let probe = tokio::spawn(serve_once(listener));
+        connection.probe_primary();
+        assert!(probe.await.unwrap().contains("select%201"));
+        for _ in 0..100 {
+            if connection.inner.failover.current() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(connection.inner.failover.current(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_load_balanced_reads() {
+        let primary = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let replica = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder(&format!("http://{}", primary.local_addr().unwrap()))
+            .replica(&format!("http://{}", replica.local_addr().unwrap()))
+            .load_balance_reads(crate::LoadBalancing::RoundRobin)
+            .build()
+            .unwrap();
+
+        let server = tokio::spawn(serve_once(replica));
+        connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        assert!(server.await.unwrap().contains("select"));
+
+        let server = tokio::spawn(serve_once(primary));
+        connection
+            .execute("insert into t values (1)")
+            .await
+            .unwrap();
+        assert!(server.await.unwrap().contains("insert"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_query() {
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        let slow = Arc::new(Mutex::new(Vec::new()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let reported = slow.clone();
+        let connection = QuestDB::builder(&url)
+            .slow_query(Duration::ZERO, move |q| {
+                reported.lock().unwrap().push(q.clone())
+            })
+            .build()
+            .unwrap();
+
+        let body =
+            r#"{"query":"","columns":[{"name":"x","type":"INT"}],"dataset":[[1]],"count":1}"#;
+        let server = tokio::spawn(serve_once_with(listener, "200 OK", body));
+        let (rows, stats) = connection
+            .exec_with_stats::<(i32,)>("select 1 x", &crate::ExecOptions::new())
+            .await
+            .unwrap();
+        let head = server.await.unwrap();
+        assert_eq!(rows, [(1,)]);
+        assert_eq!(stats.attempts, 1);
+        assert_eq!(stats.response_bytes, body.len() as u64);
+        assert!(stats.request_bytes >= "query=select%201%20x".len() as u64);
+        assert!(stats.total_duration > Duration::ZERO);
+        assert!(head.contains("select%201%20x"));
+
+        let slow = slow.lock().unwrap().clone();
+        assert_eq!(slow.len(), 1);
+        assert_eq!(slow[0].endpoint, "/exec");
+        assert_eq!(slow[0].query, "select 1 x");
+        assert_eq!(slow[0].stats.response_bytes, stats.response_bytes);
+
+        // Faster queries are not reported
+        let fired = Arc::new(Mutex::new(false));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let reported = fired.clone();
+        let connection = QuestDB::builder(&url)
+            .slow_query(Duration::from_secs(60), move |_| {
+                *reported.lock().unwrap() = true
+            })
+            .build()
+            .unwrap();
+        let server = tokio::spawn(serve_once(listener));
+        connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        server.await.unwrap();
+        assert!(!*fired.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_hedged_reads() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        // Answers every request after `delay`, counting the connections
+        async fn serve_slowly(
+            listener: tokio::net::TcpListener,
+            delay: Duration,
+            connections: Arc<AtomicUsize>,
+        ) {
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    let mut head = String::new();
+                    while !head.ends_with("\r\n\r\n") {
+                        stream.read_line(&mut head).await.unwrap();
+                    }
+                    tokio::time::sleep(delay).await;
+                    let body = r#"{"query":"","columns":[],"dataset":[],"count":0}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        }
+
+        // A slow server is hedged against the fast replica
+        let slow = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let slow_url = format!("http://{}", slow.local_addr().unwrap());
+        let fast = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fast_url = format!("http://{}", fast.local_addr().unwrap());
+        let slow_connections = Arc::new(AtomicUsize::new(0));
+        let slow_server = tokio::spawn(serve_slowly(
+            slow,
+            Duration::from_secs(5),
+            slow_connections.clone(),
+        ));
+        let connection = QuestDB::builder(&slow_url)
+            .replica(&fast_url)
+            .hedge_after(Duration::from_millis(50))
+            .build()
+            .unwrap();
+
+        let server = tokio::spawn(serve_once(fast));
+        tokio::time::timeout(
+            Duration::from_secs(2),
+            connection.query("select 1").fetch::<(i32,)>(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(server.await.unwrap().contains("select"));
+        slow_server.abort();
+
+        // Reads balanced over a single replica have no other replica to hedge against
+        let primary = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let replica = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let replica_url = format!("http://{}", replica.local_addr().unwrap());
+        let replica_connections = Arc::new(AtomicUsize::new(0));
+        let replica_server = tokio::spawn(serve_slowly(
+            replica,
+            Duration::from_millis(200),
+            replica_connections.clone(),
+        ));
+        let connection = QuestDB::builder(&format!("http://{}", primary.local_addr().unwrap()))
+            .replica(&replica_url)
+            .hedge_after(Duration::from_millis(20))
+            .load_balance_reads(crate::LoadBalancing::RoundRobin)
+            .build()
+            .unwrap();
+
+        connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        assert_eq!(replica_connections.load(Ordering::SeqCst), 1);
+        replica_server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_connection_reuse() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        // Answers any number of requests on every connection
+        async fn serve(listener: tokio::net::TcpListener, connections: Arc<AtomicUsize>) {
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    let body = r#"{"query":"","columns":[],"dataset":[],"count":0}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let mut line = String::new();
+                    while stream.read_line(&mut line).await.unwrap_or(0) > 0 {
+                        if line == "\r\n" {
+                            stream.write_all(response.as_bytes()).await.unwrap();
+                        }
+                        line.clear();
+                    }
+                });
+            }
+        }
+
+        for (max_idle, expected) in [(None, 1), (Some(0), 2)] {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let mut builder =
+                QuestDB::builder(&format!("http://{}", listener.local_addr().unwrap()));
+            if let Some(max_idle) = max_idle {
+                builder = builder.pool_max_idle_per_host(max_idle);
+            }
+            let connection = builder.build().unwrap();
+            let connections = Arc::new(AtomicUsize::new(0));
+            tokio::spawn(serve(listener, connections.clone()));
+
+            for _ in 0..2 {
+                connection
+                    .query("select 1")
+                    .fetch::<(i32,)>()
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(connections.load(Ordering::SeqCst), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_status() {
+        for (status, body, healthy) in [
+            ("200 OK", "Status: Healthy", true),
+            ("500 Internal Server Error", "Status: Unhealthy", false),
+        ] {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let connection = QuestDB::new(&url);
+            let server = tokio::spawn(serve_once_with(listener, status, body));
+
+            let res = connection.status().await.unwrap();
+            assert!(server.await.unwrap().starts_with("GET /status "));
+            assert_eq!(res.healthy, healthy);
+            assert_eq!(res.message, body);
+        }
+
+        // /status is on the authenticated REST port
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder(&format!("http://{}", listener.local_addr().unwrap()))
+            .basic_auth("admin", "quest")
+            .build()
+            .unwrap();
+        let server = tokio::spawn(serve_once_with(listener, "200 OK", "Status: Healthy"));
+        assert!(connection.is_healthy().await);
+        assert!(server
+            .await
+            .unwrap()
+            .contains("authorization: Basic YWRtaW46cXVlc3Q=\r\n"));
+
+        // Nothing listens anymore
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::new(&format!("http://{}", listener.local_addr().unwrap()));
+        drop(listener);
+        assert!(connection.status().await.is_err());
+        assert!(!connection.is_healthy().await);
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder(&format!("http://{}", listener.local_addr().unwrap()))
+            .token("secret")
+            .build()
+            .unwrap();
+        let body = "# TYPE questdb_memory_rss gauge\nquestdb_memory_rss 1024\n";
+        let server = tokio::spawn(serve_once_with(listener, "200 OK", body));
+
+        let metrics = connection.metrics().await.unwrap();
+        assert_eq!(metrics.memory_rss, Some(1024.0));
+        let head = server.await.unwrap();
+        assert!(head.starts_with("GET /metrics "));
+        assert!(head.contains("authorization: Bearer secret\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_user_agent() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::new(&format!("http://{}", listener.local_addr().unwrap()));
+        let server = tokio::spawn(serve_once(listener));
+
+        connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        let head = server.await.unwrap();
+        let expected = format!("user-agent: questdb-rs/{}\r\n", env!("CARGO_PKG_VERSION"));
+        assert!(head.contains(&expected), "{}", head);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder(&format!("http://{}", listener.local_addr().unwrap()))
+            .app_name("ingest-service/1.2")
+            .build()
+            .unwrap();
+        let server = tokio::spawn(serve_once(listener));
+
+        connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        let head = server.await.unwrap();
+        let expected = format!(
+            "user-agent: questdb-rs/{} ingest-service/1.2\r\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        assert!(head.contains(&expected), "{}", head);
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder(&format!("http://{}", listener.local_addr().unwrap()))
+            .basic_auth("admin", "quest")
+            .build()
+            .unwrap();
+        let server = tokio::spawn(serve_once(listener));
+
+        connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        let head = server.await.unwrap().to_ascii_lowercase();
+        // base64 of admin:quest
+        assert!(head.contains("authorization: basic ywrtaw46cxvlc3q=\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_token_override() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder(&format!("http://{}", listener.local_addr().unwrap()))
+            .token("service-token")
+            .build()
+            .unwrap();
+        let server = tokio::spawn(serve_once(listener));
+
+        connection
+            .query("select 1")
+            .token("tenant-token")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        let head = server.await.unwrap().to_ascii_lowercase();
+        assert!(head.contains("authorization: bearer tenant-token\r\n"));
+        assert!(!head.contains("service-token"));
+    }
+
+    #[tokio::test]
+    async fn test_proxy() {
+        let proxy = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder("http://questdb.invalid:9000")
+            .proxy(&format!("http://{}", proxy.local_addr().unwrap()))
+            .proxy_auth("admin", "quest")
+            .build()
+            .unwrap();
+        let server = tokio::spawn(serve_once(proxy));
+
+        connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        let head = server.await.unwrap().to_ascii_lowercase();
+        assert!(head.starts_with("get http://questdb.invalid:9000/exec?"));
+        assert!(head.contains("proxy-authorization: basic ywrtaw46cxvlc3q=\r\n"));
+
+        // Hosts on the no proxy list are reached directly
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connection = QuestDB::builder(&format!("http://{}", listener.local_addr().unwrap()))
+            .proxy("http://127.0.0.1:9")
+            .no_proxy("127.0.0.1")
+            .build()
+            .unwrap();
+        let server = tokio::spawn(serve_once(listener));
+
+        connection
+            .query("select 1")
+            .fetch::<(i32,)>()
+            .await
+            .unwrap();
+        assert!(server.await.unwrap().starts_with("GET /exec?"));
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
     async fn test_exec() {
         let connection = QuestDB::new("http://192.168.1.37:9000");
         let _res = match connection
@@ -48,7 +885,7 @@ mod tests {
     #[tokio::test]
     async fn test_imp() {
         let connection = QuestDB::new("http://192.168.1.37:9000");
-        let _res = match connection
+        match connection
             .imp(
                 "./links.csv",
                 "nu_table",
@@ -58,7 +895,7 @@ mod tests {
             )
             .await
         {
-            Ok(res) => res,
+            Ok(_) => {}
             Err(e) => {
                 println!("{}", e);
                 return;
@@ -71,11 +908,11 @@ mod tests {
         let connection = QuestDB::new("http://192.168.1.37:9000");
 
         let mut output_file = File::create("output.csv").unwrap();
-        let _res = match connection
+        match connection
             .exp("select * from nu_table", Some(5), &mut output_file)
             .await
         {
-            Ok(res) => res,
+            Ok(_) => {}
             Err(e) => {
                 println!("{}", e);
                 return;
@@ -0,0 +1,94 @@
+use crate::options::Priority;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Limits on the number of requests in flight for each priority class and in total
+#[derive(Debug, Default)]
+pub(crate) struct Limits {
+    interactive: Option<Semaphore>,
+    batch: Option<Semaphore>,
+    total: Option<Semaphore>,
+}
+
+/// Place of a request within the limits, released when dropped
+#[derive(Debug)]
+pub(crate) struct Permit<'a> {
+    _class: Option<SemaphorePermit<'a>>,
+    _total: Option<SemaphorePermit<'a>>,
+}
+
+impl Limits {
+    /// Limits of 0 are raised to 1, no request could ever be sent otherwise
+    pub(crate) fn set(&mut self, priority: Priority, max_in_flight: usize) {
+        let semaphore = Some(Semaphore::new(max_in_flight.max(1)));
+        match priority {
+            Priority::Interactive => self.interactive = semaphore,
+            Priority::Batch => self.batch = semaphore,
+        }
+    }
+
+    pub(crate) fn set_total(&mut self, max_in_flight: usize) {
+        self.total = Some(Semaphore::new(max_in_flight.max(1)));
+    }
+
+    /// Waits until a request of the given priority may be sent. The request stays in flight
+    /// until the returned permit is dropped.
+    pub(crate) async fn acquire(&self, priority: Priority) -> Permit<'_> {
+        let class = match priority {
+            Priority::Interactive => self.interactive.as_ref(),
+            Priority::Batch => self.batch.as_ref(),
+        };
+        // The semaphores are never closed. The class comes first so that requests waiting for
+        // their class don't hold places of the total limit.
+        let class = match class {
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        };
+        let total = match &self.total {
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        };
+        Permit {
+            _class: class,
+            _total: total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Limits;
+    use crate::Priority;
+    use futures_util::FutureExt;
+
+    #[tokio::test]
+    async fn test_total_limit() {
+        let mut limits = Limits::default();
+        limits.set_total(1);
+
+        let permit = limits.acquire(Priority::Interactive).await;
+        let waiting = limits.acquire(Priority::Batch);
+        tokio::pin!(waiting);
+        assert!(waiting.as_mut().now_or_never().is_none());
+        drop(permit);
+        waiting.await;
+    }
+
+    #[tokio::test]
+    async fn test_class_limit() {
+        let mut limits = Limits::default();
+        limits.set(Priority::Batch, 0);
+
+        // A limit of 0 lets one request through
+        let permit = limits.acquire(Priority::Batch).await;
+        let waiting = limits.acquire(Priority::Batch);
+        tokio::pin!(waiting);
+        assert!(waiting.as_mut().now_or_never().is_none());
+
+        // The full batch class doesn't hold back interactive requests
+        let interactive = limits.acquire(Priority::Interactive).now_or_never();
+        assert!(interactive.is_some());
+
+        drop(permit);
+        waiting.await;
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::ServerErrorKind;
+use crate::schema::TableColumn;
+use crate::sql::{self, StatementKind};
+use crate::{Error, QuestDB};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Metadata of tables shared by the features that need it without asking the server every time
+///
+/// The cache is invalidated by the connection itself: statements that change the schema clear
+/// it, and so do errors showing the cached view is out of date. Changes made by other clients
+/// are only noticed through those errors, [`QuestDB::clear_schema_cache`] forgets everything.
+#[derive(Debug, Default)]
+pub(crate) struct Metadata {
+    /// Columns of the tables by lowercase name, `None` for tables that don't exist yet
+    tables: Mutex<HashMap<String, Option<Arc<Vec<TableColumn>>>>>,
+}
+
+impl Metadata {
+    /// Columns of the table, fetched from the server the first time the table is seen
+    pub(crate) async fn columns(
+        &self,
+        connection: &QuestDB,
+        table: &str,
+    ) -> Result<Option<Arc<Vec<TableColumn>>>, Error> {
+        let key = table.to_ascii_lowercase();
+        if let Some(columns) = self.tables.lock().unwrap().get(&key) {
+            return Ok(columns.clone());
+        }
+
+        let columns = match connection.table_exists(table).await? {
+            true => Some(Arc::new(connection.table_columns(table).await?)),
+            false => None,
+        };
+        self.tables.lock().unwrap().insert(key, columns.clone());
+        Ok(columns)
+    }
+
+    /// Fills the cache as if the server reported `columns` for the table
+    #[cfg(test)]
+    pub(crate) fn insert(&self, table: &str, columns: Option<Vec<TableColumn>>) {
+        self.tables
+            .lock()
+            .unwrap()
+            .insert(table.to_ascii_lowercase(), columns.map(Arc::new));
+    }
+
+    pub(crate) fn clear(&self) {
+        self.tables.lock().unwrap().clear();
+    }
+
+    pub(crate) fn invalidate(&self, table: &str) {
+        self.tables
+            .lock()
+            .unwrap()
+            .remove(&table.to_ascii_lowercase());
+    }
+
+    /// Called after a statement succeeded
+    pub(crate) fn executed(&self, query: &str) {
+        let changes_schema = matches!(
+            sql::classify(query),
+            StatementKind::Create
+                | StatementKind::Alter
+                | StatementKind::Drop
+                | StatementKind::Rename
+        );
+        if changes_schema {
+            self.clear();
+        }
+    }
+
+    /// Called after rows were written, which creates the tables they were written to
+    pub(crate) fn written(&self) {
+        self.tables
+            .lock()
+            .unwrap()
+            .retain(|_, columns| columns.is_some());
+    }
+
+    /// Called with the errors of the server, clears the cache when they hint that a table
+    /// changed
+    pub(crate) fn failed(&self, error: &Error) {
+        let stale = match error {
+            Error::SQLError(e) => matches!(
+                e.kind(),
+                ServerErrorKind::TableNotFound
+                    | ServerErrorKind::TableExists
+                    | ServerErrorKind::ColumnNotFound
+                    | ServerErrorKind::TypeMismatch
+            ),
+            Error::SchemaMismatch { .. } | Error::WriteRejected { .. } => true,
+            _ => false,
+        };
+        if stale {
+            self.clear();
+        }
+    }
+}
+
+impl QuestDB {
+    /// Forgets the cached metadata of all tables, for when tables were altered by another
+    /// client. Changes made through this connection are picked up without it.
+    pub fn clear_schema_cache(&self) {
+        self.inner.metadata.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metadata;
+    use crate::schema::TableColumn;
+    use crate::Error;
+    use std::sync::Arc;
+
+    fn cached(metadata: &Metadata) -> Vec<String> {
+        let mut tables: Vec<String> = metadata.tables.lock().unwrap().keys().cloned().collect();
+        tables.sort();
+        tables
+    }
+
+    #[test]
+    fn test_invalidation() {
+        let metadata = Metadata::default();
+        let fill = || {
+            let mut tables = metadata.tables.lock().unwrap();
+            tables.insert(
+                String::from("readings"),
+                Some(Arc::new(Vec::<TableColumn>::new())),
+            );
+            tables.insert(String::from("trades"), None);
+        };
+
+        fill();
+        metadata.executed("select * from readings");
+        metadata.failed(&Error::InvalidLine(String::new()));
+        assert_eq!(cached(&metadata), ["readings", "trades"]);
+
+        metadata.written();
+        assert_eq!(cached(&metadata), ["readings"]);
+
+        fill();
+        metadata.invalidate("Readings");
+        assert_eq!(cached(&metadata), ["trades"]);
+
+        fill();
+        metadata.executed("alter table readings add column humidity double");
+        assert!(cached(&metadata).is_empty());
+
+        fill();
+        metadata.failed(&Error::SchemaMismatch {
+            table: String::from("readings"),
+            differences: vec![],
+        });
+        assert!(cached(&metadata).is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use crate::api::error_for_status;
+use crate::platform::Instant;
+use crate::{Error, QuestDB};
+
+/// Sample of the Prometheus metrics of the server
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricSample {
+    /// Name of the metric, e.g. `questdb_memory_mem_used`
+    pub name: String,
+    /// Labels telling samples of the same metric apart
+    pub labels: Vec<(String, String)>,
+    pub value: f64,
+}
+
+/// Metrics of the server, see [`QuestDB::metrics`]
+///
+/// The fields hold the metrics most monitoring needs, `None` when the server doesn't report
+/// them. Everything else is available through [`value`](Self::value) and
+/// [`samples`](Self::samples).
+#[derive(Clone, Debug)]
+pub struct ServerMetrics {
+    /// Rows written to WAL tables, `questdb_wal_written_rows_total`
+    pub wal_written_rows: Option<f64>,
+    /// Transactions committed to WAL tables but not applied to them yet, the difference of
+    /// `questdb_wal_apply_seq_txn_total` and `questdb_wal_apply_writer_txn_total`
+    pub wal_lag: Option<f64>,
+    /// Bytes of memory in use, `questdb_memory_mem_used`
+    pub memory_used: Option<f64>,
+    /// Resident set size of the process in bytes, `questdb_memory_rss`
+    pub memory_rss: Option<f64>,
+    /// Queries received over HTTP, `questdb_json_queries_total`
+    pub http_queries: Option<f64>,
+    /// Queries received over PostgreSQL wire, `questdb_pg_wire_queries_total`
+    pub pg_wire_queries: Option<f64>,
+    /// When the metrics were fetched
+    pub scraped_at: Instant,
+    samples: Vec<MetricSample>,
+}
+
+impl ServerMetrics {
+    /// Parses metrics in the Prometheus text format, lines it doesn't understand are skipped
+    pub fn parse(text: &str) -> Self {
+        let samples: Vec<MetricSample> = text.lines().filter_map(parse_sample).collect();
+        let mut metrics = ServerMetrics {
+            wal_written_rows: None,
+            wal_lag: None,
+            memory_used: None,
+            memory_rss: None,
+            http_queries: None,
+            pg_wire_queries: None,
+            scraped_at: Instant::now(),
+            samples,
+        };
+        metrics.wal_written_rows = metrics.value("questdb_wal_written_rows_total");
+        metrics.wal_lag = metrics
+            .value("questdb_wal_apply_seq_txn_total")
+            .zip(metrics.value("questdb_wal_apply_writer_txn_total"))
+            .map(|(seq, writer)| (seq - writer).max(0.0));
+        metrics.memory_used = metrics.value("questdb_memory_mem_used");
+        metrics.memory_rss = metrics.value("questdb_memory_rss");
+        metrics.http_queries = metrics.value("questdb_json_queries_total");
+        metrics.pg_wire_queries = metrics.value("questdb_pg_wire_queries_total");
+        metrics
+    }
+
+    /// Sum of the samples of the metric over all labels, `None` when the server doesn't report it
+    pub fn value(&self, name: &str) -> Option<f64> {
+        self.samples
+            .iter()
+            .filter(|s| s.name == name)
+            .map(|s| s.value)
+            .reduce(|a, b| a + b)
+    }
+
+    /// All samples, in the order the server reported them
+    pub fn samples(&self) -> &[MetricSample] {
+        &self.samples
+    }
+
+    /// Rows written to WAL tables per second since the `earlier` metrics were fetched
+    ///
+    /// # Example
+    /// ```no-test
+    /// let before = connection.metrics().await?;
+    /// tokio::time::sleep(Duration::from_secs(10)).await;
+    /// let after = connection.metrics().await?;
+    /// println!("{:?} rows/s", after.rows_per_second(&before));
+    /// ```
+    pub fn rows_per_second(&self, earlier: &ServerMetrics) -> Option<f64> {
+        let elapsed = self.scraped_at.checked_duration_since(earlier.scraped_at)?;
+        let rows = self.wal_written_rows? - earlier.wal_written_rows?;
+        (!elapsed.is_zero()).then(|| rows / elapsed.as_secs_f64())
+    }
+}
+
+impl QuestDB {
+    /// Fetches the metrics the server exposes for Prometheus, for monitoring without running
+    /// Prometheus. QuestDB only exposes them when `metrics.enabled` is set.
+    ///
+    /// The endpoint is `/metrics` of the connection's url unless
+    /// [`metrics_url`](crate::QuestDBBuilder::metrics_url) points elsewhere, e.g. to the minimal
+    /// HTTP server QuestDB runs on port 9003. The credentials of the connection are only sent to
+    /// `/metrics`, the minimal server doesn't authenticate.
+    ///
+    /// # Example
+    /// ```no-test
+    /// let metrics = connection.metrics().await?;
+    /// if metrics.wal_lag.unwrap_or_default() > 1000.0 {
+    ///     eprintln!("WAL apply is falling behind");
+    /// }
+    /// ```
+    pub async fn metrics(&self) -> Result<ServerMetrics, Error> {
+        let retry = &self.inner.retry;
+        let res = match &self.inner.metrics_url {
+            Some(url) => retry
+                .read(self.inner.client.get(url).send())
+                .await?
+                .map_err(|e| retry.attempt_error(e, None))?,
+            None => {
+                self.get_authorized(&format!("{}/metrics", self.inner.url))
+                    .await?
+            }
+        };
+        let body = retry.body(error_for_status(res).await?).await?;
+        Ok(ServerMetrics::parse(&String::from_utf8_lossy(&body)))
+    }
+}
+
+/// Parses a line such as `questdb_memory_mem_used{tag="NATIVE"} 1024`, `None` for comments and
+/// lines that aren't a sample
+fn parse_sample(line: &str) -> Option<MetricSample> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (name, labels, rest) = match line.find(['{', ' ']) {
+        Some(i) if line[i..].starts_with('{') => {
+            let (labels, rest) = parse_labels(&line[i + 1..])?;
+            (&line[..i], labels, rest)
+        }
+        Some(i) => (&line[..i], Vec::new(), &line[i..]),
+        None => return None,
+    };
+    // The value may be followed by a timestamp
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+    Some(MetricSample {
+        name: String::from(name),
+        labels,
+        value,
+    })
+}
+
+/// Parses the labels following the opening brace, returns them and what follows the closing one
+fn parse_labels(mut s: &str) -> Option<(Vec<(String, String)>, &str)> {
+    let mut labels = Vec::new();
+    loop {
+        s = s.trim_start_matches([' ', ',']);
+        if let Some(rest) = s.strip_prefix('}') {
+            return Some((labels, rest));
+        }
+        let (name, rest) = s.split_once("=\"")?;
+
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        labels.push((String::from(name.trim()), value));
+        s = &rest[end + 1..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_sample, ServerMetrics};
+
+    #[test]
+    fn test_parse_sample() {
+        let sample =
+            parse_sample(r#"questdb_memory_tag_bytes{tag="a,\"b\"", x="1"} 12.5 1700000000"#)
+                .unwrap();
+        assert_eq!(sample.name, "questdb_memory_tag_bytes");
+        assert_eq!(
+            sample.labels,
+            [
+                (String::from("tag"), String::from("a,\"b\"")),
+                (String::from("x"), String::from("1"))
+            ]
+        );
+        assert_eq!(sample.value, 12.5);
+        assert_eq!(parse_sample("questdb_memory_rss 100").unwrap().value, 100.0);
+        assert!(parse_sample("# TYPE questdb_memory_rss gauge").is_none());
+        assert!(parse_sample("questdb_memory_rss{tag=\"x\" 1").is_none());
+    }
+
+    #[test]
+    fn test_server_metrics() {
+        let metrics = ServerMetrics::parse(
+            "\
+# TYPE questdb_wal_written_rows_total counter
+questdb_wal_written_rows_total 5000
+questdb_wal_apply_seq_txn_total 120
+questdb_wal_apply_writer_txn_total 100
+questdb_memory_mem_used 2048
+questdb_json_queries_total{type=\"select\"} 3
+questdb_json_queries_total{type=\"insert\"} 4
+",
+        );
+        assert_eq!(metrics.wal_written_rows, Some(5000.0));
+        assert_eq!(metrics.wal_lag, Some(20.0));
+        assert_eq!(metrics.memory_used, Some(2048.0));
+        assert_eq!(metrics.memory_rss, None);
+        assert_eq!(metrics.http_queries, Some(7.0));
+        assert_eq!(metrics.samples().len(), 6);
+
+        let mut later = ServerMetrics::parse("questdb_wal_written_rows_total 7000");
+        later.scraped_at = metrics.scraped_at + std::time::Duration::from_secs(2);
+        assert_eq!(later.rows_per_second(&metrics), Some(1000.0));
+    }
+}
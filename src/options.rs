@@ -0,0 +1,261 @@
+use crate::redact::{self, Redacted};
+use std::fmt;
+use std::time::Duration;
+
+/// Options for a single call to [`QuestDB::exec_with`](crate::QuestDB::exec_with)
+///
+/// # Example
+/// ```
+/// use questdb::{ExecOptions, RequestContext};
+///
+/// let options = ExecOptions::new()
+///     .limit(20)
+///     .context(RequestContext::new().header("X-Tenant-Id", "acme"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ExecOptions {
+    pub(crate) limit: Option<Limit>,
+    pub(crate) count: Option<bool>,
+    pub(crate) nm: Option<bool>,
+    pub(crate) timings: Option<bool>,
+    pub(crate) quote_large_num: Option<bool>,
+    pub(crate) statement_timeout: Option<Duration>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) post: Option<bool>,
+    pub(crate) context: Option<RequestContext>,
+    pub(crate) priority: Priority,
+}
+
+impl ExecOptions {
+    /// Creates an empty set of options, the server defaults are used for everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only return some of the rows, either the first `n` rows or a range, see [`Limit`]
+    ///
+    /// # Example
+    /// ```
+    /// use questdb::{ExecOptions, Limit};
+    ///
+    /// let first = ExecOptions::new().limit(20);
+    /// let third_page = ExecOptions::new().limit(Limit::Range(40, 60));
+    /// ```
+    pub fn limit(mut self, limit: impl Into<Limit>) -> Self {
+        self.limit = Some(limit.into());
+        self
+    }
+
+    /// Instructs /exec to count rows and return this value in message header
+    pub fn count(mut self, count: bool) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Skips the metadata section of the response when true
+    pub fn nm(mut self, nm: bool) -> Self {
+        self.nm = Some(nm);
+        self
+    }
+
+    /// Asks the server to report how long compiling, executing and counting took, see
+    /// [`QueryStats::server_timings`](crate::QueryStats::server_timings)
+    pub fn timings(mut self, timings: bool) -> Self {
+        self.timings = Some(timings);
+        self
+    }
+
+    /// Returns LONG values as strings when true, JavaScript clients lose precision on numbers
+    /// beyond 2^53. Rows read with their metadata turn them back into integers, see
+    /// [`serde::long`](crate::serde::long) for results without metadata.
+    pub fn quote_large_num(mut self, quote_large_num: bool) -> Self {
+        self.quote_large_num = Some(quote_large_num);
+        self
+    }
+
+    /// Asks the server to cancel the query when it runs longer than `timeout`
+    pub fn statement_timeout(mut self, timeout: Duration) -> Self {
+        self.statement_timeout = Some(timeout);
+        self
+    }
+
+    /// Gives up on the query after `timeout`, failing with [`Error::Timeout`](crate::Error::Timeout).
+    /// The server is asked to cancel the query after the same time, unless a
+    /// [statement timeout](Self::statement_timeout) is set.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the query in the body of a POST request to /exec when true, or in the url of a GET
+    /// request when false. By default only queries longer than
+    /// [`QuestDBBuilder::max_url_length`](crate::QuestDBBuilder::max_url_length) are POSTed.
+    pub fn post(mut self, post: bool) -> Self {
+        self.post = Some(post);
+        self
+    }
+
+    /// Sets the class the query is limited by, see
+    /// [`QuestDBBuilder::max_in_flight`](crate::QuestDBBuilder::max_in_flight)
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Adds a header to this call, see [`RequestContext::header`]. It replaces a
+    /// [default header](crate::QuestDBBuilder::default_header) of the same name, and setting a
+    /// [context](Self::context) afterwards drops it.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.context = Some(self.context.unwrap_or_default().header(name, value));
+        self
+    }
+
+    /// Authenticates this call with `token` in place of the credentials of the connection, see
+    /// [`RequestContext::bearer_token`]. Setting a [context](Self::context) afterwards replaces
+    /// the token.
+    pub fn token(mut self, token: &str) -> Self {
+        self.context = Some(self.context.unwrap_or_default().bearer_token(token));
+        self
+    }
+
+    /// Attaches a request context (a set of headers) to this call only. This lets a single
+    /// client serve several tenants when QuestDB sits behind an authorizing proxy.
+    pub fn context(mut self, context: RequestContext) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Options set on `self` take precedence, anything else is taken from `defaults`. The
+    /// priority is always the one of `self`.
+    pub(crate) fn or(&self, defaults: &ExecOptions) -> ExecOptions {
+        ExecOptions {
+            limit: self.limit.or(defaults.limit),
+            count: self.count.or(defaults.count),
+            nm: self.nm.or(defaults.nm),
+            timings: self.timings.or(defaults.timings),
+            quote_large_num: self.quote_large_num.or(defaults.quote_large_num),
+            statement_timeout: self.statement_timeout.or(defaults.statement_timeout),
+            timeout: self.timeout.or(defaults.timeout),
+            post: self.post.or(defaults.post),
+            context: self.context.clone().or_else(|| defaults.context.clone()),
+            priority: self.priority,
+        }
+    }
+}
+
+/// Rows of a result to return, sent as the `limit` parameter of /exec and /exp
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// The first `n` rows
+    Rows(u64),
+    /// Rows `lo` (inclusive) to `hi` (exclusive), counting from 0, for paging
+    Range(u64, u64),
+}
+
+impl From<usize> for Limit {
+    fn from(rows: usize) -> Self {
+        Limit::Rows(rows as u64)
+    }
+}
+
+impl From<std::ops::Range<u64>> for Limit {
+    fn from(range: std::ops::Range<u64>) -> Self {
+        Limit::Range(range.start, range.end)
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Rows(rows) => write!(f, "{}", rows),
+            Limit::Range(lo, hi) => write!(f, "{},{}", lo, hi),
+        }
+    }
+}
+
+/// Class of a request, each class has its own limit on requests in flight so background work
+/// can't starve user-facing queries sharing the same connection
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// Latency sensitive queries, e.g. for dashboards. Default for queries.
+    #[default]
+    Interactive,
+    /// Background work such as reports, imports and exports. Default for imports and exports.
+    Batch,
+}
+
+/// Set of headers identifying the tenant or database a request is made for
+///
+/// Gateways in front of QuestDB usually route or authorize requests based on a header, the
+/// context is attached to the request as-is.
+#[derive(Clone, Default)]
+pub struct RequestContext {
+    pub(crate) headers: Vec<(String, String)>,
+}
+
+impl RequestContext {
+    /// Creates an empty context
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a header to the context. Invalid header names or values are reported when the
+    /// request is sent.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+
+    /// Sends `Authorization: Bearer <token>` in place of the credentials of the connection,
+    /// e.g. to act on behalf of a tenant with its own token
+    pub fn bearer_token(self, token: &str) -> Self {
+        self.header("Authorization", &format!("Bearer {}", token))
+    }
+
+    pub(crate) fn apply(&self, mut req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        for (name, value) in &self.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        req
+    }
+}
+
+impl fmt::Debug for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut headers = f.debug_map();
+        for (name, value) in &self.headers {
+            match redact::is_sensitive_header(name) {
+                true => headers.entry(name, &Redacted),
+                false => headers.entry(name, value),
+            };
+        }
+        headers.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExecOptions, Limit, Priority};
+    use std::time::Duration;
+
+    #[test]
+    fn test_or_defaults() {
+        let defaults = ExecOptions::new()
+            .limit(100)
+            .timings(true)
+            .statement_timeout(Duration::from_secs(30))
+            .priority(Priority::Batch);
+        let options = ExecOptions::new().limit(5).or(&defaults);
+
+        assert_eq!(options.limit, Some(Limit::Rows(5)));
+        assert_eq!(options.timings, Some(true));
+        assert_eq!(options.statement_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(options.priority, Priority::Interactive);
+    }
+
+    #[test]
+    fn test_limit() {
+        assert_eq!(Limit::from(20).to_string(), "20");
+        assert_eq!(Limit::from(10..20).to_string(), "10,20");
+    }
+}
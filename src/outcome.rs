@@ -0,0 +1,69 @@
+use serde_json::Value;
+
+/// What a statement run with [`QuestDB::execute`](crate::QuestDB::execute) did
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExecOutcome {
+    /// A definition, e.g. `CREATE TABLE` or `ALTER TABLE`
+    Ddl,
+    /// A modification, e.g. `INSERT` or `UPDATE`. The server reports the rows affected for
+    /// some statements only.
+    Dml { rows_affected: Option<u64> },
+    /// A query, with the number of rows it returned
+    Query { rows: u64 },
+}
+
+impl ExecOutcome {
+    /// Rows changed or returned by the statement, when known
+    pub fn rows_affected(&self) -> Option<u64> {
+        match self {
+            ExecOutcome::Ddl => None,
+            ExecOutcome::Dml { rows_affected } => *rows_affected,
+            ExecOutcome::Query { rows } => Some(*rows),
+        }
+    }
+
+    /// Tells the outcome from the response of /exec, which is `{"ddl":"OK"}` for definitions,
+    /// `{"dml":"OK"}` for modifications and the rows for queries
+    pub(crate) fn from_response(res: &Value) -> Self {
+        if res.get("dml").is_some() {
+            let rows_affected = res.get("updated").and_then(Value::as_u64);
+            return ExecOutcome::Dml { rows_affected };
+        }
+        match res.get("dataset").and_then(Value::as_array) {
+            Some(rows) => ExecOutcome::Query {
+                rows: res
+                    .get("count")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(rows.len() as u64),
+            },
+            None => ExecOutcome::Ddl,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExecOutcome;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_response() {
+        assert_eq!(
+            ExecOutcome::from_response(&json!({"ddl": "OK"})),
+            ExecOutcome::Ddl
+        );
+        assert_eq!(
+            ExecOutcome::from_response(&json!({"dml": "OK"})),
+            ExecOutcome::Dml {
+                rows_affected: None
+            }
+        );
+        let updated = ExecOutcome::from_response(&json!({"dml": "OK", "updated": 3}));
+        assert_eq!(updated.rows_affected(), Some(3));
+        let query = json!({"columns": [{"name": "x", "type": "INT"}], "dataset": [[1], [2]]});
+        assert_eq!(
+            ExecOutcome::from_response(&query),
+            ExecOutcome::Query { rows: 2 }
+        );
+    }
+}
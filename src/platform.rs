@@ -0,0 +1,175 @@
+//! Clock, timers and background tasks of the target
+//!
+//! Natively these are tokio's and the standard library's. In the browser
+//! (`wasm32-unknown-unknown`) neither tokio's timers nor `std::time::Instant` work, they are
+//! built on JavaScript's `setTimeout` and `Date.now()` instead.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{future::Future, time::Duration};
+
+/// The timeout passed before the operation completed
+#[derive(Debug)]
+pub(crate) struct Elapsed;
+
+/// Time since the Unix epoch
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn unix_now() -> Duration {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+/// Runs `future` until it completes or `duration` passes
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    tokio::time::timeout(duration, future)
+        .await
+        .map_err(|_| Elapsed)
+}
+
+/// Runs `future` in the background
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+    tokio::spawn(future);
+}
+
+/// Whether the request failed to connect, which the browser doesn't tell apart
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn is_connect(err: &reqwest::Error) -> bool {
+    err.is_connect()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use wasm::*;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) mod wasm {
+    use super::Elapsed;
+    use futures_util::future::{self, Either};
+    use std::future::Future;
+    use std::ops::{Add, AddAssign, Sub, SubAssign};
+    use std::pin::pin;
+    use std::time::Duration;
+    use wasm_bindgen::{JsCast, JsValue};
+
+    /// Point in time measured with `Date.now()`, in place of [`std::time::Instant`] which isn't
+    /// available in the browser
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Instant(Duration);
+
+    impl Instant {
+        pub fn now() -> Self {
+            Instant(unix_now())
+        }
+
+        pub fn duration_since(&self, earlier: Instant) -> Duration {
+            self.saturating_duration_since(earlier)
+        }
+
+        pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+            self.0.checked_sub(earlier.0)
+        }
+
+        pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+            self.0.saturating_sub(earlier.0)
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Instant::now().saturating_duration_since(*self)
+        }
+
+        pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+            self.0.checked_add(duration).map(Instant)
+        }
+
+        pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+            self.0.checked_sub(duration).map(Instant)
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, duration: Duration) -> Instant {
+            Instant(self.0 + duration)
+        }
+    }
+
+    impl AddAssign<Duration> for Instant {
+        fn add_assign(&mut self, duration: Duration) {
+            self.0 += duration;
+        }
+    }
+
+    impl Sub<Duration> for Instant {
+        type Output = Instant;
+
+        fn sub(self, duration: Duration) -> Instant {
+            Instant(self.0 - duration)
+        }
+    }
+
+    impl SubAssign<Duration> for Instant {
+        fn sub_assign(&mut self, duration: Duration) {
+            self.0 -= duration;
+        }
+    }
+
+    impl Sub<Instant> for Instant {
+        type Output = Duration;
+
+        fn sub(self, earlier: Instant) -> Duration {
+            self.saturating_duration_since(earlier)
+        }
+    }
+
+    pub(crate) fn unix_now() -> Duration {
+        Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+    }
+
+    pub(crate) async fn sleep(duration: Duration) {
+        // setTimeout is looked up on the global object, which is `window` on pages and `self`
+        // in workers
+        let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+            let global = js_sys::global();
+            let set_timeout = js_sys::Reflect::get(&global, &JsValue::from_str("setTimeout"))
+                .ok()
+                .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+            if let Some(set_timeout) = set_timeout {
+                let millis = JsValue::from_f64(duration.as_millis() as f64);
+                let _ = set_timeout.call2(&global, &resolve, &millis);
+            }
+        });
+        let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+    }
+
+    pub(crate) async fn timeout<F: Future>(
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, Elapsed> {
+        match future::select(pin!(future), pin!(sleep(duration))).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right(_) => Err(Elapsed),
+        }
+    }
+
+    /// Runs `future` in the background. The browser is single-threaded, so it needn't be `Send`.
+    pub(crate) fn spawn<F: Future<Output = ()> + 'static>(future: F) {
+        wasm_bindgen_futures::spawn_local(future);
+    }
+
+    pub(crate) fn is_connect(_err: &reqwest::Error) -> bool {
+        false
+    }
+}
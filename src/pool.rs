@@ -0,0 +1,144 @@
+//! Managers plugging connections into the `deadpool` and `bb8` pools
+//!
+//! A pool hands out at most its size of connections at once and checks each one with a trivial
+//! query before handing it out again, so broken servers are noticed before a request uses them.
+
+use crate::{Error, QuestDB};
+
+/// Pool manager handing out clones of a REST connection
+///
+/// Clones share the HTTP connections of the original, the pool bounds how many tasks use the
+/// server at once and drops clones whose health check fails.
+///
+/// # Example
+/// ```no-test
+/// use questdb::{QuestDB, QuestDBManager};
+///
+/// let manager = QuestDBManager::new(QuestDB::new("http://192.168.1.37:9000"));
+/// let pool = deadpool::managed::Pool::builder(manager).max_size(8).build()?;
+///
+/// let connection = pool.get().await?;
+/// connection.exec_maps("select * from readings limit 10").await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct QuestDBManager {
+    connection: QuestDB,
+}
+
+impl QuestDBManager {
+    /// Creates a manager handing out clones of `connection`
+    pub fn new(connection: QuestDB) -> Self {
+        QuestDBManager { connection }
+    }
+}
+
+/// Fails if the server doesn't answer a trivial query
+async fn health_check(connection: &QuestDB) -> Result<(), Error> {
+    connection.execute("select 1").await.map(drop)
+}
+
+#[cfg(feature = "deadpool")]
+impl deadpool::managed::Manager for QuestDBManager {
+    type Type = QuestDB;
+    type Error = Error;
+
+    async fn create(&self) -> Result<QuestDB, Error> {
+        Ok(self.connection.clone())
+    }
+
+    async fn recycle(
+        &self,
+        connection: &mut QuestDB,
+        _metrics: &deadpool::managed::Metrics,
+    ) -> deadpool::managed::RecycleResult<Error> {
+        Ok(health_check(connection).await?)
+    }
+}
+
+#[cfg(feature = "bb8")]
+#[async_trait::async_trait]
+impl bb8::ManageConnection for QuestDBManager {
+    type Connection = QuestDB;
+    type Error = Error;
+
+    async fn connect(&self) -> Result<QuestDB, Error> {
+        Ok(self.connection.clone())
+    }
+
+    async fn is_valid(&self, connection: &mut QuestDB) -> Result<(), Error> {
+        health_check(connection).await
+    }
+
+    fn has_broken(&self, _connection: &mut QuestDB) -> bool {
+        false
+    }
+}
+
+/// Pool manager opening sqlx connections to the PostgreSQL wire endpoint
+///
+/// # Example
+/// ```no-test
+/// use questdb::{PgWire, PgWireManager, QuestDB};
+///
+/// let connection = QuestDB::new("http://192.168.1.37:9000");
+/// let manager = PgWireManager::new(connection.pg_connect_options(&PgWire::new())?);
+/// let pool = bb8::Pool::builder().max_size(8).build(manager).await?;
+/// ```
+#[cfg(feature = "sqlx-adapter")]
+#[derive(Clone, Debug)]
+pub struct PgWireManager {
+    options: sqlx::postgres::PgConnectOptions,
+}
+
+#[cfg(feature = "sqlx-adapter")]
+impl PgWireManager {
+    /// Creates a manager connecting with `options`, see [`QuestDB::pg_connect_options`]
+    pub fn new(options: sqlx::postgres::PgConnectOptions) -> Self {
+        PgWireManager { options }
+    }
+
+    async fn connect(&self) -> Result<sqlx::PgConnection, Error> {
+        use sqlx::ConnectOptions;
+        Ok(self.options.connect().await?)
+    }
+}
+
+#[cfg(all(feature = "sqlx-adapter", feature = "deadpool"))]
+impl deadpool::managed::Manager for PgWireManager {
+    type Type = sqlx::PgConnection;
+    type Error = Error;
+
+    async fn create(&self) -> Result<sqlx::PgConnection, Error> {
+        self.connect().await
+    }
+
+    async fn recycle(
+        &self,
+        connection: &mut sqlx::PgConnection,
+        _metrics: &deadpool::managed::Metrics,
+    ) -> deadpool::managed::RecycleResult<Error> {
+        use sqlx::Connection;
+        connection.ping().await.map_err(Error::from)?;
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "sqlx-adapter", feature = "bb8"))]
+#[async_trait::async_trait]
+impl bb8::ManageConnection for PgWireManager {
+    type Connection = sqlx::PgConnection;
+    type Error = Error;
+
+    async fn connect(&self) -> Result<sqlx::PgConnection, Error> {
+        PgWireManager::connect(self).await
+    }
+
+    async fn is_valid(&self, connection: &mut sqlx::PgConnection) -> Result<(), Error> {
+        use sqlx::Connection;
+        Ok(connection.ping().await?)
+    }
+
+    fn has_broken(&self, _connection: &mut sqlx::PgConnection) -> bool {
+        false
+    }
+}
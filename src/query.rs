@@ -0,0 +1,469 @@
+//! Builder for SELECT statements, window functions and templates for common time-series queries
+//!
+//! The templates render the SQL for downsampling, OHLC bars and counter rates and return a
+//! [`Select`] that can be narrowed down further before running it with
+//! [`QuestDB::exec`](crate::QuestDB::exec).
+//!
+//! # Example
+//! ```
+//! use questdb::query::{self, Bucket, Fill};
+//! use questdb::Timestamp;
+//!
+//! let sql = query::downsample("readings", "ts", "temp", "avg", Bucket::hours(1))
+//!     .column("sensor")
+//!     .fill(Fill::Prev)
+//!     .between(
+//!         "ts",
+//!         Timestamp::parse("2023-01-01").unwrap(),
+//!         Timestamp::parse("2023-01-02").unwrap(),
+//!     )
+//!     .to_string();
+//!
+//! assert_eq!(
+//!     sql,
+//!     "SELECT \"ts\", avg(\"temp\") AS \"avg_temp\", \"sensor\" FROM \"readings\" \
+//!      WHERE \"ts\" >= '2023-01-01T00:00:00.000000Z' AND \"ts\" < '2023-01-02T00:00:00.000000Z' \
+//!      SAMPLE BY 1h FILL(PREV) ALIGN TO CALENDAR"
+//! );
+//! ```
+
+use crate::sql::{quote_ident, quote_literal};
+use crate::{Error, Interval, Timestamp};
+use std::fmt::{self, Formatter};
+use std::time::Duration;
+
+/// Width of the time buckets of a `SAMPLE BY` query
+pub type Bucket = Interval;
+
+/// How buckets without any rows are filled in
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Fill {
+    /// Leaves the bucket out
+    None,
+    /// Emits the bucket with null values
+    Null,
+    /// Repeats the values of the previous bucket
+    Prev,
+    /// Interpolates between the surrounding buckets
+    Linear,
+    /// Uses a constant
+    Value(f64),
+}
+
+impl fmt::Display for Fill {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Fill::None => write!(f, "NONE"),
+            Fill::Null => write!(f, "NULL"),
+            Fill::Prev => write!(f, "PREV"),
+            Fill::Linear => write!(f, "LINEAR"),
+            Fill::Value(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// SELECT statement on a single table, rendered by its `Display` implementation
+///
+/// Columns and filters are SQL expressions and are used as-is, names passed to the other
+/// methods are quoted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Select {
+    table: String,
+    columns: Vec<String>,
+    filters: Vec<String>,
+    sample_by: Option<Bucket>,
+    fill: Option<Fill>,
+    order_by: Vec<String>,
+    limit: Option<usize>,
+}
+
+impl Select {
+    /// Selects from a table
+    pub fn from(table: &str) -> Self {
+        Select {
+            table: String::from(table),
+            columns: Vec::new(),
+            filters: Vec::new(),
+            sample_by: None,
+            fill: None,
+            order_by: Vec::new(),
+            limit: None,
+        }
+    }
+
+    /// Adds a column by name
+    pub fn column(self, name: &str) -> Self {
+        self.expr(&quote_ident(name))
+    }
+
+    /// Adds an expression to the selected columns, such as `count()` or `avg(temp) AS t`
+    pub fn expr(mut self, expr: &str) -> Self {
+        self.columns.push(String::from(expr));
+        self
+    }
+
+    /// Adds a window function as `alias`
+    pub fn window(self, window: &Window, alias: &str) -> Self {
+        self.expr(&format!("{} AS {}", window, quote_ident(alias)))
+    }
+
+    /// Adds a condition, all conditions must hold
+    pub fn filter(mut self, condition: &str) -> Self {
+        self.filters.push(String::from(condition));
+        self
+    }
+
+    /// Only selects rows with `column` from `from` (inclusive) to `to` (exclusive)
+    pub fn between(self, column: &str, from: Timestamp, to: Timestamp) -> Self {
+        let column = quote_ident(column);
+        self.filter(&format!(
+            "{} >= {} AND {} < {}",
+            column,
+            quote_literal(&from.to_string()),
+            column,
+            quote_literal(&to.to_string())
+        ))
+    }
+
+    /// Aggregates the rows into buckets of the designated timestamp, aligned to the calendar
+    pub fn sample_by(mut self, bucket: Bucket) -> Self {
+        self.sample_by = Some(bucket);
+        self
+    }
+
+    /// Fills in empty buckets, only used along with [`sample_by`](Self::sample_by)
+    pub fn fill(mut self, fill: Fill) -> Self {
+        self.fill = Some(fill);
+        self
+    }
+
+    /// Orders by a column, ascending unless `descending`
+    pub fn order_by(mut self, column: &str, descending: bool) -> Self {
+        let direction = if descending { " DESC" } else { "" };
+        self.order_by
+            .push(format!("{}{}", quote_ident(column), direction));
+        self
+    }
+
+    /// Only returns the first `limit` rows
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl fmt::Display for Select {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let columns = match self.columns.is_empty() {
+            true => String::from("*"),
+            false => self.columns.join(", "),
+        };
+        write!(f, "SELECT {} FROM {}", columns, quote_ident(&self.table))?;
+
+        if !self.filters.is_empty() {
+            write!(f, " WHERE {}", self.filters.join(" AND "))?;
+        }
+        if let Some(bucket) = self.sample_by {
+            write!(f, " SAMPLE BY {}", bucket)?;
+            if let Some(fill) = self.fill {
+                write!(f, " FILL({})", fill)?;
+            }
+            write!(f, " ALIGN TO CALENDAR")?;
+        }
+        if !self.order_by.is_empty() {
+            write!(f, " ORDER BY {}", self.order_by.join(", "))?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+        Ok(())
+    }
+}
+
+/// Window function call, i.e. a function evaluated over a window of rows related to the current
+/// row with an `OVER (...)` clause
+///
+/// ```
+/// use questdb::query::{Select, Window};
+/// use std::time::Duration;
+///
+/// let moving_average = Window::avg("price")
+///     .partition_by("symbol")
+///     .order_by("ts", false)
+///     .range_preceding(Duration::from_secs(60));
+/// let sql = Select::from("trades")
+///     .column("ts")
+///     .column("symbol")
+///     .window(&moving_average, "avg_1m")
+///     .to_string();
+///
+/// assert_eq!(
+///     sql,
+///     "SELECT \"ts\", \"symbol\", avg(\"price\") OVER (PARTITION BY \"symbol\" ORDER BY \"ts\" \
+///      RANGE BETWEEN 1 MINUTE PRECEDING AND CURRENT ROW) AS \"avg_1m\" FROM \"trades\""
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Window {
+    function: String,
+    partition_by: Vec<String>,
+    order_by: Vec<String>,
+    frame: Option<String>,
+}
+
+impl Window {
+    /// Window over any function call, used as-is
+    pub fn new(function: &str) -> Self {
+        Window {
+            function: String::from(function),
+            partition_by: Vec::new(),
+            order_by: Vec::new(),
+            frame: None,
+        }
+    }
+
+    /// Number of the row within its partition, starting at 1
+    pub fn row_number() -> Self {
+        Window::new("row_number()")
+    }
+
+    /// Rank of the row within its partition, with gaps after ties
+    pub fn rank() -> Self {
+        Window::new("rank()")
+    }
+
+    /// Rank of the row within its partition, without gaps after ties
+    pub fn dense_rank() -> Self {
+        Window::new("dense_rank()")
+    }
+
+    /// Average of the column over the window, e.g. a moving average
+    pub fn avg(column: &str) -> Self {
+        Window::new(&format!("avg({})", quote_ident(column)))
+    }
+
+    /// Sum of the column over the window, e.g. a running total
+    pub fn sum(column: &str) -> Self {
+        Window::new(&format!("sum({})", quote_ident(column)))
+    }
+
+    /// Splits the rows into partitions the function is evaluated on separately
+    pub fn partition_by(mut self, column: &str) -> Self {
+        self.partition_by.push(quote_ident(column));
+        self
+    }
+
+    /// Orders the rows of a partition, ascending unless `descending`
+    pub fn order_by(mut self, column: &str, descending: bool) -> Self {
+        let direction = if descending { " DESC" } else { "" };
+        self.order_by
+            .push(format!("{}{}", quote_ident(column), direction));
+        self
+    }
+
+    /// Limits the window to the current row and the `rows` before it
+    pub fn rows_preceding(mut self, rows: u64) -> Self {
+        self.frame = Some(format!("ROWS BETWEEN {} PRECEDING AND CURRENT ROW", rows));
+        self
+    }
+
+    /// Limits the window to the rows ordered within `range` before the current row, the window
+    /// must be ordered by the designated timestamp
+    pub fn range_preceding(mut self, range: Duration) -> Self {
+        self.frame = Some(format!(
+            "RANGE BETWEEN {} PRECEDING AND CURRENT ROW",
+            interval(range)
+        ));
+        self
+    }
+
+    /// Extends the window from the first row of the partition to the current row
+    pub fn unbounded_preceding(mut self) -> Self {
+        self.frame = Some(String::from(
+            "ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW",
+        ));
+        self
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut clauses = Vec::new();
+        if !self.partition_by.is_empty() {
+            clauses.push(format!("PARTITION BY {}", self.partition_by.join(", ")));
+        }
+        if !self.order_by.is_empty() {
+            clauses.push(format!("ORDER BY {}", self.order_by.join(", ")));
+        }
+        if let Some(frame) = &self.frame {
+            clauses.push(frame.clone());
+        }
+        write!(f, "{} OVER ({})", self.function, clauses.join(" "))
+    }
+}
+
+/// Duration in the largest unit it is a whole number of, e.g. `90 SECOND`
+fn interval(duration: Duration) -> String {
+    let micros = duration.as_micros();
+    let units = [
+        (86_400_000_000, "DAY"),
+        (3_600_000_000, "HOUR"),
+        (60_000_000, "MINUTE"),
+        (1_000_000, "SECOND"),
+        (1_000, "MILLISECOND"),
+    ];
+    for (size, unit) in units {
+        if micros >= size && micros.is_multiple_of(size) {
+            return format!("{} {}", micros / size, unit);
+        }
+    }
+    format!("{} MICROSECOND", micros)
+}
+
+/// Aggregates `column` with `aggregate` (e.g. `avg`, `max` or `sum`) per bucket. The result is
+/// named `<aggregate>_<column>`.
+pub fn downsample(
+    table: &str,
+    timestamp: &str,
+    column: &str,
+    aggregate: &str,
+    bucket: Bucket,
+) -> Select {
+    Select::from(table)
+        .column(timestamp)
+        .expr(&format!(
+            "{}({}) AS {}",
+            aggregate,
+            quote_ident(column),
+            quote_ident(&format!("{}_{}", aggregate, column))
+        ))
+        .sample_by(bucket)
+}
+
+/// Open, high, low and close of `price` per bucket, and the total `volume` if given
+///
+/// ```
+/// use questdb::query::{self, Bucket};
+///
+/// let sql = query::ohlc("trades", "ts", "price", Some("amount"), Bucket::minutes(1))
+///     .filter("symbol = 'BTC-USD'")
+///     .to_string();
+/// assert_eq!(
+///     sql,
+///     "SELECT \"ts\", first(\"price\") AS \"open\", max(\"price\") AS \"high\", \
+///      min(\"price\") AS \"low\", last(\"price\") AS \"close\", sum(\"amount\") AS \"volume\" \
+///      FROM \"trades\" WHERE symbol = 'BTC-USD' SAMPLE BY 1m ALIGN TO CALENDAR"
+/// );
+/// ```
+pub fn ohlc(
+    table: &str,
+    timestamp: &str,
+    price: &str,
+    volume: Option<&str>,
+    bucket: Bucket,
+) -> Select {
+    let price = quote_ident(price);
+    let select = Select::from(table)
+        .column(timestamp)
+        .expr(&format!("first({}) AS \"open\"", price))
+        .expr(&format!("max({}) AS \"high\"", price))
+        .expr(&format!("min({}) AS \"low\"", price))
+        .expr(&format!("last({}) AS \"close\"", price));
+
+    match volume {
+        Some(volume) => select.expr(&format!("sum({}) AS \"volume\"", quote_ident(volume))),
+        None => select,
+    }
+    .sample_by(bucket)
+}
+
+/// Increase of a monotonic `counter` per bucket as `delta`, and per second as `rate`
+///
+/// The increase is measured between the first and last row of each bucket, so it doesn't
+/// include the increase between buckets and can't account for counter resets. Fails with
+/// [`Error::InvalidInterval`] for buckets of months or years, whose length varies.
+pub fn counter_rate(
+    table: &str,
+    timestamp: &str,
+    counter: &str,
+    bucket: Bucket,
+) -> Result<Select, Error> {
+    let secs = bucket
+        .to_duration()
+        .ok_or_else(|| Error::InvalidInterval(bucket.to_string()))?
+        .as_secs_f64();
+    // Display never uses an exponent, a decimal point keeps the division fractional
+    let mut secs = secs.to_string();
+    if !secs.contains('.') {
+        secs.push_str(".0");
+    }
+
+    let counter = quote_ident(counter);
+    let delta = format!("last({}) - first({})", counter, counter);
+    Ok(Select::from(table)
+        .column(timestamp)
+        .expr(&format!("{} AS \"delta\"", delta))
+        .expr(&format!("({}) / {} AS \"rate\"", delta, secs))
+        .sample_by(bucket))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        assert_eq!(
+            Select::from("readings").to_string(),
+            "SELECT * FROM \"readings\""
+        );
+        assert_eq!(
+            Select::from("readings")
+                .column("id")
+                .filter("temp > 30")
+                .order_by("ts", true)
+                .limit(10)
+                .to_string(),
+            "SELECT \"id\" FROM \"readings\" WHERE temp > 30 ORDER BY \"ts\" DESC LIMIT 10"
+        );
+    }
+
+    #[test]
+    fn test_window() {
+        assert_eq!(
+            Window::row_number()
+                .partition_by("sensor")
+                .order_by("ts", true)
+                .to_string(),
+            "row_number() OVER (PARTITION BY \"sensor\" ORDER BY \"ts\" DESC)"
+        );
+        assert_eq!(
+            Window::sum("amount").unbounded_preceding().to_string(),
+            "sum(\"amount\") OVER (ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW)"
+        );
+        assert_eq!(interval(Duration::from_secs(90)), "90 SECOND");
+        assert_eq!(interval(Duration::from_secs(7200)), "2 HOUR");
+        assert_eq!(interval(Duration::from_micros(1500)), "1500 MICROSECOND");
+    }
+
+    #[test]
+    fn test_counter_rate() {
+        assert_eq!(
+            counter_rate("metrics", "ts", "requests", Bucket::minutes(5))
+                .unwrap()
+                .fill(Fill::Value(0.0))
+                .to_string(),
+            "SELECT \"ts\", last(\"requests\") - first(\"requests\") AS \"delta\", \
+             (last(\"requests\") - first(\"requests\")) / 300.0 AS \"rate\" FROM \"metrics\" \
+             SAMPLE BY 5m FILL(0) ALIGN TO CALENDAR"
+        );
+
+        let rate = |bucket: &str| {
+            counter_rate("metrics", "ts", "requests", bucket.parse().unwrap())
+                .map(|select| select.to_string())
+        };
+        assert!(rate("1500T").unwrap().contains(") / 1.5 AS \"rate\""));
+        assert!(rate("250U").unwrap().contains(") / 0.00025 AS \"rate\""));
+        assert!(matches!(rate("1M"), Err(Error::InvalidInterval(_))));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::options::{ExecOptions, Limit, Priority, RequestContext};
+use crate::outcome::ExecOutcome;
+use crate::row::{QueryResult, Row};
+use crate::stats::QueryStats;
+use crate::{Error, QuestDB};
+#[cfg(not(target_arch = "wasm32"))]
+use futures_util::stream::Stream;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+/// A query with its options, run by one of the `fetch` methods, see [`QuestDB::query`]
+///
+/// # Example
+/// ```no-test
+/// let page = connection
+///     .query("select * from readings")
+///     .limit(20, 40)
+///     .count(true)
+///     .fetch::<TestData>()
+///     .await?;
+/// ```
+#[derive(Clone, Debug)]
+#[must_use = "the query is only run by one of the fetch methods"]
+pub struct ExecRequest<'a> {
+    connection: &'a QuestDB,
+    query: String,
+    options: ExecOptions,
+}
+
+impl<'a> ExecRequest<'a> {
+    /// Only returns rows `lo` (inclusive) to `hi` (exclusive), for paging
+    pub fn limit(mut self, lo: u64, hi: u64) -> Self {
+        self.options.limit = Some(Limit::Range(lo, hi));
+        self
+    }
+
+    /// Instructs /exec to count rows and return this value in message header
+    pub fn count(mut self, count: bool) -> Self {
+        self.options.count = Some(count);
+        self
+    }
+
+    /// Skips the metadata section of the response when true
+    pub fn nm(mut self, nm: bool) -> Self {
+        self.options.nm = Some(nm);
+        self
+    }
+
+    /// Asks the server to report how long compiling, executing and counting took
+    pub fn timings(mut self, timings: bool) -> Self {
+        self.options.timings = Some(timings);
+        self
+    }
+
+    /// Returns LONG values as strings when true
+    pub fn quote_large_num(mut self, quote_large_num: bool) -> Self {
+        self.options.quote_large_num = Some(quote_large_num);
+        self
+    }
+
+    /// Asks the server to cancel the query when it runs longer than `timeout`
+    pub fn statement_timeout(mut self, timeout: Duration) -> Self {
+        self.options.statement_timeout = Some(timeout);
+        self
+    }
+
+    /// Gives up on the query after `timeout`, see [`ExecOptions::timeout`]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the query in the body of a POST request when true, see [`ExecOptions::post`]
+    pub fn post(mut self, post: bool) -> Self {
+        self.options.post = Some(post);
+        self
+    }
+
+    /// Sets the class the query is limited by
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.options.priority = priority;
+        self
+    }
+
+    /// Adds a header to the query, see [`ExecOptions::header`]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.options = self.options.header(name, value);
+        self
+    }
+
+    /// Authenticates the query with `token`, see [`ExecOptions::token`]
+    pub fn token(mut self, token: &str) -> Self {
+        self.options = self.options.token(token);
+        self
+    }
+
+    /// Attaches a request context (a set of headers) to the query
+    pub fn context(mut self, context: RequestContext) -> Self {
+        self.options.context = Some(context);
+        self
+    }
+
+    /// Replaces all options set so far
+    pub fn options(mut self, options: ExecOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Runs the query, see [`QuestDB::exec_with`]
+    pub async fn fetch<T: DeserializeOwned>(self) -> Result<Vec<T>, Error> {
+        self.connection.exec_with(&self.query, &self.options).await
+    }
+
+    /// Runs the query, see [`QuestDB::exec_with_stats`]
+    pub async fn fetch_with_stats<T: DeserializeOwned>(
+        self,
+    ) -> Result<(Vec<T>, QueryStats), Error> {
+        self.connection
+            .exec_with_stats(&self.query, &self.options)
+            .await
+    }
+
+    /// Runs the query, see [`QuestDB::exec_with_metadata`]
+    pub async fn fetch_with_metadata<T: DeserializeOwned>(self) -> Result<QueryResult<T>, Error> {
+        self.connection
+            .exec_with_metadata(&self.query, &self.options)
+            .await
+    }
+
+    /// Runs the query, see [`QuestDB::exec_rows`]
+    pub async fn fetch_rows(self) -> Result<Vec<Row>, Error> {
+        self.connection
+            .exec_rows_with(&self.query, &self.options)
+            .await
+    }
+
+    /// Runs a query that returns exactly one row, failing with [`Error::RowCount`] otherwise
+    pub async fn fetch_one<T: DeserializeOwned>(self) -> Result<T, Error> {
+        match self.fetch_optional().await? {
+            Some(row) => Ok(row),
+            None => Err(Error::RowCount {
+                expected: "one row",
+                got: 0,
+            }),
+        }
+    }
+
+    /// Runs a query that returns at most one row, failing with [`Error::RowCount`] when it
+    /// returns more
+    pub async fn fetch_optional<T: DeserializeOwned>(mut self) -> Result<Option<T>, Error> {
+        // A second row is enough to tell there are too many
+        self.options.limit = Some(Limit::Rows(2));
+        let mut rows: Vec<T> = self.fetch().await?;
+        match rows.len() {
+            0 | 1 => Ok(rows.pop()),
+            got => Err(Error::RowCount {
+                expected: "at most one row",
+                got,
+            }),
+        }
+    }
+
+    /// Runs a query that returns a single value, such as `select count() from readings`
+    pub async fn fetch_scalar<V: DeserializeOwned>(self) -> Result<V, Error> {
+        let (value,): (V,) = self.fetch_one().await?;
+        Ok(value)
+    }
+
+    /// Runs the query and yields the rows while they arrive, see [`QuestDB::exec_stream`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn stream<T: DeserializeOwned>(
+        self,
+    ) -> Result<impl Stream<Item = Result<T, Error>> + 'a, Error> {
+        self.connection
+            .exec_stream(&self.query, &self.options)
+            .await
+    }
+
+    /// Runs a statement, see [`QuestDB::execute`]
+    pub async fn execute(self) -> Result<ExecOutcome, Error> {
+        self.connection
+            .execute_with(&self.query, &self.options)
+            .await
+    }
+}
+
+impl QuestDB {
+    /// Starts building a query, the builder replaces the positional arguments of
+    /// [`exec`](Self::exec)
+    ///
+    /// # Example
+    /// ```no-test
+    /// let count: i64 = connection
+    ///     .query("select count() from readings")
+    ///     .fetch_scalar()
+    ///     .await?;
+    /// ```
+    pub fn query(&self, query: &str) -> ExecRequest<'_> {
+        ExecRequest {
+            connection: self,
+            query: String::from(query),
+            options: ExecOptions::new(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use crate::QuestDB;
+
+    #[test]
+    fn test_sql_macro() {
+        let connection = QuestDB::new("http://127.0.0.1:9000");
+        let table = String::from("sensor \"readings\"");
+        let name = "it's";
+        let id = 42;
+
+        let request = crate::sql!(
+            connection,
+            "select {{}} from {table:ident} where name = {name} and id = {id}"
+        );
+        assert_eq!(
+            request.query,
+            "select {} from \"sensor \"\"readings\"\"\" where name = 'it''s' and id = 42"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use crate::platform::{self, Instant};
+use crate::Error;
+#[cfg(not(target_arch = "wasm32"))]
+use bytes::Bytes;
+use reqwest::Response;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Decides whether a failed request is sent again, for retry behavior beyond the
+/// [built-in backoff](crate::QuestDBBuilder::max_attempts)
+///
+/// The policy is asked after every failed attempt at a read query or an export, including
+/// failures the built-in backoff never retries such as `429 Too Many Requests`. Statements that
+/// may write are never retried. A [retry timeout](crate::QuestDBBuilder::retry_timeout), when
+/// set, still ends the retries. Any `Fn(&RetryAttempt) -> Option<Duration>` closure can be used
+/// as a policy.
+///
+/// # Example
+/// ```
+/// use questdb::{QuestDB, RetryAttempt};
+/// use std::time::Duration;
+///
+/// // Waits out rate limiting, but gives up on anything else
+/// let connection = QuestDB::builder("http://192.168.1.37:9000")
+///     .retry_policy(|attempt: &RetryAttempt| match attempt.status {
+///         Some(429) if attempt.elapsed < Duration::from_secs(30) => Some(Duration::from_secs(1)),
+///         _ => None,
+///     })
+///     .build()
+///     .unwrap();
+/// ```
+pub trait RetryPolicy: Send + Sync {
+    /// Time to wait before sending the request again, `None` returns the failure to the caller
+    fn retry(&self, attempt: &RetryAttempt<'_>) -> Option<Duration>;
+}
+
+impl<F> RetryPolicy for F
+where
+    F: Fn(&RetryAttempt<'_>) -> Option<Duration> + Send + Sync,
+{
+    fn retry(&self, attempt: &RetryAttempt<'_>) -> Option<Duration> {
+        self(attempt)
+    }
+}
+
+/// Failed attempt at a request, handed to a [`RetryPolicy`]
+#[derive(Debug)]
+pub struct RetryAttempt<'a> {
+    /// Number of the attempt that failed, starting at 0
+    pub attempt: u32,
+    /// Time since the first attempt was sent
+    pub elapsed: Duration,
+    /// Status the server answered with, e.g. 503
+    pub status: Option<u16>,
+    /// Why no response was received, e.g. a connection error or a timeout
+    pub error: Option<&'a Error>,
+}
+
+/// Policy configured with [`QuestDBBuilder::retry_policy`](crate::QuestDBBuilder::retry_policy)
+#[derive(Clone)]
+pub(crate) struct CustomPolicy(pub(crate) Arc<dyn RetryPolicy>);
+
+impl fmt::Debug for CustomPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RetryPolicy")
+    }
+}
+
+/// Timeouts applied to every request and the budget available for retrying it
+#[derive(Clone, Debug)]
+pub(crate) struct RetryConfig {
+    /// Upper bound for a single attempt
+    pub(crate) request_timeout: Option<Duration>,
+    /// Upper bound for the whole operation including retries, zero disables retries
+    pub(crate) retry_timeout: Duration,
+    /// Upper bound for establishing a connection
+    pub(crate) connect_timeout: Option<Duration>,
+    /// Upper bound for waiting on the server, for the response and for each chunk of its body
+    pub(crate) read_timeout: Option<Duration>,
+    /// Upper bound for the whole operation including retries and reading the response
+    pub(crate) total_timeout: Option<Duration>,
+    /// Upper bound for the number of attempts, `None` leaves it to the retry timeout
+    pub(crate) max_attempts: Option<u32>,
+    /// Wait before the first retry, doubled for every following one
+    pub(crate) backoff: Duration,
+    /// Upper bound for the wait between two attempts, before the jitter
+    pub(crate) max_backoff: Duration,
+    /// Upper bound for the random time added to every wait
+    pub(crate) jitter: Duration,
+    /// Replaces the attempts and the backoff above
+    pub(crate) policy: Option<CustomPolicy>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            request_timeout: None,
+            retry_timeout: Duration::ZERO,
+            connect_timeout: None,
+            read_timeout: None,
+            total_timeout: None,
+            max_attempts: None,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            jitter: Duration::from_millis(10),
+            policy: None,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Point in time at which an operation started at `started` must give up, `None` when the
+    /// operation must not be retried
+    pub(crate) fn deadline(&self, started: Instant, idempotent: bool) -> Option<Instant> {
+        if !idempotent || self.retry_timeout.is_zero() {
+            return None;
+        }
+        Some(started + self.retry_timeout)
+    }
+
+    /// Time to wait before retrying the request after the failed `attempt` (starting at 0),
+    /// `None` when it must not be retried: it isn't idempotent, the failure isn't transient, the
+    /// attempts or the time budget are used up, or the retry policy gives up
+    pub(crate) fn next_retry(
+        &self,
+        idempotent: bool,
+        attempt: u32,
+        started: Instant,
+        deadline: Option<Instant>,
+        res: &Result<Response, Error>,
+    ) -> Option<Duration> {
+        if !idempotent {
+            return None;
+        }
+        let wait = match &self.policy {
+            Some(CustomPolicy(policy)) => {
+                let (status, error) = match res {
+                    Ok(res) if res.status().is_success() => return None,
+                    Ok(res) => (Some(res.status().as_u16()), None),
+                    Err(e) => (None, Some(e)),
+                };
+                policy.retry(&RetryAttempt {
+                    attempt,
+                    elapsed: started.elapsed(),
+                    status,
+                    error,
+                })?
+            }
+            None => {
+                let attempts_left = match self.max_attempts {
+                    Some(max) => attempt + 1 < max,
+                    None => deadline.is_some(),
+                };
+                if !attempts_left || !is_retriable(res) {
+                    return None;
+                }
+                self.backoff(attempt)
+            }
+        };
+        match deadline {
+            Some(deadline) if Instant::now() + wait >= deadline => None,
+            _ => Some(wait),
+        }
+    }
+
+    /// Time to wait before the retry following `attempt` (starting at 0): the backoff doubling up
+    /// to its maximum, plus some jitter so that clients failing together don't retry together
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let base = self
+            .backoff
+            .saturating_mul(2u32.saturating_pow(attempt.min(31)));
+        base.min(self.max_backoff) + jitter(self.jitter)
+    }
+
+    /// Timeout for the next attempt so that it ends before the deadline
+    pub(crate) fn attempt_timeout(&self, deadline: Option<Instant>) -> Option<Duration> {
+        let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        match (self.request_timeout, remaining) {
+            (Some(t), Some(r)) => Some(t.min(r)),
+            (t, r) => t.or(r),
+        }
+    }
+
+    /// Shorter of `timeout`, set for one operation, and the total timeout
+    pub(crate) fn operation_timeout(&self, timeout: Option<Duration>) -> Option<Duration> {
+        timeout.into_iter().chain(self.total_timeout).min()
+    }
+
+    /// Runs `operation` within the total timeout, failing with [`Error::Timeout`] once it passes
+    pub(crate) async fn total<T, F>(&self, operation: F) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        match self.total_timeout {
+            Some(timeout) => platform::timeout(timeout, operation)
+                .await
+                .unwrap_or(Err(Error::Timeout(timeout))),
+            None => operation.await,
+        }
+    }
+
+    /// Waits for the server within the read timeout, failing with [`Error::ReadTimeout`] once it
+    /// passes
+    pub(crate) async fn read<F: Future>(&self, read: F) -> Result<F::Output, Error> {
+        match self.read_timeout {
+            Some(timeout) => platform::timeout(timeout, read)
+                .await
+                .map_err(|_| Error::ReadTimeout(timeout)),
+            None => Ok(read.await),
+        }
+    }
+
+    /// Next chunk of the body of `res`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn chunk(&self, res: &mut Response) -> Result<Option<Bytes>, Error> {
+        self.read(res.chunk()).await?.map_err(Error::from)
+    }
+
+    /// Whole body of `res`, read chunk by chunk so that a stalled response fails with
+    /// [`Error::ReadTimeout`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn body(&self, mut res: Response) -> Result<Vec<u8>, Error> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.chunk(&mut res).await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    /// Whole body of `res`. The browser hands it over at once, the read timeout applies to all of
+    /// it.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) async fn body(&self, res: Response) -> Result<Vec<u8>, Error> {
+        Ok(self.read(res.bytes()).await??.to_vec())
+    }
+
+    /// Error for an attempt that failed, with the timeouts told apart from other failures.
+    /// `attempt_timeout` is the timeout the attempt was sent with.
+    pub(crate) fn attempt_error(
+        &self,
+        err: reqwest::Error,
+        attempt_timeout: Option<Duration>,
+    ) -> Error {
+        match (err.is_timeout(), platform::is_connect(&err)) {
+            (true, true) => match self.connect_timeout {
+                Some(timeout) => Error::ConnectTimeout(timeout),
+                None => Error::ExecError(err),
+            },
+            (true, false) => match attempt_timeout {
+                Some(timeout) => Error::Timeout(timeout),
+                None => Error::ExecError(err),
+            },
+            _ => Error::ExecError(err),
+        }
+    }
+}
+
+/// Whether the outcome of an attempt is a transient failure worth retrying
+pub(crate) fn is_retriable(res: &Result<Response, Error>) -> bool {
+    match res {
+        Ok(res) => res.status().is_server_error(),
+        Err(Error::ExecError(err)) => platform::is_connect(err) || err.is_timeout(),
+        Err(Error::ConnectTimeout(_) | Error::ReadTimeout(_) | Error::Timeout(_)) => true,
+        Err(_) => false,
+    }
+}
+
+/// Time to wait before the retry following `attempt` (starting at 0) with the default backoff:
+/// 10ms doubling up to one second, plus up to 10ms of jitter
+pub(crate) fn backoff(attempt: u32) -> Duration {
+    RetryConfig::default().backoff(attempt)
+}
+
+/// Random duration between zero and `max`
+pub(crate) fn jitter(max: Duration) -> Duration {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(platform::unix_now().subsec_nanos());
+    let nanos = max.as_nanos().max(1) as u64;
+    Duration::from_nanos(hasher.finish() % nanos)
+}
@@ -0,0 +1,393 @@
+use crate::stats::QueryTimings;
+use serde::de::value::MapDeserializer;
+use serde::de::{DeserializeOwned, Error as _, IntoDeserializer, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Column of a query result, as described by the metadata sent along with the rows
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct ColumnMeta {
+    pub name: String,
+    /// Type of the column as named by the server, e.g. `DOUBLE` or `TIMESTAMP`
+    #[serde(rename = "type")]
+    pub column_type: String,
+}
+
+/// Rows of a query together with the metadata of their columns, see
+/// [`QuestDB::exec_with_metadata`](crate::QuestDB::exec_with_metadata)
+#[derive(Clone, Debug)]
+pub struct QueryResult<T> {
+    pub columns: Vec<ColumnMeta>,
+    pub rows: Vec<T>,
+    /// Number of rows of the whole result, regardless of the limit. Only counted by the server
+    /// for queries run with [`ExecOptions::count`](crate::ExecOptions::count).
+    pub count: Option<u64>,
+    /// Time the server spent on the query, only reported for queries run with
+    /// [`ExecOptions::timings`](crate::ExecOptions::timings)
+    pub timings: Option<QueryTimings>,
+}
+
+impl<T> QueryResult<T> {
+    /// Position of the column called `name`
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name == name)
+    }
+}
+
+/// Row of a query whose columns aren't known at compile time, see
+/// [`QuestDB::exec_rows`](crate::QuestDB::exec_rows)
+///
+/// # Example
+/// ```no-test
+/// for row in connection.exec_rows("select * from readings").await? {
+///     let temp: f64 = row.get("temp")?;
+///     // Columns that may be null are read as options
+///     let humidity: Option<f64> = row.get("humidity")?;
+///     let first: i64 = row.get_by_index(0)?;
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    columns: Arc<[ColumnMeta]>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    /// Value of the column called `name`, converted to `T`
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> Result<T, crate::Error> {
+        let index = self
+            .columns
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| crate::Error::ColumnNotFound(String::from(name)))?;
+        self.get_by_index(index)
+    }
+
+    /// Value of the column at `index`, converted to `T`
+    pub fn get_by_index<T: DeserializeOwned>(&self, index: usize) -> Result<T, crate::Error> {
+        let value = self
+            .values
+            .get(index)
+            .ok_or_else(|| crate::Error::ColumnNotFound(format!("#{}", index)))?;
+        T::deserialize(value).map_err(|e| {
+            let name = self.columns.get(index).map_or("?", |c| c.name.as_str());
+            crate::Error::DeserializeError(serde_json::Error::custom(format!(
+                "column {}: {}",
+                name, e
+            )))
+        })
+    }
+
+    /// Whether the column called `name` is null, or doesn't exist
+    pub fn is_null(&self, name: &str) -> bool {
+        let index = self.columns.iter().position(|c| c.name == name);
+        index.is_none_or(|i| self.values.get(i).is_none_or(Value::is_null))
+    }
+
+    /// Columns of the row, in the order of the values
+    pub fn columns(&self) -> &[ColumnMeta] {
+        &self.columns
+    }
+
+    /// Raw values of the row
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// Rows returned by /exec together with the metadata of their columns
+#[derive(Debug, Deserialize)]
+pub(crate) struct Dataset {
+    #[serde(default)]
+    pub(crate) columns: Vec<ColumnMeta>,
+    pub(crate) dataset: Vec<Vec<Value>>,
+    #[serde(default)]
+    pub(crate) count: Option<u64>,
+}
+
+impl Dataset {
+    /// Pairs every value with the name of its column
+    pub(crate) fn into_maps(self) -> Vec<HashMap<String, Value>> {
+        let columns = self.columns;
+        self.dataset
+            .into_iter()
+            .map(|row| columns.iter().map(|c| c.name.clone()).zip(row).collect())
+            .collect()
+    }
+
+    /// Wraps every row, sharing the metadata between them
+    pub(crate) fn into_dynamic_rows(self) -> Vec<Row> {
+        let columns: Arc<[ColumnMeta]> = self.columns.into();
+        self.dataset
+            .into_iter()
+            .map(|mut values| {
+                unquote_longs(&columns, &mut values);
+                Row {
+                    columns: columns.clone(),
+                    values,
+                }
+            })
+            .collect()
+    }
+
+    /// Deserializes every row, see [`RowDeserializer`]
+    pub(crate) fn into_rows<T: DeserializeOwned>(self) -> Result<Vec<T>, serde_json::Error> {
+        let columns = self.columns;
+        self.dataset
+            .into_iter()
+            .enumerate()
+            .map(|(i, values)| from_row(i, &columns, values))
+            .collect()
+    }
+}
+
+/// Deserializes the `index`th row of a result, see [`RowDeserializer`]
+pub(crate) fn from_row<T: DeserializeOwned>(
+    index: usize,
+    columns: &[ColumnMeta],
+    mut values: Vec<Value>,
+) -> Result<T, serde_json::Error> {
+    unquote_longs(columns, &mut values);
+    T::deserialize(RowDeserializer { columns, values }).map_err(|e| row_error(index, columns, e))
+}
+
+/// Turns LONG values quoted by `quoteLargeNum=true` back into numbers, so they deserialize into
+/// integer fields without losing precision
+fn unquote_longs(columns: &[ColumnMeta], values: &mut [Value]) {
+    for (column, value) in columns.iter().zip(values) {
+        if column.column_type != "LONG" {
+            continue;
+        }
+        if let Some(long) = value.as_str().and_then(|s| s.parse::<i64>().ok()) {
+            *value = Value::from(long);
+        }
+    }
+}
+
+/// Names the row and the available columns, as a missing or mistyped field is usually a
+/// column the query doesn't select or calls differently
+fn row_error(row: usize, columns: &[ColumnMeta], e: serde_json::Error) -> serde_json::Error {
+    let columns: Vec<_> = columns.iter().map(|c| c.name.as_str()).collect();
+    serde_json::Error::custom(format!(
+        "row {}: {} (columns: {})",
+        row,
+        e,
+        columns.join(", ")
+    ))
+}
+
+/// A row of /exec, which is an array of values in the order of the columns
+///
+/// Structs and maps get the values by column name, everything else, such as tuples and
+/// vectors, by position. Without metadata (`nm=true`) all types get the values by position.
+struct RowDeserializer<'a> {
+    columns: &'a [ColumnMeta],
+    values: Vec<Value>,
+}
+
+impl RowDeserializer<'_> {
+    fn by_name<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, serde_json::Error> {
+        if self.columns.is_empty() {
+            return Value::Array(self.values).deserialize_any(visitor);
+        }
+        if self.columns.len() != self.values.len() {
+            return Err(serde_json::Error::custom(format!(
+                "{} values for {} columns",
+                self.values.len(),
+                self.columns.len()
+            )));
+        }
+        let entries = self
+            .columns
+            .iter()
+            .map(|c| c.name.clone())
+            .zip(self.values)
+            .map(|(name, value)| (name.into_deserializer(), value));
+        let mut map = MapDeserializer::new(entries);
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+}
+
+impl<'de> Deserializer<'de> for RowDeserializer<'_> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        Value::Array(self.values).deserialize_any(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.by_name(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.by_name(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dataset, QueryResult};
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Reading {
+        temp: Option<f64>,
+        id: i32,
+    }
+
+    #[test]
+    fn test_into_maps() {
+        let dataset: Dataset = serde_json::from_value(json!({
+            "query": "select * from readings",
+            "columns": [
+                {"name": "id", "type": "INT"},
+                {"name": "temp", "type": "DOUBLE"}
+            ],
+            "dataset": [[1, 21.5], [2, null]],
+            "count": 2
+        }))
+        .unwrap();
+        assert_eq!(dataset.count, Some(2));
+
+        let rows = dataset.into_maps();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["id"], json!(1));
+        assert_eq!(rows[0]["temp"], json!(21.5));
+        assert_eq!(rows[1]["temp"], json!(null));
+    }
+
+    #[test]
+    fn test_into_rows() {
+        let dataset = || -> Dataset {
+            serde_json::from_value(json!({
+                "columns": [
+                    {"name": "id", "type": "INT"},
+                    {"name": "temp", "type": "DOUBLE"}
+                ],
+                "dataset": [[1, 21.5], [2, null]]
+            }))
+            .unwrap()
+        };
+
+        // Structs by name, whatever the order of their fields
+        let rows: Vec<Reading> = dataset().into_rows().unwrap();
+        assert_eq!(rows[1], Reading { temp: None, id: 2 });
+
+        // Tuples by position
+        let rows: Vec<(i32, Option<f64>)> = dataset().into_rows().unwrap();
+        assert_eq!(rows, vec![(1, Some(21.5)), (2, None)]);
+
+        // Without metadata structs are read by position too
+        let mut positional = dataset();
+        positional.columns.clear();
+        positional.dataset = vec![vec![json!(21.5), json!(1)]];
+        let rows: Vec<Reading> = positional.into_rows().unwrap();
+        assert_eq!(
+            rows[0],
+            Reading {
+                temp: Some(21.5),
+                id: 1
+            }
+        );
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Missing {
+            id: i32,
+            humidity: f64,
+        }
+        let err = dataset().into_rows::<Missing>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "row 0: missing field `humidity` (columns: id, temp)"
+        );
+    }
+
+    #[test]
+    fn test_short_row() {
+        let dataset: Dataset = serde_json::from_value(json!({
+            "columns": [{"name": "id", "type": "INT"}, {"name": "temp", "type": "DOUBLE"}],
+            "dataset": [[1]]
+        }))
+        .unwrap();
+        let rows = dataset.into_dynamic_rows();
+        assert!(!rows[0].is_null("id"));
+        assert!(rows[0].is_null("temp"));
+    }
+
+    #[test]
+    fn test_quoted_longs() {
+        let dataset: Dataset = serde_json::from_value(json!({
+            "columns": [{"name": "id", "type": "LONG"}, {"name": "note", "type": "STRING"}],
+            "dataset": [["9007199254740993", "12"]]
+        }))
+        .unwrap();
+        let rows: Vec<(i64, String)> = dataset.into_rows().unwrap();
+        assert_eq!(rows[0], (9_007_199_254_740_993, String::from("12")));
+    }
+
+    #[test]
+    fn test_dynamic_rows() {
+        let dataset: Dataset = serde_json::from_value(json!({
+            "columns": [
+                {"name": "id", "type": "INT"},
+                {"name": "temp", "type": "DOUBLE"}
+            ],
+            "dataset": [[1, 21.5], [2, null]]
+        }))
+        .unwrap();
+
+        let rows = dataset.into_dynamic_rows();
+        assert_eq!(rows[0].get::<f64>("temp").unwrap(), 21.5);
+        assert_eq!(rows[0].get_by_index::<i32>(0).unwrap(), 1);
+        assert_eq!(rows[1].get::<Option<f64>>("temp").unwrap(), None);
+        assert!(rows[1].is_null("temp"));
+        assert!(rows[1].get::<f64>("temp").is_err());
+        assert!(matches!(
+            rows[0].get::<f64>("humidity"),
+            Err(crate::Error::ColumnNotFound(_))
+        ));
+        assert!(rows[0].get_by_index::<f64>(2).is_err());
+    }
+
+    #[test]
+    fn test_column_index() {
+        let res = QueryResult::<()> {
+            columns: serde_json::from_value(json!([
+                {"name": "id", "type": "INT"},
+                {"name": "temp", "type": "DOUBLE"}
+            ]))
+            .unwrap(),
+            rows: Vec::new(),
+            count: None,
+            timings: None,
+        };
+        assert_eq!(res.columns[1].column_type, "DOUBLE");
+        assert_eq!(res.column_index("temp"), Some(1));
+        assert_eq!(res.column_index("humidity"), None);
+    }
+}
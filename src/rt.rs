@@ -0,0 +1,165 @@
+//! Using the client from executors other than tokio, such as async-std or smol
+//!
+//! The HTTP stack is built on tokio's reactor, so futures of the client need one to register
+//! their sockets and timers with. Outside of a tokio runtime, [`compat`](CompatExt::compat)
+//! provides it: the crate starts a single-threaded reactor on a background thread the first time
+//! it is needed, which only drives the client's IO and timers. The futures themselves are still
+//! polled by the caller's executor. Inside a tokio runtime the wrapper does nothing.
+//!
+//! # Example
+//! ```no-test
+//! use questdb::rt::CompatExt;
+//!
+//! async_std::task::block_on(async {
+//!     let connection = QuestDB::new("http://192.168.1.37:9000");
+//!     let rows = connection
+//!         .exec_with::<TestData>("select * from readings", &ExecOptions::new())
+//!         .compat()
+//!         .await?;
+//! });
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use tokio::runtime::{Builder, EnterGuard, Handle};
+
+/// Handle of the background reactor, started on first use
+fn reactor() -> &'static Handle {
+    static REACTOR: OnceLock<Handle> = OnceLock::new();
+    REACTOR.get_or_init(|| {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("the reactor can be started");
+        let handle = runtime.handle().clone();
+        std::thread::Builder::new()
+            .name(String::from("questdb-reactor"))
+            .spawn(move || runtime.block_on(std::future::pending::<()>()))
+            .expect("the reactor thread can be spawned");
+        handle
+    })
+}
+
+/// Guard returned by [`enter`], the reactor is available until it is dropped
+pub struct ReactorGuard {
+    _guard: Option<EnterGuard<'static>>,
+}
+
+/// Makes the reactor available to the current thread until the guard is dropped, e.g. around
+/// calls that start background tasks such as
+/// [`exec_cancellable`](crate::QuestDB::exec_cancellable). Nothing is entered inside a tokio
+/// runtime.
+pub fn enter() -> ReactorGuard {
+    ReactorGuard {
+        _guard: Handle::try_current().err().map(|_| reactor().enter()),
+    }
+}
+
+/// Future polled with the reactor available, see [`CompatExt::compat`]
+pub struct Compat<F: Future> {
+    // Boxed to poll it without projecting the pin, taken out when dropped
+    inner: Option<Pin<Box<F>>>,
+}
+
+impl<F: Future> Future for Compat<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let _guard = enter();
+        match self.inner.as_mut() {
+            Some(inner) => inner.as_mut().poll(cx),
+            None => unreachable!("the future is only taken when dropped"),
+        }
+    }
+}
+
+impl<F: Future> Drop for Compat<F> {
+    fn drop(&mut self) {
+        // Sockets and timers deregister from the reactor when dropped
+        let _guard = enter();
+        self.inner.take();
+    }
+}
+
+/// Runs futures of the client on any executor, see the [module](self) documentation
+pub trait CompatExt: Future + Sized {
+    /// Wraps the future to make the reactor available whenever it is polled
+    fn compat(self) -> Compat<Self> {
+        Compat {
+            inner: Some(Box::pin(self)),
+        }
+    }
+}
+
+impl<F: Future> CompatExt for F {}
+
+#[cfg(test)]
+mod tests {
+    use super::CompatExt;
+    use crate::{ExecOptions, QuestDB};
+    use std::future::Future;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+    use std::time::Duration;
+
+    /// Minimal executor without a tokio runtime, parks the thread until woken
+    fn block_on<F: Future>(future: F) -> F::Output {
+        struct Unpark(Thread);
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_compat() {
+        // Timers register with the reactor when created, so create it inside the wrapper
+        block_on(async { tokio::time::sleep(Duration::from_millis(10)).await }.compat());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut head = String::new();
+            while !head.ends_with("\r\n\r\n") {
+                stream.read_line(&mut head).unwrap();
+            }
+            let body =
+                r#"{"query":"","columns":[{"name":"x","type":"INT"}],"dataset":[[1]],"count":1}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.get_mut().write_all(response.as_bytes()).unwrap();
+        });
+
+        let connection = QuestDB::new(&url);
+        let rows = block_on(
+            connection
+                .exec_with::<(i32,)>("select 1 x", &ExecOptions::new())
+                .compat(),
+        )
+        .unwrap();
+        assert_eq!(rows, [(1,)]);
+        server.join().unwrap();
+    }
+}
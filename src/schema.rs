@@ -0,0 +1,1163 @@
+use crate::query::Select;
+use crate::sql::{quote_ident, quote_literal};
+use crate::timestamp::{civil_from_days, days_from_civil, MICROS_PER_DAY, MICROS_PER_SEC};
+use crate::types::Schema;
+use crate::{Error, Interval, QuestDB, SampleUnit, Timestamp};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Definition of a table built at runtime, for applications whose tables are driven by
+/// configuration
+///
+/// # Example
+/// ```
+/// use questdb::{PartitionBy, Schema, TableSchema};
+///
+/// let schema = TableSchema::new("readings")
+///     .column("sensor", Schema::Symbol)
+///     .column("temp", Schema::Double)
+///     .column("ts", Schema::Timestamp)
+///     .timestamp("ts")
+///     .partition_by(PartitionBy::Day);
+///
+/// assert_eq!(
+///     schema.create_statement(),
+///     "CREATE TABLE IF NOT EXISTS \"readings\" (\"sensor\" SYMBOL, \"temp\" DOUBLE, \"ts\" TIMESTAMP) \
+///      timestamp(\"ts\") PARTITION BY DAY",
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableSchema {
+    name: String,
+    columns: Vec<ColumnSchema>,
+    timestamp: Option<String>,
+    partition_by: Option<PartitionBy>,
+}
+
+/// Column of a [`TableSchema`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub column_type: Schema,
+}
+
+/// Partitioning of a table by its designated timestamp
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PartitionBy {
+    None,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl fmt::Display for PartitionBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionBy::None => write!(f, "NONE"),
+            PartitionBy::Hour => write!(f, "HOUR"),
+            PartitionBy::Day => write!(f, "DAY"),
+            PartitionBy::Week => write!(f, "WEEK"),
+            PartitionBy::Month => write!(f, "MONTH"),
+            PartitionBy::Year => write!(f, "YEAR"),
+        }
+    }
+}
+
+impl PartitionBy {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "NONE" => Some(PartitionBy::None),
+            "HOUR" => Some(PartitionBy::Hour),
+            "DAY" => Some(PartitionBy::Day),
+            "WEEK" => Some(PartitionBy::Week),
+            "MONTH" => Some(PartitionBy::Month),
+            "YEAR" => Some(PartitionBy::Year),
+            _ => None,
+        }
+    }
+
+    /// Start of the partition `ts` falls into, `None` for unpartitioned tables
+    pub fn floor(&self, ts: Timestamp) -> Option<Timestamp> {
+        let micros = ts.as_micros();
+        let days = micros.div_euclid(MICROS_PER_DAY);
+        let (year, month, _) = civil_from_days(days);
+        let start = match self {
+            PartitionBy::None => return None,
+            PartitionBy::Hour => micros - micros.rem_euclid(3600 * MICROS_PER_SEC),
+            PartitionBy::Day => days * MICROS_PER_DAY,
+            // Weeks start on monday, 1970-01-01 was a thursday
+            PartitionBy::Week => (days - (days + 3).rem_euclid(7)) * MICROS_PER_DAY,
+            PartitionBy::Month => days_from_civil(year, month, 1) * MICROS_PER_DAY,
+            PartitionBy::Year => days_from_civil(year, 1, 1) * MICROS_PER_DAY,
+        };
+        Some(Timestamp::from_micros(start))
+    }
+
+    /// Length of a partition, `None` for unpartitioned tables
+    pub fn interval(&self) -> Option<Interval> {
+        match self {
+            PartitionBy::None => None,
+            PartitionBy::Hour => Some(Interval::hours(1)),
+            PartitionBy::Day => Some(Interval::days(1)),
+            PartitionBy::Week => Some(Interval::days(7)),
+            PartitionBy::Month => Some(Interval::months(1)),
+            PartitionBy::Year => Some(Interval::new(1, SampleUnit::Years)),
+        }
+    }
+
+    /// Name of the partition `ts` falls into, as listed by `table_partitions()` and expected by
+    /// `ALTER TABLE ... DROP PARTITION LIST`, e.g. `2023-03-16` for daily partitions
+    pub fn partition_name(&self, ts: Timestamp) -> Option<String> {
+        let start = self.floor(ts)?.as_micros();
+        let days = start.div_euclid(MICROS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        Some(match self {
+            PartitionBy::None => return None,
+            PartitionBy::Hour => format!(
+                "{:04}-{:02}-{:02}T{:02}",
+                year,
+                month,
+                day,
+                start.rem_euclid(MICROS_PER_DAY) / (3600 * MICROS_PER_SEC)
+            ),
+            PartitionBy::Day => format!("{:04}-{:02}-{:02}", year, month, day),
+            PartitionBy::Week => {
+                // The ISO year of a week is the year of its thursday
+                let (iso_year, _, _) = civil_from_days(days + 3);
+                let week = (days - iso_week_start(iso_year)) / 7 + 1;
+                format!("{:04}-W{:02}", iso_year, week)
+            }
+            PartitionBy::Month => format!("{:04}-{:02}", year, month),
+            PartitionBy::Year => format!("{:04}", year),
+        })
+    }
+
+    /// Start and end, exclusive, of the partition with the given name
+    pub fn partition_range(&self, name: &str) -> Result<(Timestamp, Timestamp), Error> {
+        let invalid = || Error::InvalidTimestamp(String::from(name));
+        let number = |s: Option<&str>| s.and_then(|s| s.parse::<i64>().ok()).ok_or_else(invalid);
+        let day = |days: i64| Timestamp::from_micros(days * MICROS_PER_DAY);
+
+        let (date, hour) = match name.split_once('T') {
+            Some((date, hour)) => (date, Some(hour)),
+            None => (name, None),
+        };
+        let mut parts = date.split('-');
+        let year = number(parts.next())?;
+        let (start, end) = match self {
+            PartitionBy::None => return Err(invalid()),
+            PartitionBy::Hour => {
+                let days = days_from_civil(year, number(parts.next())?, number(parts.next())?);
+                let start = days * MICROS_PER_DAY + number(hour)? * 3600 * MICROS_PER_SEC;
+                (
+                    Timestamp::from_micros(start),
+                    Timestamp::from_micros(start + 3600 * MICROS_PER_SEC),
+                )
+            }
+            PartitionBy::Day => {
+                let days = days_from_civil(year, number(parts.next())?, number(parts.next())?);
+                (day(days), day(days + 1))
+            }
+            PartitionBy::Week => {
+                let week = number(parts.next().and_then(|w| w.strip_prefix('W')))?;
+                let days = iso_week_start(year) + (week - 1) * 7;
+                (day(days), day(days + 7))
+            }
+            PartitionBy::Month => {
+                let month = number(parts.next())?;
+                let next = match month {
+                    12 => days_from_civil(year + 1, 1, 1),
+                    _ => days_from_civil(year, month + 1, 1),
+                };
+                (day(days_from_civil(year, month, 1)), day(next))
+            }
+            PartitionBy::Year => (
+                day(days_from_civil(year, 1, 1)),
+                day(days_from_civil(year + 1, 1, 1)),
+            ),
+        };
+        // Only the parts of the name this partitioning uses are accepted
+        if parts.next().is_some() || hour.is_some() != (*self == PartitionBy::Hour) {
+            return Err(invalid());
+        }
+        Ok((start, end))
+    }
+}
+
+/// Day of the monday starting the first ISO week of the year, the week containing january 4th
+fn iso_week_start(year: i64) -> i64 {
+    let jan4 = days_from_civil(year, 1, 4);
+    jan4 - (jan4 + 3).rem_euclid(7)
+}
+
+impl TableSchema {
+    /// Creates the definition of a table without any columns
+    pub fn new(name: &str) -> Self {
+        TableSchema {
+            name: String::from(name),
+            columns: Vec::new(),
+            timestamp: None,
+            partition_by: None,
+        }
+    }
+
+    /// Adds a column
+    pub fn column(mut self, name: &str, column_type: Schema) -> Self {
+        self.columns.push(ColumnSchema {
+            name: String::from(name),
+            column_type,
+        });
+        self
+    }
+
+    /// Makes the given column, which must be a timestamp column of the table, the designated
+    /// timestamp
+    pub fn timestamp(mut self, column: &str) -> Self {
+        self.timestamp = Some(String::from(column));
+        self
+    }
+
+    /// Partitions the table, requires a designated timestamp
+    pub fn partition_by(mut self, partition_by: PartitionBy) -> Self {
+        self.partition_by = Some(partition_by);
+        self
+    }
+
+    /// Name of the table
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Columns in the order they are created in
+    pub fn columns(&self) -> &[ColumnSchema] {
+        &self.columns
+    }
+
+    /// Name of the designated timestamp column
+    pub fn designated_timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Partitioning of the table, the server default when `None`
+    pub fn partitioning(&self) -> Option<PartitionBy> {
+        self.partition_by
+    }
+
+    /// `CREATE TABLE IF NOT EXISTS` statement for the table
+    pub fn create_statement(&self) -> String {
+        let columns: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| format!("{} {}", quote_ident(&c.name), c.column_type))
+            .collect();
+
+        let mut sql = format!(
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            quote_ident(&self.name),
+            columns.join(", ")
+        );
+        if let Some(ts) = &self.timestamp {
+            sql += &format!(" timestamp({})", quote_ident(ts));
+        }
+        if let Some(p) = self.partition_by {
+            sql += &format!(" PARTITION BY {}", p);
+        }
+        sql
+    }
+
+    /// Parses the definition of a table from a `CREATE TABLE` statement such as the one returned
+    /// by `SHOW CREATE TABLE`. Returns `None` when the statement uses column types or clauses
+    /// that can't be represented, storage options like symbol capacity or WAL are ignored.
+    ///
+    /// ```
+    /// use questdb::{PartitionBy, Schema, TableSchema};
+    ///
+    /// let schema = TableSchema::from_ddl(
+    ///     "CREATE TABLE 'readings' (\n\
+    ///          sensor SYMBOL CAPACITY 256 CACHE,\n\
+    ///          temp DOUBLE,\n\
+    ///          ts TIMESTAMP\n\
+    ///      ) timestamp(ts) PARTITION BY DAY WAL;",
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(
+    ///     schema,
+    ///     TableSchema::new("readings")
+    ///         .column("sensor", Schema::Symbol)
+    ///         .column("temp", Schema::Double)
+    ///         .column("ts", Schema::Timestamp)
+    ///         .timestamp("ts")
+    ///         .partition_by(PartitionBy::Day)
+    /// );
+    /// ```
+    pub fn from_ddl(ddl: &str) -> Option<Self> {
+        let ddl = ddl.trim();
+        let prefix = "CREATE TABLE";
+        if !ddl.get(..prefix.len())?.eq_ignore_ascii_case(prefix) {
+            return None;
+        }
+        let rest = &ddl[prefix.len()..];
+
+        let open = rest.find('(')?;
+        let close = open + closing_parenthesis(&rest[open..])?;
+        let name = rest[..open].trim();
+        let name = match name.get(..13) {
+            Some(p) if p.eq_ignore_ascii_case("IF NOT EXISTS") => name[13..].trim(),
+            _ => name,
+        };
+        let mut schema = TableSchema::new(unquote(name));
+
+        for definition in split_top_level(&rest[open + 1..close]) {
+            let mut words = definition.split_whitespace();
+            let name = unquote(words.next()?);
+            let column_type = Schema::from_name(words.next()?)?;
+            schema = schema.column(name, column_type);
+        }
+
+        let mut words = rest[close + 1..]
+            .split(|c: char| c.is_whitespace() || c == ';')
+            .filter(|w| !w.is_empty());
+        while let Some(word) = words.next() {
+            let lower = word.to_ascii_lowercase();
+            if let Some(ts) = lower.strip_prefix("timestamp(") {
+                let ts = &word[word.len() - ts.len()..];
+                schema = schema.timestamp(unquote(ts.strip_suffix(')')?));
+            } else if lower == "partition" {
+                words.next().filter(|w| w.eq_ignore_ascii_case("BY"))?;
+                schema = schema.partition_by(PartitionBy::parse(words.next()?)?);
+            }
+        }
+        Some(schema)
+    }
+
+    /// Differences between the definition and the columns of the existing table. Columns of the
+    /// table missing from the definition are fine.
+    fn differences<'a>(&'a self, live: &'a [TableColumn]) -> Vec<Difference<'a>> {
+        let mut differences = Vec::new();
+        for column in &self.columns {
+            let expected = column.column_type.to_string();
+            match live
+                .iter()
+                .find(|c| c.name.eq_ignore_ascii_case(&column.name))
+            {
+                None => differences.push(Difference::Missing(column)),
+                Some(c) if !c.column_type.eq_ignore_ascii_case(&expected) => {
+                    differences.push(Difference::Type {
+                        column,
+                        actual: &c.column_type,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        if let Some(ts) = &self.timestamp {
+            let designated = live.iter().find(|c| c.designated);
+            if !designated.is_some_and(|c| c.name.eq_ignore_ascii_case(ts)) {
+                differences.push(Difference::Timestamp(ts));
+            }
+        }
+        differences
+    }
+
+    /// Changes bringing the existing table in line with the definition, or the differences
+    /// that can't be reconciled under the policy
+    fn plan(
+        &self,
+        live: &[TableColumn],
+        policy: SyncPolicy,
+    ) -> Result<Vec<SchemaChange>, Vec<String>> {
+        let mut changes = Vec::new();
+        let mut conflicts = Vec::new();
+        for difference in self.differences(live) {
+            match difference {
+                Difference::Missing(column) => changes.push(SchemaChange::AddColumn {
+                    name: column.name.clone(),
+                    column_type: column.column_type,
+                }),
+                Difference::Type { column, actual }
+                    if policy == SyncPolicy::AddColumnsAndWiden
+                        && widens(actual, column.column_type) =>
+                {
+                    changes.push(SchemaChange::WidenColumn {
+                        name: column.name.clone(),
+                        from: String::from(actual),
+                        to: column.column_type,
+                    })
+                }
+                difference => conflicts.push(difference.to_string()),
+            }
+        }
+
+        match conflicts.is_empty() {
+            true => Ok(changes),
+            false => Err(conflicts),
+        }
+    }
+}
+
+/// Position of the parenthesis closing the one `s` starts with
+fn closing_parenthesis(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 1 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits on commas outside of parentheses, e.g. between column definitions
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+/// Removes the quotes around a name
+fn unquote(name: &str) -> &str {
+    name.trim_matches(|c| c == '\'' || c == '"')
+}
+
+/// Query aggregating a column of the given type, optionally limited to a time range of the
+/// timestamp column
+fn column_stats_query(
+    table: &str,
+    column: &str,
+    column_type: &str,
+    range: Option<(&str, (Timestamp, Timestamp))>,
+) -> Select {
+    let name = quote_ident(column);
+    let ordered = [
+        "BYTE",
+        "SHORT",
+        "CHAR",
+        "INT",
+        "LONG",
+        "FLOAT",
+        "DOUBLE",
+        "DATE",
+        "TIMESTAMP",
+    ]
+    .contains(&column_type);
+    let distinct = match column_type {
+        "INT" | "LONG" | "IPV4" => Some("approx_count_distinct"),
+        "SYMBOL" | "STRING" | "VARCHAR" | "LONG256" | "UUID" => Some("count_distinct"),
+        _ => None,
+    };
+
+    let mut select = Select::from(table)
+        .expr("count() AS \"rows\"")
+        .expr(&format!("count({}) AS \"non_null\"", name));
+    if let Some(distinct) = distinct {
+        select = select.expr(&format!("{}({}) AS \"distinct\"", distinct, name));
+    }
+    if ordered {
+        select = select
+            .expr(&format!("min({}) AS \"min\"", name))
+            .expr(&format!("max({}) AS \"max\"", name));
+    }
+    match range {
+        Some((timestamp, (from, to))) => select.between(timestamp, from, to),
+        None => select,
+    }
+}
+
+/// Statement creating a table, as returned by [`QuestDB::show_create_table`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableDefinition {
+    /// `CREATE TABLE` statement as reported by the server
+    pub ddl: String,
+    /// Definition parsed from the statement, `None` when it can't be represented by a
+    /// [`TableSchema`]
+    pub schema: Option<TableSchema>,
+}
+
+/// Difference between a [`TableSchema`] and the existing table
+enum Difference<'a> {
+    Missing(&'a ColumnSchema),
+    Type {
+        column: &'a ColumnSchema,
+        actual: &'a str,
+    },
+    Timestamp(&'a str),
+}
+
+impl fmt::Display for Difference<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Missing(column) => write!(f, "column '{}' is missing", column.name),
+            Difference::Type { column, actual } => write!(
+                f,
+                "column '{}' is {}, expected {}",
+                column.name, actual, column.column_type
+            ),
+            Difference::Timestamp(ts) => write!(f, "'{}' is not the designated timestamp", ts),
+        }
+    }
+}
+
+/// Whether values of type `from` can be converted to `to` without loss
+fn widens(from: &str, to: Schema) -> bool {
+    const INTEGERS: [&str; 4] = ["BYTE", "SHORT", "INT", "LONG"];
+    let from = from.to_ascii_uppercase();
+    match to {
+        Schema::Short | Schema::Int | Schema::Long => {
+            let to = to.to_string();
+            let position = |t: &str| INTEGERS.iter().position(|i| *i == t);
+            matches!((position(&from), position(&to)), (Some(f), Some(t)) if f < t)
+        }
+        Schema::Double => ["BYTE", "SHORT", "INT", "FLOAT"].contains(&from.as_str()),
+        Schema::String => from == "SYMBOL" || from == "CHAR",
+        _ => false,
+    }
+}
+
+/// How [`QuestDB::sync_table`] may alter an existing table
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// Only add the missing columns
+    #[default]
+    AddColumns,
+    /// Also change the type of columns to a wider one, e.g. `INT` to `LONG` or `FLOAT` to
+    /// `DOUBLE`
+    AddColumnsAndWiden,
+}
+
+/// Change applied to a table by [`QuestDB::sync_table`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaChange {
+    AddColumn {
+        name: String,
+        column_type: Schema,
+    },
+    WidenColumn {
+        name: String,
+        from: String,
+        to: Schema,
+    },
+}
+
+impl SchemaChange {
+    /// `ALTER TABLE` statement applying the change
+    pub fn statement(&self, table: &str) -> String {
+        match self {
+            SchemaChange::AddColumn { name, column_type } => format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                quote_ident(table),
+                quote_ident(name),
+                column_type
+            ),
+            SchemaChange::WidenColumn { name, to, .. } => format!(
+                "ALTER TABLE {} ALTER COLUMN {} TYPE {}",
+                quote_ident(table),
+                quote_ident(name),
+                to
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaChange::AddColumn { name, column_type } => {
+                write!(f, "added column '{}' {}", name, column_type)
+            }
+            SchemaChange::WidenColumn { name, from, to } => {
+                write!(f, "changed column '{}' from {} to {}", name, from, to)
+            }
+        }
+    }
+}
+
+/// Table parameter tuning ingestion, set with [`QuestDB::set_table_param`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TableParam {
+    /// Number of rows of a non-WAL table kept uncommitted before they are committed
+    MaxUncommittedRows(u32),
+    /// How long out-of-order rows are buffered before being merged into the table
+    O3MaxLag(Duration),
+}
+
+impl fmt::Display for TableParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableParam::MaxUncommittedRows(rows) => write!(f, "maxUncommittedRows = {}", rows),
+            TableParam::O3MaxLag(lag) => write!(f, "o3MaxLag = {}us", lag.as_micros()),
+        }
+    }
+}
+
+/// Column of an existing table, as reported by `table_columns()`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct TableColumn {
+    #[serde(rename = "column")]
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: String,
+    #[serde(default)]
+    pub indexed: bool,
+    #[serde(default)]
+    pub designated: bool,
+}
+
+/// Aggregates of a column, see [`QuestDB::column_stats`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnStats {
+    /// Type of the column
+    pub column_type: String,
+    /// Number of rows
+    pub rows: u64,
+    /// Number of rows where the column isn't null
+    pub non_null: u64,
+    /// Number of distinct values, estimated for integer columns. `None` for types the server
+    /// can't count distinct values of.
+    pub distinct: Option<u64>,
+    /// Smallest value, `Null` for types without an order or when all values are null
+    pub min: Value,
+    /// Largest value, `Null` for types without an order or when all values are null
+    pub max: Value,
+}
+
+impl QuestDB {
+    /// Lists the columns of a table
+    ///
+    /// # Example
+    /// ```no-test
+    /// let connection = QuestDB::new("http://192.168.1.37:9000");
+    /// for column in connection.table_columns("readings").await.unwrap() {
+    ///     println!("{}: {}", column.name, column.column_type);
+    /// }
+    /// ```
+    pub async fn table_columns(&self, table: &str) -> Result<Vec<TableColumn>, Error> {
+        let query = format!("SELECT * FROM table_columns({})", quote_literal(table));
+        self.exec_maps(&query)
+            .await?
+            .into_iter()
+            .map(|row| Ok(serde_json::from_value(row.into_iter().collect())?))
+            .collect()
+    }
+
+    /// Computes the number of rows, non-null and distinct values and the range of values of a
+    /// column, for data quality checks or to split exports into chunks of similar size
+    ///
+    /// `range` limits the rows to a time range of the designated timestamp, from inclusive to
+    /// exclusive.
+    ///
+    /// # Example
+    /// ```no-test
+    /// let stats = connection.column_stats("readings", "temp", None).await?;
+    /// if stats.non_null < stats.rows {
+    ///     println!("{} readings without a temperature", stats.rows - stats.non_null);
+    /// }
+    /// ```
+    pub async fn column_stats(
+        &self,
+        table: &str,
+        column: &str,
+        range: Option<(Timestamp, Timestamp)>,
+    ) -> Result<ColumnStats, Error> {
+        let columns = self.table_columns(table).await?;
+        let column_type = columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(column))
+            .map(|c| c.column_type.to_ascii_uppercase())
+            .ok_or_else(|| Error::SchemaMismatch {
+                table: String::from(table),
+                differences: vec![format!("column '{}' is missing", column)],
+            })?;
+
+        let timestamp = match range {
+            Some(_) => Some(
+                columns
+                    .iter()
+                    .find(|c| c.designated)
+                    .map(|c| c.name.as_str())
+                    .ok_or_else(|| Error::SchemaMismatch {
+                        table: String::from(table),
+                        differences: vec![String::from("there is no designated timestamp")],
+                    })?,
+            ),
+            None => None,
+        };
+        let select = column_stats_query(table, column, &column_type, timestamp.zip(range));
+
+        let mut row = self
+            .exec_maps(&select.to_string())
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let mut take = |key: &str| row.remove(key).unwrap_or(Value::Null);
+        Ok(ColumnStats {
+            rows: take("rows").as_u64().unwrap_or(0),
+            non_null: take("non_null").as_u64().unwrap_or(0),
+            distinct: take("distinct").as_u64(),
+            min: take("min"),
+            max: take("max"),
+            column_type,
+        })
+    }
+
+    /// Row of `tables()` describing the table, `None` when it doesn't exist
+    async fn table_info(&self, table: &str) -> Result<Option<HashMap<String, Value>>, Error> {
+        let tables = self.exec_maps("SELECT * FROM tables()").await?;
+        // Older versions name the column `name`
+        Ok(tables.into_iter().find(|row| {
+            row.get("table_name")
+                .or_else(|| row.get("name"))
+                .and_then(|name| name.as_str())
+                .is_some_and(|name| name.eq_ignore_ascii_case(table))
+        }))
+    }
+
+    /// Whether the table exists
+    pub async fn table_exists(&self, table: &str) -> Result<bool, Error> {
+        Ok(self.table_info(table).await?.is_some())
+    }
+
+    /// Partitioning and designated timestamp of an existing table
+    async fn table_partitioning(
+        &self,
+        table: &str,
+    ) -> Result<(PartitionBy, Option<String>), Error> {
+        let row = self
+            .table_info(table)
+            .await?
+            .ok_or_else(|| Error::TableNotFound(String::from(table)))?;
+
+        let partition_by = row
+            .get("partitionBy")
+            .and_then(|p| p.as_str())
+            .and_then(PartitionBy::parse)
+            .unwrap_or(PartitionBy::None);
+        let timestamp = row
+            .get("designatedTimestamp")
+            .and_then(|ts| ts.as_str())
+            .filter(|ts| !ts.is_empty())
+            .map(String::from);
+        Ok((partition_by, timestamp))
+    }
+
+    /// Deletes the rows with a designated timestamp from `from` (inclusive) to `to` (exclusive)
+    /// by dropping the partitions covering the range, which is far cheaper than rewriting them
+    ///
+    /// Both ends of the range must be partition boundaries, otherwise rows outside of the range
+    /// would be dropped too and [`Error::UnalignedRange`] is returned without deleting anything.
+    ///
+    /// # Example
+    /// ```no-test
+    /// let from = Timestamp::parse("2023-01-01")?;
+    /// let to = Timestamp::parse("2023-02-01")?;
+    /// connection.delete_range("readings", from, to).await?;
+    /// ```
+    pub async fn delete_range(
+        &self,
+        table: &str,
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<(), Error> {
+        let (partition_by, timestamp) = self.table_partitioning(table).await?;
+        let unaligned = |reason: &str| {
+            Err(Error::UnalignedRange(format!(
+                "{}..{} of '{}': {}",
+                from, to, table, reason
+            )))
+        };
+
+        let timestamp = match (partition_by, timestamp) {
+            (PartitionBy::None, _) | (_, None) => return unaligned("the table isn't partitioned"),
+            (_, Some(timestamp)) => timestamp,
+        };
+        if partition_by.floor(from) != Some(from) || partition_by.floor(to) != Some(to) {
+            return unaligned(&format!("not on {} partition boundaries", partition_by));
+        }
+        if from >= to {
+            return Ok(());
+        }
+
+        let ts = quote_ident(&timestamp);
+        self.execute(&format!(
+            "ALTER TABLE {} DROP PARTITION WHERE {} >= {} AND {} < {}",
+            quote_ident(table),
+            ts,
+            quote_literal(&from.to_string()),
+            ts,
+            quote_literal(&to.to_string())
+        ))
+        .await
+        .map(drop)
+    }
+
+    /// Renames a table
+    ///
+    /// Returns [`Error::TableNotFound`] when `from` doesn't exist. When `to` exists it is dropped
+    /// first if `overwrite` is set, otherwise [`Error::TableExists`] is returned.
+    ///
+    /// # Example
+    /// ```no-test
+    /// connection.rename_table("readings_staging", "readings", true).await?;
+    /// ```
+    pub async fn rename_table(&self, from: &str, to: &str, overwrite: bool) -> Result<(), Error> {
+        if !self.table_exists(from).await? {
+            return Err(Error::TableNotFound(String::from(from)));
+        }
+        // Renaming to a different case of the same name is allowed
+        if !from.eq_ignore_ascii_case(to) && self.table_exists(to).await? {
+            if !overwrite {
+                return Err(Error::TableExists(String::from(to)));
+            }
+            self.execute(&format!("DROP TABLE {}", quote_ident(to)))
+                .await?;
+        }
+
+        self.execute(&format!(
+            "RENAME TABLE {} TO {}",
+            quote_ident(from),
+            quote_ident(to)
+        ))
+        .await
+        .map(drop)
+    }
+
+    /// Sets a parameter of a table
+    ///
+    /// # Example
+    /// ```no-test
+    /// connection
+    ///     .set_table_param("readings", TableParam::O3MaxLag(Duration::from_secs(10)))
+    ///     .await?;
+    /// ```
+    pub async fn set_table_param(&self, table: &str, param: TableParam) -> Result<(), Error> {
+        self.execute(&format!(
+            "ALTER TABLE {} SET PARAM {}",
+            quote_ident(table),
+            param
+        ))
+        .await
+        .map(drop)
+    }
+
+    /// Enables deduplication of rows with the same upsert keys on a WAL table, the keys must
+    /// include the designated timestamp
+    pub async fn enable_dedup(&self, table: &str, upsert_keys: &[&str]) -> Result<(), Error> {
+        let keys: Vec<String> = upsert_keys.iter().map(|k| quote_ident(k)).collect();
+        self.execute(&format!(
+            "ALTER TABLE {} DEDUP ENABLE UPSERT KEYS({})",
+            quote_ident(table),
+            keys.join(", ")
+        ))
+        .await
+        .map(drop)
+    }
+
+    /// Disables deduplication on a table
+    pub async fn disable_dedup(&self, table: &str) -> Result<(), Error> {
+        self.execute(&format!("ALTER TABLE {} DEDUP DISABLE", quote_ident(table)))
+            .await
+            .map(drop)
+    }
+
+    /// Returns the statement creating the table, for tooling that snapshots schemas or compares
+    /// them across environments
+    ///
+    /// # Example
+    /// ```no-test
+    /// let staging = staging.show_create_table("readings").await?;
+    /// let production = production.show_create_table("readings").await?;
+    /// if staging.schema != production.schema {
+    ///     println!("readings differs:\n{}\n{}", staging.ddl, production.ddl);
+    /// }
+    /// ```
+    pub async fn show_create_table(&self, table: &str) -> Result<TableDefinition, Error> {
+        let rows = self
+            .exec_maps(&format!("SHOW CREATE TABLE {}", quote_ident(table)))
+            .await?;
+        let ddl = rows
+            .into_iter()
+            .next()
+            .and_then(|mut row| match row.remove("ddl") {
+                Some(Value::String(ddl)) => Some(ddl),
+                _ => None,
+            })
+            .ok_or_else(|| Error::TableNotFound(String::from(table)))?;
+
+        let schema = TableSchema::from_ddl(&ddl);
+        Ok(TableDefinition { ddl, schema })
+    }
+
+    /// Creates the table if it doesn't exist, otherwise checks that the existing table has all
+    /// the columns of the schema with the same types
+    ///
+    /// Returns [`Error::SchemaMismatch`] listing the differences when the table isn't compatible,
+    /// the table is never altered.
+    ///
+    /// # Example
+    /// ```no-test
+    /// let schema = TableSchema::new("readings")
+    ///     .column("temp", Schema::Double)
+    ///     .column("ts", Schema::Timestamp)
+    ///     .timestamp("ts");
+    /// connection.ensure_table(&schema).await?;
+    /// ```
+    pub async fn ensure_table(&self, schema: &TableSchema) -> Result<(), Error> {
+        self.execute(&schema.create_statement()).await?;
+
+        let live = self.table_columns(&schema.name).await?;
+        let differences = schema.differences(&live);
+        if !differences.is_empty() {
+            self.inner.metadata.invalidate(&schema.name);
+            return Err(Error::SchemaMismatch {
+                table: schema.name.clone(),
+                differences: differences.iter().map(|d| d.to_string()).collect(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Creates the table if it doesn't exist, otherwise alters it to match the schema as far as
+    /// the policy allows, a lightweight alternative to migrations
+    ///
+    /// Columns of the table missing from the schema are kept. Returns the changes that were
+    /// applied, or [`Error::SchemaMismatch`] without altering anything when the table can't be
+    /// reconciled.
+    ///
+    /// # Example
+    /// ```no-test
+    /// let changes = connection.sync_table(&schema, SyncPolicy::AddColumnsAndWiden).await?;
+    /// for change in changes {
+    ///     println!("readings: {}", change);
+    /// }
+    /// ```
+    pub async fn sync_table(
+        &self,
+        schema: &TableSchema,
+        policy: SyncPolicy,
+    ) -> Result<Vec<SchemaChange>, Error> {
+        self.execute(&schema.create_statement()).await?;
+
+        let live = self.table_columns(&schema.name).await?;
+        let changes = schema.plan(&live, policy).map_err(|differences| {
+            self.inner.metadata.invalidate(&schema.name);
+            Error::SchemaMismatch {
+                table: schema.name.clone(),
+                differences,
+            }
+        })?;
+        for change in &changes {
+            self.execute(&change.statement(&schema.name)).await?;
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        column_stats_query, PartitionBy, SchemaChange, SyncPolicy, TableColumn, TableParam,
+        TableSchema,
+    };
+    use crate::types::Schema;
+    use crate::Timestamp;
+    use std::time::Duration;
+
+    fn live(name: &str, column_type: &str, designated: bool) -> TableColumn {
+        TableColumn {
+            name: String::from(name),
+            column_type: String::from(column_type),
+            indexed: false,
+            designated,
+        }
+    }
+
+    #[test]
+    fn test_differences() {
+        let schema = TableSchema::new("readings")
+            .column("id", Schema::Int)
+            .column("temp", Schema::Double)
+            .column("ts", Schema::Timestamp)
+            .timestamp("ts");
+
+        let compatible = [
+            live("id", "INT", false),
+            live("TEMP", "DOUBLE", false),
+            live("ts", "TIMESTAMP", true),
+            live("extra", "STRING", false),
+        ];
+        assert!(schema.differences(&compatible).is_empty());
+
+        let incompatible = [live("id", "LONG", false), live("ts", "TIMESTAMP", false)];
+        let differences: Vec<String> = schema
+            .differences(&incompatible)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            differences,
+            [
+                "column 'id' is LONG, expected INT",
+                "column 'temp' is missing",
+                "'ts' is not the designated timestamp",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_partition_floor() {
+        let ts = |s: &str| s.parse::<Timestamp>().unwrap();
+        let t = ts("2023-03-16T10:20:30.5Z");
+
+        assert_eq!(PartitionBy::None.floor(t), None);
+        assert_eq!(PartitionBy::Hour.floor(t), Some(ts("2023-03-16T10:00:00Z")));
+        assert_eq!(PartitionBy::Day.floor(t), Some(ts("2023-03-16")));
+        assert_eq!(PartitionBy::Week.floor(t), Some(ts("2023-03-13")));
+        assert_eq!(PartitionBy::Month.floor(t), Some(ts("2023-03-01")));
+        assert_eq!(PartitionBy::Year.floor(t), Some(ts("2023-01-01")));
+        assert_eq!(
+            PartitionBy::Week.floor(ts("1969-12-31")),
+            Some(ts("1969-12-29"))
+        );
+    }
+
+    #[test]
+    fn test_partition_names() {
+        let ts = |s: &str| s.parse::<Timestamp>().unwrap();
+        let t = ts("2021-01-03T10:20:30Z");
+
+        let cases = [
+            (
+                PartitionBy::Hour,
+                "2021-01-03T10",
+                "2021-01-03T10:00:00Z",
+                "2021-01-03T11:00:00Z",
+            ),
+            (PartitionBy::Day, "2021-01-03", "2021-01-03", "2021-01-04"),
+            // Belongs to the last week of 2020
+            (PartitionBy::Week, "2020-W53", "2020-12-28", "2021-01-04"),
+            (PartitionBy::Month, "2021-01", "2021-01-01", "2021-02-01"),
+            (PartitionBy::Year, "2021", "2021-01-01", "2022-01-01"),
+        ];
+        for (partition_by, name, start, end) in cases {
+            assert_eq!(partition_by.partition_name(t).as_deref(), Some(name));
+            assert_eq!(
+                partition_by.partition_range(name).unwrap(),
+                (ts(start), ts(end))
+            );
+        }
+
+        assert_eq!(PartitionBy::None.partition_name(t), None);
+        assert!(PartitionBy::Day.partition_range("2021-01").is_err());
+        assert!(PartitionBy::Day.partition_range("2021-01-03T10").is_err());
+        assert_eq!(PartitionBy::Week.interval().unwrap().to_string(), "7d");
+    }
+
+    #[test]
+    fn test_table_param() {
+        assert_eq!(
+            TableParam::MaxUncommittedRows(10_000).to_string(),
+            "maxUncommittedRows = 10000"
+        );
+        assert_eq!(
+            TableParam::O3MaxLag(Duration::from_millis(1500)).to_string(),
+            "o3MaxLag = 1500000us"
+        );
+    }
+
+    #[test]
+    fn test_from_ddl() {
+        let schema = TableSchema::from_ddl(
+            "CREATE TABLE \"trades\" (symbol SYMBOL CAPACITY 256 CACHE INDEX CAPACITY 256, \
+             price DOUBLE, amount DOUBLE, timestamp TIMESTAMP) \
+             timestamp(timestamp) PARTITION BY MONTH WAL \
+             WITH maxUncommittedRows=500000, o3MaxLag=600000000us;",
+        )
+        .unwrap();
+        assert_eq!(schema.name(), "trades");
+        assert_eq!(schema.columns().len(), 4);
+        assert_eq!(schema.designated_timestamp(), Some("timestamp"));
+        assert_eq!(schema.partitioning(), Some(PartitionBy::Month));
+
+        let round_trip = TableSchema::from_ddl(&schema.create_statement());
+        assert_eq!(round_trip.as_ref(), Some(&schema));
+
+        assert_eq!(TableSchema::from_ddl("CREATE TABLE t (id UUID)"), None);
+        assert_eq!(TableSchema::from_ddl("DROP TABLE t"), None);
+    }
+
+    #[test]
+    fn test_column_stats_query() {
+        assert_eq!(
+            column_stats_query("readings", "sensor", "SYMBOL", None).to_string(),
+            "SELECT count() AS \"rows\", count(\"sensor\") AS \"non_null\", \
+             count_distinct(\"sensor\") AS \"distinct\" FROM \"readings\""
+        );
+
+        let range = (Timestamp::from_micros(0), Timestamp::from_micros(1_000_000));
+        assert_eq!(
+            column_stats_query("readings", "temp", "DOUBLE", Some(("ts", range))).to_string(),
+            "SELECT count() AS \"rows\", count(\"temp\") AS \"non_null\", \
+             min(\"temp\") AS \"min\", max(\"temp\") AS \"max\" FROM \"readings\" \
+             WHERE \"ts\" >= '1970-01-01T00:00:00.000000Z' AND \"ts\" < '1970-01-01T00:00:01.000000Z'"
+        );
+    }
+
+    #[test]
+    fn test_plan() {
+        let schema = TableSchema::new("readings")
+            .column("id", Schema::Long)
+            .column("temp", Schema::Double)
+            .column("sensor", Schema::Symbol);
+        let live = [live("id", "INT", false), live("sensor", "SYMBOL", false)];
+
+        assert_eq!(
+            schema.plan(&live, SyncPolicy::AddColumns),
+            Err(vec![String::from("column 'id' is INT, expected LONG")])
+        );
+        assert_eq!(
+            schema.plan(&live, SyncPolicy::AddColumnsAndWiden),
+            Ok(vec![
+                SchemaChange::WidenColumn {
+                    name: String::from("id"),
+                    from: String::from("INT"),
+                    to: Schema::Long,
+                },
+                SchemaChange::AddColumn {
+                    name: String::from("temp"),
+                    column_type: Schema::Double,
+                },
+            ])
+        );
+        assert_eq!(
+            SchemaChange::AddColumn {
+                name: String::from("temp"),
+                column_type: Schema::Double,
+            }
+            .statement("readings"),
+            "ALTER TABLE \"readings\" ADD COLUMN \"temp\" DOUBLE"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! Adapters for `#[serde(with = "...")]` converting QuestDB's timestamp representations and
+//! quoted LONG values, and [`Symbol`] for mapping SYMBOL columns to enums
+//!
+//! Query results contain timestamps as ISO 8601 strings (`2019-10-17T00:00:00.100000Z`) while
+//! line protocol and most applications work with integers or date types. These modules convert
+//! between them on struct fields.
+//!
+//! # Example
+//! ```
+//! use questdb::serde::{ts_iso, ts_micros};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Reading {
+//!     #[serde(with = "ts_micros")]
+//!     ts: i64,
+//!     #[serde(with = "ts_iso::option")]
+//!     calibrated: Option<questdb::Timestamp>,
+//! }
+//!
+//! let reading: Reading = serde_json::from_str(
+//!     r#"{"ts": "2019-10-17T00:00:00.100000Z", "calibrated": null}"#,
+//! ).unwrap();
+//! assert_eq!(reading.ts, 1_571_270_400_100_000);
+//! ```
+
+/// `i64` fields holding microseconds since the epoch. Deserializes from ISO 8601 strings or
+/// integers, serializes as an integer.
+pub mod ts_micros {
+    use crate::Timestamp;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(micros: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(*micros)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+        Timestamp::deserialize(deserializer).map(|ts| ts.as_micros())
+    }
+
+    /// Same as [`ts_micros`](super::ts_micros) for nullable columns
+    pub mod option {
+        use crate::Timestamp;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            micros: &Option<i64>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match micros {
+                Some(micros) => serializer.serialize_some(micros),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<i64>, D::Error> {
+            Option::<Timestamp>::deserialize(deserializer).map(|ts| ts.map(|ts| ts.as_micros()))
+        }
+    }
+}
+
+/// Fields of any type convertible from and to [`Timestamp`](crate::Timestamp): `i64`
+/// microseconds, `Timestamp`, `chrono::DateTime<Utc>` (feature `chrono`) and
+/// `time::OffsetDateTime` (feature `time`). Deserializes from ISO 8601 strings or integers,
+/// serializes as an ISO 8601 string.
+pub mod ts_iso {
+    use crate::Timestamp;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Clone + Into<Timestamp>,
+        S: Serializer,
+    {
+        value.clone().into().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: From<Timestamp>,
+        D: Deserializer<'de>,
+    {
+        Timestamp::deserialize(deserializer).map(T::from)
+    }
+
+    /// Same as [`ts_iso`](super::ts_iso) for nullable columns
+    pub mod option {
+        use crate::Timestamp;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            T: Clone + Into<Timestamp>,
+            S: Serializer,
+        {
+            value.clone().map(Into::into).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+        where
+            T: From<Timestamp>,
+            D: Deserializer<'de>,
+        {
+            Option::<Timestamp>::deserialize(deserializer).map(|ts| ts.map(T::from))
+        }
+    }
+}
+
+/// `i64` fields of LONG columns, deserializing from integers or from the strings the server
+/// returns for queries run with [`ExecOptions::quote_large_num`](crate::ExecOptions::quote_large_num).
+/// Serializes as an integer.
+///
+/// Rows read with their metadata convert quoted LONG values by themselves, this is only needed
+/// for results without metadata (`nm=true`) or values deserialized outside of queries.
+///
+/// # Example
+/// ```
+/// use questdb::serde::long;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Trade {
+///     #[serde(with = "long")]
+///     id: i64,
+/// }
+///
+/// let trade: Trade = serde_json::from_str(r#"{"id": "9007199254740993"}"#).unwrap();
+/// assert_eq!(trade.id, 9_007_199_254_740_993);
+/// ```
+pub mod long {
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(value: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(*value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+        deserializer.deserialize_any(LongVisitor)
+    }
+
+    struct LongVisitor;
+
+    impl Visitor<'_> for LongVisitor {
+        type Value = i64;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an integer or a string holding one")
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<i64, E> {
+            Ok(v)
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<i64, E> {
+            i64::try_from(v).map_err(|_| E::custom(format!("{} is out of range for a LONG", v)))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<i64, E> {
+            v.parse()
+                .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+        }
+    }
+
+    /// Same as [`long`](super::long) for nullable columns
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        #[derive(Deserialize)]
+        struct Long(#[serde(with = "super")] i64);
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<i64>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            value.serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<i64>, D::Error> {
+            Option::<Long>::deserialize(deserializer).map(|v| v.map(|Long(v)| v))
+        }
+    }
+}
+
+/// Value of a SYMBOL or string column mapped to the enum `T`, keeping values that aren't a
+/// variant of `T` instead of failing
+///
+/// Symbols deserialize into any enum of unit variants with serde's derive, which fails on
+/// unknown values. Wrapping the enum in `Symbol` accepts values added to the table after the
+/// enum was written.
+///
+/// # Example
+/// ```
+/// use questdb::serde::Symbol;
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, PartialEq, Deserialize)]
+/// #[serde(rename_all = "lowercase")]
+/// enum Side {
+///     Buy,
+///     Sell,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Trade {
+///     side: Symbol<Side>,
+/// }
+///
+/// let trades: Vec<Trade> = serde_json::from_str(r#"[{"side": "buy"}, {"side": "short"}]"#).unwrap();
+/// assert_eq!(trades[0].side, Symbol::Known(Side::Buy));
+/// assert_eq!(trades[1].side, Symbol::Other(String::from("short")));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Symbol<T> {
+    /// Value matching a variant of `T`
+    Known(T),
+    /// Any other value
+    Other(String),
+}
+
+impl<T> Symbol<T> {
+    /// The variant, `None` for other values
+    pub fn known(&self) -> Option<&T> {
+        match self {
+            Symbol::Known(value) => Some(value),
+            Symbol::Other(_) => None,
+        }
+    }
+}
+
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Symbol<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::value::{Error, StrDeserializer};
+        use serde::de::IntoDeserializer;
+
+        let value = String::deserialize(deserializer)?;
+        let known: StrDeserializer<'_, Error> = value.as_str().into_deserializer();
+        Ok(match T::deserialize(known) {
+            Ok(known) => Symbol::Known(known),
+            Err(_) => Symbol::Other(value),
+        })
+    }
+}
+
+impl<T: serde::Serialize> serde::Serialize for Symbol<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Symbol::Known(value) => value.serialize(serializer),
+            Symbol::Other(value) => serializer.serialize_str(value),
+        }
+    }
+}
@@ -0,0 +1,462 @@
+//! Lightweight SQL inspection helpers
+//!
+//! These do not parse SQL, they only look at keywords outside of string literals, comments and
+//! parentheses, which is enough to tell statements apart and spot common mistakes before a query
+//! reaches the server.
+//!
+//! # Example
+//! ```
+//! use questdb::sql::{classify, lint, Lint, LintOptions, StatementKind};
+//!
+//! assert_eq!(classify("select * from readings"), StatementKind::Select);
+//!
+//! let options = LintOptions::new().large_table("readings", "ts");
+//! assert_eq!(
+//!     lint("select * from readings", &options),
+//!     vec![
+//!         Lint::MissingLimit,
+//!         Lint::MissingTimeFilter { table: String::from("readings") },
+//!     ]
+//! );
+//! ```
+
+use crate::{Error, Timestamp};
+use std::fmt::Formatter;
+
+/// Kind of a SQL statement, as determined by its leading keyword
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StatementKind {
+    Select,
+    Show,
+    Explain,
+    Insert,
+    Update,
+    Create,
+    Alter,
+    Drop,
+    Truncate,
+    Rename,
+    Copy,
+    Other,
+}
+
+impl StatementKind {
+    /// Whether the statement can never modify data or schema
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            StatementKind::Select | StatementKind::Show | StatementKind::Explain
+        )
+    }
+
+    fn from_keyword(keyword: &str) -> Self {
+        match keyword.to_ascii_uppercase().as_str() {
+            "SELECT" => StatementKind::Select,
+            "SHOW" => StatementKind::Show,
+            "EXPLAIN" => StatementKind::Explain,
+            "INSERT" => StatementKind::Insert,
+            "UPDATE" => StatementKind::Update,
+            "CREATE" => StatementKind::Create,
+            "ALTER" => StatementKind::Alter,
+            "DROP" => StatementKind::Drop,
+            "TRUNCATE" => StatementKind::Truncate,
+            "RENAME" => StatementKind::Rename,
+            "COPY" => StatementKind::Copy,
+            _ => StatementKind::Other,
+        }
+    }
+}
+
+impl std::fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatementKind::Select => write!(f, "SELECT"),
+            StatementKind::Show => write!(f, "SHOW"),
+            StatementKind::Explain => write!(f, "EXPLAIN"),
+            StatementKind::Insert => write!(f, "INSERT"),
+            StatementKind::Update => write!(f, "UPDATE"),
+            StatementKind::Create => write!(f, "CREATE"),
+            StatementKind::Alter => write!(f, "ALTER"),
+            StatementKind::Drop => write!(f, "DROP"),
+            StatementKind::Truncate => write!(f, "TRUNCATE"),
+            StatementKind::Rename => write!(f, "RENAME"),
+            StatementKind::Copy => write!(f, "COPY"),
+            StatementKind::Other => write!(f, "unknown statement"),
+        }
+    }
+}
+
+/// Splits a query into its words along with their parenthesis depth, skipping comments and
+/// string literals. Parentheses wrapping the whole query are ignored.
+fn words(sql: &str) -> Vec<(usize, &str)> {
+    let bytes = sql.as_bytes();
+    let mut words = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            quote @ (b'\'' | b'"') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'(' => {
+                if !words.is_empty() || depth > 0 {
+                    depth += 1;
+                }
+                i += 1;
+            }
+            b')' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                words.push((depth, &sql[start..i]));
+            }
+            _ => i += 1,
+        }
+    }
+
+    words
+}
+
+/// Words outside of any parentheses
+fn top_level_words(sql: &str) -> Vec<&str> {
+    words(sql)
+        .into_iter()
+        .filter(|(depth, _)| *depth == 0)
+        .map(|(_, w)| w)
+        .collect()
+}
+
+/// Determines the kind of statement from its leading keyword. Common table expressions are
+/// classified by the statement following them.
+pub fn classify(sql: &str) -> StatementKind {
+    let words = top_level_words(sql);
+    let mut words = words.iter();
+
+    match words.next() {
+        Some(w) if w.eq_ignore_ascii_case("WITH") => words
+            .map(|w| StatementKind::from_keyword(w))
+            .find(|k| *k != StatementKind::Other)
+            .unwrap_or(StatementKind::Other),
+        Some(w) => StatementKind::from_keyword(w),
+        None => StatementKind::Other,
+    }
+}
+
+/// Quotes a table or column name so it can be used in a statement whatever characters or
+/// keywords it contains
+///
+/// ```
+/// assert_eq!(questdb::sql::quote_ident("order"), "\"order\"");
+/// ```
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quotes a string literal, doubling any single quotes it contains
+///
+/// ```
+/// assert_eq!(questdb::sql::quote_literal("it's"), "'it''s'");
+/// ```
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Value bound to a `$n` placeholder of a query, see [`bind`]
+///
+/// Converts from the Rust types it stands for, `None` becomes `NULL`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SqlValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Timestamp(Timestamp),
+}
+
+impl std::fmt::Display for SqlValue {
+    /// Writes the value as a SQL literal
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SqlValue::Null => write!(f, "NULL"),
+            SqlValue::Bool(b) => write!(f, "{}", b),
+            SqlValue::Int(i) => write!(f, "{}", i),
+            // NaN and infinity have no literal, QuestDB stores them as null anyway
+            SqlValue::Float(v) if !v.is_finite() => write!(f, "NULL"),
+            SqlValue::Float(v) => write!(f, "{:?}", v),
+            SqlValue::Str(s) => write!(f, "{}", quote_literal(s)),
+            SqlValue::Timestamp(ts) => write!(f, "{}", quote_literal(&ts.to_string())),
+        }
+    }
+}
+
+macro_rules! sql_value_from {
+    ($variant:ident: $($t:ty),*) => {
+        $(impl From<$t> for SqlValue {
+            fn from(value: $t) -> Self {
+                SqlValue::$variant(value.into())
+            }
+        })*
+    };
+}
+
+sql_value_from!(Bool: bool);
+sql_value_from!(Int: i8, i16, i32, i64, u8, u16, u32);
+sql_value_from!(Float: f32, f64);
+sql_value_from!(Str: String, &str, &String);
+sql_value_from!(Timestamp: Timestamp);
+
+impl<T: Into<SqlValue>> From<Option<T>> for SqlValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(SqlValue::Null, Into::into)
+    }
+}
+
+/// Builds the list of values bound to the `$1`, `$2`, ... placeholders of a query
+///
+/// ```
+/// use questdb::{params, sql::SqlValue};
+///
+/// let sensor: Option<i32> = None;
+/// assert_eq!(
+///     params![42, "it's", sensor],
+///     vec![SqlValue::Int(42), SqlValue::Str(String::from("it's")), SqlValue::Null]
+/// );
+/// ```
+#[macro_export]
+macro_rules! params {
+    ($($value:expr),* $(,)?) => {
+        ::std::vec::Vec::<$crate::sql::SqlValue>::from([$($crate::sql::SqlValue::from($value)),*])
+    };
+}
+
+/// Replaces the `$1`, `$2`, ... placeholders of a query with the literals of the values, `$1`
+/// being the first one. Placeholders inside string literals, quoted names and comments are left
+/// alone, placeholders without a value are an error.
+///
+/// ```
+/// use questdb::{params, sql::bind, Timestamp};
+///
+/// let sql = bind(
+///     "select * from readings where sensor = $1 and ts > $2",
+///     &params!["it's", Timestamp::from_micros(0)],
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     sql,
+///     "select * from readings where sensor = 'it''s' and ts > '1970-01-01T00:00:00.000000Z'"
+/// );
+/// ```
+pub fn bind(sql: &str, params: &[SqlValue]) -> Result<String, Error> {
+    let bytes = sql.as_bytes();
+    let mut bound = String::with_capacity(sql.len());
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            quote @ (b'\'' | b'"') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'$' if bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let index: usize = sql[start + 1..i].parse().unwrap_or(usize::MAX);
+                let value = index.checked_sub(1).and_then(|n| params.get(n)).ok_or(
+                    Error::MissingParameter {
+                        index,
+                        given: params.len(),
+                    },
+                )?;
+
+                bound.push_str(&sql[copied..start]);
+                bound.push_str(&value.to_string());
+                copied = i;
+            }
+            _ => i += 1,
+        }
+    }
+
+    bound.push_str(&sql[copied.min(sql.len())..]);
+    Ok(bound)
+}
+
+/// Potential problem found by [`lint`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Lint {
+    /// A SELECT without a LIMIT clause may return an unbounded number of rows
+    MissingLimit,
+    /// A large table is read without filtering on its designated timestamp, scanning every
+    /// partition
+    MissingTimeFilter { table: String },
+}
+
+impl std::fmt::Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lint::MissingLimit => write!(f, "query has no LIMIT clause"),
+            Lint::MissingTimeFilter { table } => {
+                write!(f, "large table '{}' is read without a time filter", table)
+            }
+        }
+    }
+}
+
+/// Configuration for [`lint`]
+#[derive(Clone, Debug, Default)]
+pub struct LintOptions {
+    large_tables: Vec<(String, String)>,
+}
+
+impl LintOptions {
+    /// Creates options without any large table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks `table` as large, queries reading it must filter on `timestamp_column`
+    pub fn large_table(mut self, table: &str, timestamp_column: &str) -> Self {
+        self.large_tables
+            .push((String::from(table), String::from(timestamp_column)));
+        self
+    }
+}
+
+/// Checks a query for common mistakes. Only SELECT statements are inspected, anything else
+/// yields no lints.
+pub fn lint(sql: &str, options: &LintOptions) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    if classify(sql) != StatementKind::Select {
+        return lints;
+    }
+
+    let words = words(sql);
+    let is = |w: &str, keyword: &str| w.eq_ignore_ascii_case(keyword);
+
+    if !words.iter().any(|(depth, w)| *depth == 0 && is(w, "LIMIT")) {
+        lints.push(Lint::MissingLimit);
+    }
+
+    for (table, timestamp) in &options.large_tables {
+        // Every position where the table is read from, at any nesting level
+        let reads = words.windows(2).enumerate().filter(|(_, pair)| {
+            (is(pair[0].1, "FROM") || is(pair[0].1, "JOIN")) && is(pair[1].1, table)
+        });
+
+        for (at, pair) in reads {
+            let depth = pair[0].0;
+            // The WHERE clause belonging to the same SELECT, up to the end of its scope
+            let filtered = words[at..]
+                .iter()
+                .take_while(|(d, _)| *d >= depth)
+                .skip_while(|(d, w)| !(*d == depth && is(w, "WHERE")))
+                .any(|(_, w)| is(w, timestamp));
+
+            if !filtered {
+                lints.push(Lint::MissingTimeFilter {
+                    table: table.clone(),
+                });
+                break;
+            }
+        }
+    }
+
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("select * from readings"), StatementKind::Select);
+        assert_eq!(classify("  -- latest\n(SELECT 1)"), StatementKind::Select);
+        assert_eq!(classify("/* x */ show tables"), StatementKind::Show);
+        assert_eq!(
+            classify("with a as (select * from t) insert into b select * from a"),
+            StatementKind::Insert
+        );
+        assert_eq!(classify("drop table 'select'"), StatementKind::Drop);
+        assert_eq!(classify("vacuum table readings"), StatementKind::Other);
+        assert!(!classify("").is_read_only());
+    }
+
+    #[test]
+    fn test_bind() {
+        assert_eq!(
+            bind(
+                "select '$1', \"$1\" from t -- $1\nwhere a = $1 and b = $2 and c = $10",
+                &params![1.5, f64::NAN, 3, 4, 5, 6, 7, 8, 9, true]
+            )
+            .unwrap(),
+            "select '$1', \"$1\" from t -- $1\nwhere a = 1.5 and b = NULL and c = true"
+        );
+        assert_eq!(
+            bind("select $1", &[]).unwrap_err().to_string(),
+            "Query refers to $1 but 0 parameters were given"
+        );
+        assert!(bind("select $0", &params![1]).is_err());
+    }
+
+    #[test]
+    fn test_lint_time_filter() {
+        let options = LintOptions::new().large_table("readings", "ts");
+        assert!(lint(
+            "select * from readings where ts in '2023-01-01' limit 10",
+            &options
+        )
+        .is_empty());
+        assert_eq!(
+            lint(
+                "select * from (select * from readings) where ts > now() limit 10",
+                &options
+            ),
+            vec![Lint::MissingTimeFilter {
+                table: String::from("readings")
+            }]
+        );
+        assert_eq!(lint("insert into readings values(1)", &options), vec![]);
+    }
+}
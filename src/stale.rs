@@ -0,0 +1,79 @@
+use crate::platform::Instant;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Last successful response of every read-only query, served in place of an error while the
+/// server is unavailable, see [`QuestDBBuilder::serve_stale`](crate::QuestDBBuilder::serve_stale)
+#[derive(Debug)]
+pub(crate) struct StaleCache {
+    max_staleness: Duration,
+    /// Responses by the query parameters they were requested with
+    results: Mutex<HashMap<String, (Instant, Value)>>,
+}
+
+impl StaleCache {
+    pub(crate) fn new(max_staleness: Duration) -> Self {
+        StaleCache {
+            max_staleness,
+            results: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Remembers a successful response, dropping responses too old to be served
+    pub(crate) fn put(&self, key: &str, result: &Value) {
+        self.put_at(key, result, Instant::now());
+    }
+
+    fn put_at(&self, key: &str, result: &Value, now: Instant) {
+        let mut results = self.results.lock().unwrap();
+        results.retain(|_, (at, _)| now.duration_since(*at) <= self.max_staleness);
+        results.insert(String::from(key), (now, result.clone()));
+    }
+
+    /// Last response along with its age, unless it is older than the maximum staleness
+    pub(crate) fn get(&self, key: &str) -> Option<(Value, Duration)> {
+        self.get_at(key, Instant::now())
+    }
+
+    fn get_at(&self, key: &str, now: Instant) -> Option<(Value, Duration)> {
+        let results = self.results.lock().unwrap();
+        let (at, result) = results.get(key)?;
+        let age = now.duration_since(*at);
+        (age <= self.max_staleness).then(|| (result.clone(), age))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaleCache;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_max_staleness() {
+        let cache = StaleCache::new(Duration::from_secs(60));
+        let start = Instant::now();
+        cache.put_at("query=select 1", &json!({"dataset": [[1]]}), start);
+
+        let later = start + Duration::from_secs(30);
+        assert_eq!(
+            cache.get_at("query=select 1", later),
+            Some((json!({"dataset": [[1]]}), Duration::from_secs(30)))
+        );
+        assert_eq!(cache.get_at("query=select 2", later), None);
+        assert_eq!(
+            cache.get_at("query=select 1", start + Duration::from_secs(61)),
+            None
+        );
+
+        // Expired responses are dropped when another one is stored
+        cache.put_at(
+            "query=select 2",
+            &json!({}),
+            start + Duration::from_secs(61),
+        );
+        assert_eq!(cache.results.lock().unwrap().len(), 1);
+    }
+}
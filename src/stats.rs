@@ -0,0 +1,114 @@
+use crate::platform::Instant;
+use serde::Deserialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Execution statistics of a single operation
+#[derive(Clone, Debug, Default)]
+pub struct QueryStats {
+    /// Size of the request url and body
+    pub request_bytes: u64,
+    /// Size of the response body
+    pub response_bytes: u64,
+    /// Timings reported by the server, only available for queries run with
+    /// [`ExecOptions::timings`](crate::ExecOptions::timings)
+    pub server_timings: Option<QueryTimings>,
+    /// Number of requests sent, including retries and hedged requests
+    pub attempts: u32,
+    /// Time from the call until the response was read completely
+    pub total_duration: Duration,
+    /// Age of the result when the server was unavailable and an earlier result was returned
+    /// instead, see [`QuestDBBuilder::serve_stale`](crate::QuestDBBuilder::serve_stale)
+    pub stale: Option<Duration>,
+}
+
+/// Time spent by the server on the phases of a query
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct QueryTimings {
+    /// Time spent compiling the query
+    #[serde(deserialize_with = "nanos")]
+    pub compiler: Duration,
+    /// Time spent executing the query and serializing the rows
+    #[serde(deserialize_with = "nanos")]
+    pub execute: Duration,
+    /// Time spent counting the rows
+    #[serde(deserialize_with = "nanos")]
+    pub count: Duration,
+}
+
+fn nanos<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_nanos)
+}
+
+/// Collects the statistics of an operation while it runs
+pub(crate) struct Tracker {
+    started: Instant,
+    attempts: AtomicU32,
+}
+
+impl Tracker {
+    pub(crate) fn start() -> Self {
+        Tracker {
+            started: Instant::now(),
+            attempts: AtomicU32::new(0),
+        }
+    }
+
+    /// Records that a request was sent
+    pub(crate) fn attempt(&self) {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn finish(&self, request_bytes: usize, response_bytes: usize) -> QueryStats {
+        QueryStats {
+            request_bytes: request_bytes as u64,
+            response_bytes: response_bytes as u64,
+            server_timings: None,
+            attempts: self.attempts.load(Ordering::Relaxed),
+            total_duration: self.started.elapsed(),
+            stale: None,
+        }
+    }
+}
+
+/// Operation that took longer than the configured threshold, see
+/// [`QuestDBBuilder::slow_query`](crate::QuestDBBuilder::slow_query)
+#[derive(Clone, Debug)]
+pub struct SlowQuery {
+    /// Endpoint that was called: `/exec`, `/imp` or `/exp`
+    pub endpoint: &'static str,
+    /// SQL text of queries and exports, name of the table for imports
+    pub query: String,
+    /// Statistics of the operation
+    pub stats: QueryStats,
+}
+
+/// Callback invoked with every slow operation
+pub(crate) struct SlowQueryHook {
+    pub(crate) threshold: Duration,
+    pub(crate) callback: Arc<dyn Fn(&SlowQuery) + Send + Sync>,
+}
+
+impl SlowQueryHook {
+    /// Reports the operation if it exceeded the threshold, fast operations cost a comparison
+    pub(crate) fn observe(&self, endpoint: &'static str, query: &str, stats: &QueryStats) {
+        if stats.total_duration >= self.threshold {
+            (self.callback)(&SlowQuery {
+                endpoint,
+                query: String::from(query),
+                stats: stats.clone(),
+            });
+        }
+    }
+}
+
+impl fmt::Debug for SlowQueryHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlowQueryHook")
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
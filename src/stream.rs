@@ -0,0 +1,292 @@
+use crate::error::SQLError;
+use crate::limit::Permit;
+use crate::options::ExecOptions;
+use crate::platform;
+use crate::row::{self, ColumnMeta, Dataset};
+use crate::stats::Tracker;
+use crate::{sql, Error, QuestDB};
+use futures_util::stream::{self, Stream};
+use reqwest::Response;
+use serde::de::{DeserializeOwned, Error as _};
+use serde_json::Value;
+
+/// Where the parser is in the response
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    /// Before the rows, the metadata is still arriving
+    Header,
+    /// Within the array of rows
+    Rows,
+    /// After the last row
+    Done,
+}
+
+/// Picks the rows out of an /exec response while its body arrives
+///
+/// The response is an object with the rows under `dataset`, each row an array of values.
+/// Everything before the rows, which includes the column metadata, is parsed once the rows
+/// start. Each row is parsed once it arrived completely.
+struct RowParser {
+    buffer: Vec<u8>,
+    /// Bytes of `buffer` already parsed
+    pos: usize,
+    state: State,
+    columns: Vec<ColumnMeta>,
+}
+
+impl RowParser {
+    fn new() -> Self {
+        RowParser {
+            buffer: Vec::new(),
+            pos: 0,
+            state: State::Header,
+            columns: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        // Rows already returned aren't kept
+        if self.state != State::Header {
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+        }
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Next complete row, `None` when more of the body is needed or there are no more rows
+    fn next_row(&mut self) -> Result<Option<Vec<Value>>, Error> {
+        if self.state == State::Header {
+            const DATASET: &[u8] = b"\"dataset\":[";
+            let Some(start) = self
+                .buffer
+                .windows(DATASET.len())
+                .position(|w| w == DATASET)
+            else {
+                return Ok(None);
+            };
+            let rows = start + DATASET.len();
+            // Closing the empty dataset makes the start of the response a valid object
+            let mut header = self.buffer[..rows].to_vec();
+            header.extend_from_slice(b"]}");
+            let header: Dataset = serde_json::from_slice(&header)?;
+            self.columns = header.columns;
+            self.pos = rows;
+            self.state = State::Rows;
+        }
+        if self.state == State::Done {
+            return Ok(None);
+        }
+
+        let separators = self.buffer[self.pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_whitespace() || **b == b',')
+            .count();
+        self.pos += separators;
+        match self.buffer.get(self.pos) {
+            None => Ok(None),
+            Some(b']') => {
+                self.state = State::Done;
+                Ok(None)
+            }
+            Some(_) => match value_len(&self.buffer[self.pos..]) {
+                Some(len) => {
+                    let row = serde_json::from_slice(&self.buffer[self.pos..self.pos + len])?;
+                    self.pos += len;
+                    Ok(Some(row))
+                }
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Checks the end of the body, a response without rows is either an error or a statement
+    /// that doesn't return rows
+    fn finish(&self) -> Result<(), Error> {
+        match self.state {
+            State::Header => {
+                let res: Value = serde_json::from_slice(&self.buffer)?;
+                match res.get("error") {
+                    Some(_) => Err(Error::SQLError(serde_json::from_value::<SQLError>(res)?)),
+                    None => Ok(()),
+                }
+            }
+            State::Rows => Err(Error::DeserializeError(serde_json::Error::custom(
+                "response ended within the rows",
+            ))),
+            State::Done => Ok(()),
+        }
+    }
+}
+
+/// Length of the array or object at the start of `bytes`, `None` while it is incomplete
+fn value_len(bytes: &[u8]) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &b) in bytes.iter().enumerate() {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Everything a row stream needs between two rows
+struct RowStream<'a> {
+    res: Response,
+    parser: RowParser,
+    query: String,
+    tracker: Tracker,
+    request_bytes: usize,
+    received: usize,
+    /// Rows returned so far
+    rows: usize,
+    _permit: Permit<'a>,
+}
+
+impl QuestDB {
+    /// Executes the query and yields the rows while the response arrives, so a large result
+    /// never has to fit in memory. Rows map onto `T` as with [`exec_with`](Self::exec_with).
+    ///
+    /// The request is sent, and retried, before this returns. A [timeout](ExecOptions::timeout),
+    /// or the total timeout of the connection, only bounds this part. Failures while reading the
+    /// rows end the stream with an error. The stream holds its place within
+    /// [`QuestDBBuilder::max_in_flight`](crate::QuestDBBuilder::max_in_flight) until it is
+    /// dropped.
+    ///
+    /// # Example
+    /// ```no-test
+    /// use futures_util::{pin_mut, TryStreamExt};
+    ///
+    /// let rows = connection
+    ///     .exec_stream::<Reading>("select * from readings", &ExecOptions::new())
+    ///     .await?;
+    /// pin_mut!(rows);
+    /// while let Some(reading) = rows.try_next().await? {
+    ///     println!("{:?}", reading);
+    /// }
+    /// ```
+    pub async fn exec_stream<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        options: &ExecOptions,
+    ) -> Result<impl Stream<Item = Result<T, Error>> + '_, Error> {
+        let tracker = Tracker::start();
+        self.check_read_only(query)?;
+        let options = &options.or(&self.inner.default_options);
+        let idempotent = sql::classify(query).is_read_only();
+        let params = crate::api::encode_params(query, options);
+
+        let permit = self.inner.limits.acquire(options.priority).await;
+        let request = self.exec_request(&tracker, idempotent, &params, options);
+        let (res, request_bytes) = match self.inner.retry.operation_timeout(options.timeout) {
+            Some(timeout) => platform::timeout(timeout, request)
+                .await
+                .unwrap_or((Err(Error::Timeout(timeout)), 0)),
+            None => request.await,
+        };
+        let res = res?;
+
+        let state = RowStream {
+            res,
+            parser: RowParser::new(),
+            query: String::from(query),
+            tracker,
+            request_bytes,
+            received: 0,
+            rows: 0,
+            _permit: permit,
+        };
+        Ok(stream::try_unfold(state, move |mut state| async move {
+            loop {
+                if let Some(values) = state.parser.next_row()? {
+                    let row = row::from_row(state.rows, &state.parser.columns, values)?;
+                    state.rows += 1;
+                    return Ok(Some((row, state)));
+                }
+                match self.inner.retry.chunk(&mut state.res).await? {
+                    Some(chunk) => {
+                        state.received += chunk.len();
+                        state.parser.push(&chunk);
+                    }
+                    None => break,
+                }
+            }
+
+            let stats = state.tracker.finish(state.request_bytes, state.received);
+            self.observe("/exec", &state.query, &stats);
+            match state.parser.finish() {
+                Ok(()) => {
+                    self.inner.metadata.executed(&state.query);
+                    Ok(None)
+                }
+                Err(e) => {
+                    self.inner.metadata.failed(&e);
+                    Err(e)
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RowParser;
+    use serde_json::json;
+
+    const RESPONSE: &str = r#"{"query":"select \"dataset\":[ from x","columns":[{"name":"id","type":"INT"},{"name":"note","type":"STRING"}],"timestamp":-1,"dataset":[[1,"a],[\"b"],[2,null]],"count":2}"#;
+
+    #[test]
+    fn test_row_parser() {
+        // Rows are found however the body is split
+        for chunk_size in 1..RESPONSE.len() {
+            let mut parser = RowParser::new();
+            let mut rows = Vec::new();
+            for chunk in RESPONSE.as_bytes().chunks(chunk_size) {
+                parser.push(chunk);
+                while let Some(row) = parser.next_row().unwrap() {
+                    rows.push(row);
+                }
+            }
+            parser.finish().unwrap();
+            assert_eq!(parser.columns.len(), 2);
+            assert_eq!(
+                rows,
+                vec![
+                    vec![json!(1), json!("a],[\"b")],
+                    vec![json!(2), json!(null)]
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_row_parser_errors() {
+        let mut parser = RowParser::new();
+        parser.push(br#"{"query":"selec","error":"unexpected token","position":0}"#);
+        assert!(parser.next_row().unwrap().is_none());
+        assert!(matches!(parser.finish(), Err(crate::Error::SQLError(_))));
+
+        let mut parser = RowParser::new();
+        parser.push(&RESPONSE.as_bytes()[..150]);
+        while parser.next_row().unwrap().is_some() {}
+        assert!(parser.finish().is_err());
+    }
+}
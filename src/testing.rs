@@ -0,0 +1,197 @@
+//! Assertions for integration tests running against a QuestDB server
+//!
+//! The helpers run a query and compare its rows to the expected ones, panicking with a line per
+//! differing row so failures can be read without printing whole results.
+//!
+//! # Example
+//! ```no-test
+//! use questdb::testing::{assert_query_yields, assert_table_eq};
+//!
+//! assert_table_eq(
+//!     &connection,
+//!     "readings",
+//!     "sensor,temp
+//!      a,16.4
+//!      b,17.1",
+//! )
+//! .await;
+//!
+//! assert_query_yields(
+//!     &connection,
+//!     "select sensor, max(temp) temp from readings order by sensor",
+//!     &[Reading { sensor: "a".into(), temp: 16.4 }],
+//! )
+//! .await;
+//! ```
+
+use crate::sql::quote_ident;
+use crate::{ExecOptions, QuestDB};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+
+/// Runs the query and asserts that it returns exactly the `expected` rows, in order
+///
+/// # Panics
+/// When the query fails or returns different rows.
+pub async fn assert_query_yields<T>(connection: &QuestDB, query: &str, expected: &[T])
+where
+    T: DeserializeOwned + PartialEq + Debug,
+{
+    let actual: Vec<T> = connection
+        .exec_with(query, &ExecOptions::new())
+        .await
+        .unwrap_or_else(|e| panic!("query '{}' failed: {}", query, e));
+    check(query, expected, &actual);
+}
+
+/// Runs the query and asserts that it returns the rows of the CSV fixture, in order
+///
+/// The first line of the fixture names the columns to compare, other columns of the result
+/// are ignored. Values are compared as the server exports them, e.g. timestamps as
+/// `2023-01-01T00:00:00.000000Z`. Leading whitespace of every line is ignored so fixtures can be
+/// indented along with the test.
+///
+/// # Panics
+/// When the query fails, lacks a column of the fixture or returns different rows.
+pub async fn assert_query_csv(connection: &QuestDB, query: &str, csv: &str) {
+    let mut output = Vec::new();
+    connection
+        .exp(query, None, &mut output)
+        .await
+        .unwrap_or_else(|e| panic!("query '{}' failed: {}", query, e));
+    let output = String::from_utf8_lossy(&output);
+
+    let mut fixture = parse_csv(csv).into_iter();
+    let header = fixture.next().unwrap_or_default();
+    let expected: Vec<Vec<String>> = fixture.collect();
+
+    let mut result = parse_csv(&output).into_iter();
+    let columns = result.next().unwrap_or_default();
+    let positions: Vec<usize> = header
+        .iter()
+        .map(|name| {
+            columns
+                .iter()
+                .position(|c| c == name)
+                .unwrap_or_else(|| panic!("query '{}' has no column '{}'", query, name))
+        })
+        .collect();
+    let actual: Vec<Vec<String>> = result
+        .map(|row| {
+            positions
+                .iter()
+                .map(|&i| row.get(i).cloned().unwrap_or_default())
+                .collect()
+        })
+        .collect();
+
+    check(query, &expected, &actual);
+}
+
+/// Asserts that the table holds exactly the rows of the CSV fixture, in the order of its
+/// designated timestamp, see [`assert_query_csv`] for the format
+pub async fn assert_table_eq(connection: &QuestDB, table: &str, csv: &str) {
+    let query = format!("select * from {}", quote_ident(table));
+    assert_query_csv(connection, &query, csv).await;
+}
+
+fn check<T: PartialEq + Debug>(query: &str, expected: &[T], actual: &[T]) {
+    let differences = diff(expected, actual);
+    if !differences.is_empty() {
+        panic!(
+            "query '{}' returned {} rows, expected {}:\n{}",
+            query,
+            actual.len(),
+            expected.len(),
+            differences.join("\n")
+        );
+    }
+}
+
+/// One line per row that differs
+fn diff<T: PartialEq + Debug>(expected: &[T], actual: &[T]) -> Vec<String> {
+    let mut differences = Vec::new();
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e != a => {
+                differences.push(format!("  row {}: expected {:?}, got {:?}", i + 1, e, a))
+            }
+            (Some(e), None) => differences.push(format!("  row {}: missing {:?}", i + 1, e)),
+            (None, Some(a)) => differences.push(format!("  row {}: unexpected {:?}", i + 1, a)),
+            _ => {}
+        }
+    }
+    differences
+}
+
+/// Splits CSV into records of unquoted values, skipping blank lines
+fn parse_csv(csv: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut line_start = true;
+
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                value.push('"');
+            }
+            '"' => quoted = !quoted,
+            c if quoted => value.push(c),
+            ',' => record.push(std::mem::take(&mut value)),
+            '\n' => {
+                if !line_start {
+                    record.push(std::mem::take(&mut value));
+                    records.push(std::mem::take(&mut record));
+                }
+                line_start = true;
+                continue;
+            }
+            '\r' => {}
+            c if c.is_whitespace() && line_start => continue,
+            c => value.push(c),
+        }
+        line_start = false;
+    }
+    if !line_start {
+        record.push(value);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, parse_csv};
+
+    #[test]
+    fn test_parse_csv() {
+        let records = parse_csv(
+            "sensor,note\n\
+             \x20   a,\"multi\nline, \"\"quoted\"\"\"\n\
+             \n\
+             \x20   b,\r\n",
+        );
+        assert_eq!(
+            records,
+            [
+                vec!["sensor", "note"],
+                vec!["a", "multi\nline, \"quoted\""],
+                vec!["b", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        assert!(diff(&[1, 2], &[1, 2]).is_empty());
+        assert_eq!(
+            diff(&[1, 2, 3], &[1, 5]),
+            ["  row 2: expected 2, got 5", "  row 3: missing 3"]
+        );
+        assert_eq!(diff(&[1], &[1, 4]), ["  row 2: unexpected 4"]);
+    }
+}
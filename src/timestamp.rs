@@ -0,0 +1,302 @@
+use crate::platform;
+use crate::Error;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+pub(crate) const MICROS_PER_SEC: i64 = 1_000_000;
+pub(crate) const MICROS_PER_DAY: i64 = 86_400 * MICROS_PER_SEC;
+
+/// Point in time with microsecond precision, the resolution of QuestDB's `TIMESTAMP` type
+///
+/// Parses from and prints as the ISO 8601 representation QuestDB uses in query results, e.g.
+/// `2019-10-17T00:00:00.100000Z`, and converts from and to `chrono` and `time` types when the
+/// features of the same name are enabled.
+///
+/// # Example
+/// ```
+/// use questdb::Timestamp;
+///
+/// let ts: Timestamp = "2019-10-17T00:00:00.100000Z".parse().unwrap();
+/// assert_eq!(ts.as_micros(), 1_571_270_400_100_000);
+/// assert_eq!(ts.to_string(), "2019-10-17T00:00:00.100000Z");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    /// Creates a timestamp from microseconds since the unix epoch
+    pub fn from_micros(micros: i64) -> Self {
+        Timestamp(micros)
+    }
+
+    /// Microseconds since the unix epoch
+    pub fn as_micros(&self) -> i64 {
+        self.0
+    }
+
+    /// Current time
+    pub fn now() -> Self {
+        Timestamp(platform::unix_now().as_micros() as i64)
+    }
+
+    /// Parses an ISO 8601 date (`2019-10-17`) or date and time with an optional fraction of a
+    /// second and offset (`2019-10-17T00:00:00.100000Z`). Digits beyond microseconds are
+    /// truncated.
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        parse_iso(s).ok_or_else(|| Error::InvalidTimestamp(String::from(s)))
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Timestamp::parse(s)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.0.div_euclid(MICROS_PER_DAY);
+        let micros = self.0.rem_euclid(MICROS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        let secs = micros / MICROS_PER_SEC;
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+            year,
+            month,
+            day,
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            micros % MICROS_PER_SEC
+        )
+    }
+}
+
+impl From<Timestamp> for i64 {
+    fn from(ts: Timestamp) -> i64 {
+        ts.0
+    }
+}
+
+impl From<i64> for Timestamp {
+    fn from(micros: i64) -> Timestamp {
+        Timestamp(micros)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    /// Accepts the ISO 8601 strings returned by /exec as well as microseconds since the epoch
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TimestampVisitor;
+
+        impl Visitor<'_> for TimestampVisitor {
+            type Value = Timestamp;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an ISO 8601 timestamp or microseconds since the epoch")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Timestamp, E> {
+                parse_iso(v).ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Timestamp, E> {
+                Ok(Timestamp(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Timestamp, E> {
+                i64::try_from(v)
+                    .map(Timestamp)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+            }
+        }
+
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+    fn from(dt: chrono::DateTime<chrono::Utc>) -> Self {
+        Timestamp(dt.timestamp_micros())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for chrono::DateTime<chrono::Utc> {
+    fn from(ts: Timestamp) -> Self {
+        // Every i64 of microseconds is within chrono's range
+        chrono::DateTime::from_timestamp_micros(ts.0).unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<::time::OffsetDateTime> for Timestamp {
+    fn from(dt: ::time::OffsetDateTime) -> Self {
+        Timestamp((dt.unix_timestamp_nanos() / 1000) as i64)
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<Timestamp> for ::time::OffsetDateTime {
+    /// Saturates at the limits of `time`'s range
+    fn from(ts: Timestamp) -> Self {
+        ::time::OffsetDateTime::from_unix_timestamp_nanos(ts.0 as i128 * 1000).unwrap_or_else(
+            |_| match ts.0 < 0 {
+                true => ::time::PrimitiveDateTime::MIN.assume_utc(),
+                false => ::time::PrimitiveDateTime::MAX.assume_utc(),
+            },
+        )
+    }
+}
+
+/// Days since 1970-01-01 of a date of the proleptic Gregorian calendar
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of [`days_from_civil`]
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Reads exactly `n` digits
+fn digits(s: &mut &[u8], n: usize) -> Option<i64> {
+    if s.len() < n || !s[..n].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let value = s[..n]
+        .iter()
+        .fold(0, |acc, d| acc * 10 + i64::from(d - b'0'));
+    *s = &s[n..];
+    Some(value)
+}
+
+/// Consumes `c` if it is the next character
+fn expect(s: &mut &[u8], c: u8) -> Option<()> {
+    match s.first() {
+        Some(&first) if first == c => {
+            *s = &s[1..];
+            Some(())
+        }
+        _ => None,
+    }
+}
+
+fn parse_iso(s: &str) -> Option<Timestamp> {
+    let mut s = s.trim().as_bytes();
+
+    let year = digits(&mut s, 4)?;
+    expect(&mut s, b'-')?;
+    let month = digits(&mut s, 2)?;
+    expect(&mut s, b'-')?;
+    let day = digits(&mut s, 2)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut micros = days_from_civil(year, month, day) * MICROS_PER_DAY;
+
+    if s.is_empty() {
+        return Some(Timestamp(micros));
+    }
+    expect(&mut s, b'T').or_else(|| expect(&mut s, b' '))?;
+
+    let hour = digits(&mut s, 2)?;
+    expect(&mut s, b':')?;
+    let minute = digits(&mut s, 2)?;
+    let second = match expect(&mut s, b':') {
+        Some(()) => digits(&mut s, 2)?,
+        None => 0,
+    };
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    micros += (hour * 3600 + minute * 60 + second) * MICROS_PER_SEC;
+
+    if expect(&mut s, b'.').is_some() {
+        let len = s.iter().take_while(|c| c.is_ascii_digit()).count();
+        if len == 0 {
+            return None;
+        }
+        let fraction = digits(&mut s, len.min(6))?;
+        micros += fraction * 10i64.pow(6 - len.min(6) as u32);
+        // Sub-microsecond digits are truncated
+        digits(&mut s, len.saturating_sub(6));
+    }
+
+    match s.first() {
+        None => {}
+        Some(b'Z') if s.len() == 1 => {}
+        Some(&sign @ (b'+' | b'-')) => {
+            s = &s[1..];
+            let hours = digits(&mut s, 2)?;
+            expect(&mut s, b':');
+            let minutes = digits(&mut s, 2)?;
+            if !s.is_empty() {
+                return None;
+            }
+            let offset = (hours * 3600 + minutes * 60) * MICROS_PER_SEC;
+            micros += if sign == b'+' { -offset } else { offset };
+        }
+        _ => return None,
+    }
+
+    Some(Timestamp(micros))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iso_round_trip() {
+        for s in [
+            "1970-01-01T00:00:00.000000Z",
+            "2019-10-17T00:00:00.100000Z",
+            "1969-12-31T23:59:59.999999Z",
+            "2400-02-29T12:30:01.000001Z",
+        ] {
+            assert_eq!(Timestamp::parse(s).unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_parse_variants() {
+        let ts = Timestamp::from_micros(1_571_270_400_000_000);
+        assert_eq!(Timestamp::parse("2019-10-17").unwrap(), ts);
+        assert_eq!(Timestamp::parse("2019-10-17 00:00").unwrap(), ts);
+        assert_eq!(Timestamp::parse("2019-10-17T02:00:00+02:00").unwrap(), ts);
+        assert_eq!(
+            Timestamp::parse("2019-10-17T00:00:00.000000999Z").unwrap(),
+            ts
+        );
+        assert!(Timestamp::parse("2019-13-17").is_err());
+        assert!(Timestamp::parse("2019-10-17T00:00:00Zjunk").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt::Formatter;
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Atomicity {
     Strict,
     Relaxed,
@@ -14,8 +15,7 @@ impl std::fmt::Display for Atomicity {
     }
 }
 
-#[derive(Copy, Clone)]
-#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Schema {
     Boolean,
     Byte,
@@ -53,3 +53,27 @@ impl std::fmt::Display for Schema {
         }
     }
 }
+
+impl Schema {
+    /// Type with the given name, case insensitive
+    pub(crate) fn from_name(name: &str) -> Option<Schema> {
+        let schema = match name.to_ascii_uppercase().as_str() {
+            "BOOLEAN" => Schema::Boolean,
+            "BYTE" => Schema::Byte,
+            "SHORT" => Schema::Short,
+            "CHAR" => Schema::Char,
+            "INT" => Schema::Int,
+            "FLOAT" => Schema::Float,
+            "SYMBOL" => Schema::Symbol,
+            "STRING" => Schema::String,
+            "LONG" => Schema::Long,
+            "DATE" => Schema::Date,
+            "TIMESTAMP" => Schema::Timestamp,
+            "DOUBLE" => Schema::Double,
+            "BINARY" => Schema::Binary,
+            "LONG256" => Schema::Long256,
+            _ => return None,
+        };
+        Some(schema)
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{Error, QuestDB};
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the server, see [`QuestDB::server_version`]
+///
+/// Parsed from the build information QuestDB reports, such as
+/// `Build Information: QuestDB 7.3.10, JDK 17.0.7, Commit Hash 9ad4a4bd6e8a`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// Commit the server was built from, when reported
+    pub commit: Option<String>,
+    /// Build information as reported by the server
+    pub build: String,
+}
+
+impl ServerVersion {
+    /// Whether the server is at least the given version, e.g. to check the server supports a
+    /// feature before using it
+    pub fn at_least(&self, major: u32, minor: u32, patch: u32) -> bool {
+        (self.major, self.minor, self.patch) >= (major, minor, patch)
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(commit) = &self.commit {
+            write!(f, " ({})", commit)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ServerVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidVersion(String::from(s));
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+
+        // The version is the first number after the product name, which may be followed by the
+        // edition, e.g. `QuestDB Enterprise 2.1.0`
+        let product = parts
+            .iter()
+            .find_map(|p| p.split_once("QuestDB").map(|(_, rest)| rest))
+            .ok_or_else(invalid)?;
+        let version = product
+            .split_whitespace()
+            .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+            .ok_or_else(invalid)?;
+        // Pre-release suffixes, e.g. `-SNAPSHOT`, are ignored
+        let mut numbers = version
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|n| n.parse::<u32>().map_err(|_| invalid()));
+
+        let major = numbers.next().ok_or_else(invalid)??;
+        let minor = numbers.next().transpose()?.unwrap_or_default();
+        let patch = numbers.next().transpose()?.unwrap_or_default();
+        let commit = parts
+            .iter()
+            .find_map(|p| p.strip_prefix("Commit Hash"))
+            .map(|hash| String::from(hash.trim()))
+            .filter(|hash| !hash.is_empty());
+
+        Ok(ServerVersion {
+            major,
+            minor,
+            patch,
+            commit,
+            build: String::from(s.trim()),
+        })
+    }
+}
+
+impl QuestDB {
+    /// Version of the server, for logging it or checking the server is recent enough
+    ///
+    /// # Example
+    /// ```no-test
+    /// let version = connection.server_version().await?;
+    /// println!("Connected to QuestDB {}", version);
+    /// assert!(version.at_least(7, 3, 0), "QuestDB 7.3 or later is required");
+    /// ```
+    pub async fn server_version(&self) -> Result<ServerVersion, Error> {
+        self.fetch_scalar::<String>("select build").await?.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ServerVersion;
+    use crate::Error;
+
+    #[test]
+    fn test_parse_version() {
+        let version: ServerVersion =
+            "Build Information: QuestDB 7.3.10, JDK 17.0.7, Commit Hash 9ad4a4bd6e8a"
+                .parse()
+                .unwrap();
+        assert_eq!((version.major, version.minor, version.patch), (7, 3, 10));
+        assert_eq!(version.commit.as_deref(), Some("9ad4a4bd6e8a"));
+        assert_eq!(version.to_string(), "7.3.10 (9ad4a4bd6e8a)");
+        assert!(version.at_least(7, 3, 0));
+        assert!(!version.at_least(8, 0, 0));
+
+        let version: ServerVersion = "Build Information: QuestDB Enterprise 2.1-SNAPSHOT, JDK 17"
+            .parse()
+            .unwrap();
+        assert_eq!((version.major, version.minor, version.patch), (2, 1, 0));
+        assert_eq!(version.commit, None);
+
+        assert!(matches!(
+            "PostgreSQL 12.3".parse::<ServerVersion>(),
+            Err(Error::InvalidVersion(_))
+        ));
+    }
+}